@@ -4,7 +4,7 @@ use std::sync::{Arc, Mutex};
 use actix_web::{App, HttpServer, web};
 use config::{Config, File};
 use serde::Deserialize;
-use tracing::info;
+use tracing::{info, warn};
 use tracing_subscriber::fmt;
 
 use binance_sdk::config::ConfigurationRestApi;
@@ -12,23 +12,92 @@ use binance_sdk::derivatives_trading_usds_futures;
 use binance_sdk::derivatives_trading_usds_futures::DerivativesTradingUsdsFuturesRestApi;
 use binance_sdk::spot;
 use binance_sdk::spot::SpotRestApi;
+use binance_sdk::sub_account;
+use binance_sdk::sub_account::SubAccountRestApi;
 
-use crate::config::{AppConfig, load_config};
+use crate::common::exchange_info::ExchangeInfoCache;
+use crate::common::kline_store::KlineStore;
+use crate::common::klines::KlineClient;
+use crate::common::rate_limit::RateLimiter;
+use crate::common::retry::RetryPolicy;
+use crate::common::risk::RiskEngine;
+use crate::common::submissions::OrderSubmissions;
+use crate::config::{AppConfig, AuthConfig, load_config};
 use crate::handler::usds_future as usds_future_handler;
 use crate::handler::spot as sport_handler;
+use crate::handler::sub_account as sub_account_handler;
+use crate::handler::klines as klines_handler;
+use crate::handler::limits as limits_handler;
+use crate::handler::metrics as metrics_handler;
+use crate::handler::portfolio as portfolio_handler;
+use crate::handler::ws as ws_handler;
 use crate::handler::{echo, health_check, index};
+use crate::stream::account::AccountCache;
+use crate::stream::market::MarketStreams;
+use crate::stream::user_data::UserDataStreams;
+
+// 币安环境, 在 keys.toml 中按 key 配置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Environment {
+    #[default]
+    Production,
+    Testnet,
+}
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Key {
     #[serde(rename = "apiKey", alias = "api_key")]
     pub api_key: String,
     pub secret: String,
+    #[serde(default)]
+    pub environment: Environment,
+    // 自定义 REST 地址 (如本地 mock), 设置后忽略 environment
+    #[serde(default)]
+    pub base_url: Option<String>,
+    // 母账户 key, 只有母账户可以调用 /sub_account 接口
+    #[serde(default)]
+    pub master: bool,
+}
+
+impl Key {
+    /// 正式环境且没有自定义 base_url, 与公开行情客户端访问的是同一个币安
+    pub fn is_production(&self) -> bool {
+        self.environment == Environment::Production && self.base_url.is_none()
+    }
 }
 
+// key 名称到 REST API 客户端的映射
+pub type ClientMap<T> = Arc<Mutex<HashMap<String, T>>>;
+
 // 新增全局的 REST API 客户端类型定义
 pub struct AppState {
-    pub rest_usds_future_clients: Arc<Mutex<HashMap<String, derivatives_trading_usds_futures::rest_api::RestApi>>>,
-    pub rest_spot_clients: Arc<Mutex<HashMap<String, spot::rest_api::RestApi>>>,
+    pub rest_usds_future_clients: ClientMap<derivatives_trading_usds_futures::rest_api::RestApi>,
+    pub rest_spot_clients: ClientMap<spot::rest_api::RestApi>,
+    // 只包含 master = true 的 key
+    pub rest_sub_account_clients: ClientMap<sub_account::rest_api::RestApi>,
+    // 公开行情接口使用的共享客户端
+    pub public_usds_future_client: derivatives_trading_usds_futures::rest_api::RestApi,
+    pub public_spot_client: spot::rest_api::RestApi,
+    // 合约和现货的交易规则缓存, 用于下单前校验
+    pub exchange_info: Arc<ExchangeInfoCache>,
+    // 本地 K 线存储, 未配置 [kline_store] 时为 None
+    pub kline_store: Option<Arc<KlineStore>>,
+    // 按币安权重和下单数限额的本地限流
+    pub rate_limiter: Arc<RateLimiter>,
+    // 临时错误的重试策略和重试统计
+    pub retry: Arc<RetryPolicy>,
+    // 最近的下单请求, 按 client order id 去重
+    pub order_submissions: Arc<OrderSubmissions>,
+    // 按 key 的下单前风控规则
+    pub risk: Arc<RiskEngine>,
+    // U 本位合约用户数据流, 按 key 共享上游连接
+    pub usds_future_user_streams: Arc<UserDataStreams>,
+    // U 本位合约挂单, 持仓和余额缓存, 由用户数据流更新
+    pub usds_future_account_cache: Arc<AccountCache>,
+    // 公开行情流, 按流共享上游连接
+    pub market_streams: Arc<MarketStreams>,
+    pub auth: Arc<AuthConfig>,
 }
 
 fn load_keys() -> Result<HashMap<String, Key>, config::ConfigError> {
@@ -42,35 +111,46 @@ fn load_keys() -> Result<HashMap<String, Key>, config::ConfigError> {
 // 定义 ClientBuilder 特征
 pub trait ClientBuilder {
     type ApiClient; // 关联类型
-    fn build(conf: ConfigurationRestApi) -> Self::ApiClient;
+    fn build(conf: ConfigurationRestApi, environment: Environment) -> Self::ApiClient;
 }
 
 // 为 USDS 期货客户端实现 ClientBuilder 特征
 impl ClientBuilder for DerivativesTradingUsdsFuturesRestApi {
     type ApiClient = derivatives_trading_usds_futures::rest_api::RestApi;
-    fn build(conf: ConfigurationRestApi) -> Self::ApiClient {
-        Self::production(conf)
+    fn build(conf: ConfigurationRestApi, environment: Environment) -> Self::ApiClient {
+        match (&conf.base_path, environment) {
+            (Some(_), _) => Self::from_config(conf),
+            (None, Environment::Production) => Self::production(conf),
+            (None, Environment::Testnet) => Self::testnet(conf),
+        }
     }
 }
 
 // 为现货客户端实现 ClientBuilder 特征
 impl ClientBuilder for SpotRestApi {
     type ApiClient = spot::rest_api::RestApi;
-    fn build(conf: ConfigurationRestApi) -> Self::ApiClient {
-        Self::production(conf)
+    fn build(conf: ConfigurationRestApi, environment: Environment) -> Self::ApiClient {
+        match (&conf.base_path, environment) {
+            (Some(_), _) => Self::from_config(conf),
+            (None, Environment::Production) => Self::production(conf),
+            (None, Environment::Testnet) => Self::testnet(conf),
+        }
     }
 }
 
-// 使用泛型实现 init_rest_clients 函数
-pub fn init_rest_clients<T: ClientBuilder + 'static>(
-    keys: &HashMap<String, Key>,
-    app_config: &AppConfig,
-) -> Result<Arc<Mutex<HashMap<String, T::ApiClient>>>, std::io::Error> {
-    // 初始化一个 HashMap 来存储每个 key 对应的 rest_client
-    let mut rest_clients = HashMap::new();
+// 为子账户客户端实现 ClientBuilder 特征, 子账户接口没有 testnet
+impl ClientBuilder for SubAccountRestApi {
+    type ApiClient = sub_account::rest_api::RestApi;
+    fn build(conf: ConfigurationRestApi, _environment: Environment) -> Self::ApiClient {
+        match &conf.base_path {
+            Some(_) => Self::from_config(conf),
+            None => Self::production(conf),
+        }
+    }
+}
 
-    // 提前处理代理配置
-    let proxy_config = app_config
+fn proxy_config(app_config: &AppConfig) -> Option<binance_sdk::config::ProxyConfig> {
+    app_config
         .proxy
         .as_ref()
         .map(|proxy| binance_sdk::config::ProxyConfig {
@@ -84,56 +164,165 @@ pub fn init_rest_clients<T: ClientBuilder + 'static>(
                     username: auth.username.clone(),
                     password: auth.password.clone(),
                 }),
-        });
+        })
+}
+
+// 网络错误统一由 RetryPolicy 重试, SDK 按 retries - attempt 计算剩余次数, 设为 0 会下溢, 设为 1 即不重试
+const SDK_RETRIES: u32 = 1;
+
+// 不带 key 的客户端, 只用于公开的行情接口, 所有请求共用
+pub fn init_public_client<T: ClientBuilder>(
+    app_config: &AppConfig,
+) -> Result<T::ApiClient, std::io::Error> {
+    let mut builder = ConfigurationRestApi::builder().retries(SDK_RETRIES);
+    if let Some(proxy) = proxy_config(app_config) {
+        builder = builder.proxy(proxy);
+    }
+
+    let rest_conf = builder
+        .build()
+        .map_err(|_| std::io::Error::other("Failed to build REST configuration"))?;
+
+    Ok(T::build(rest_conf, Environment::Production))
+}
+
+// 使用泛型实现 init_rest_clients 函数
+pub fn init_rest_clients<T: ClientBuilder + 'static>(
+    keys: &HashMap<String, Key>,
+    app_config: &AppConfig,
+) -> Result<ClientMap<T::ApiClient>, std::io::Error> {
+    // 初始化一个 HashMap 来存储每个 key 对应的 rest_client
+    let mut rest_clients = HashMap::new();
+
+    // 提前处理代理配置
+    let proxy_config = proxy_config(app_config);
 
     // 遍历每个 key，为其创建对应的 rest_client
     for (key_name, key) in keys.iter() {
         // 构建 REST 配置
         let mut builder = ConfigurationRestApi::builder()
             .api_key(key.api_key.clone())
-            .api_secret(key.secret.clone());
+            .api_secret(key.secret.clone())
+            .retries(SDK_RETRIES);
 
         // 设置代理配置
         if let Some(proxy) = &proxy_config {
             builder = builder.proxy(proxy.clone());
         }
 
+        if let Some(base_url) = &key.base_url {
+            builder = builder.base_path(base_url.clone());
+        }
+
         let rest_conf = match builder.build() {
             Ok(conf) => conf,
             Err(_) => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "Failed to build REST configuration",
-                ));
+                return Err(std::io::Error::other("Failed to build REST configuration"));
             }
         };
 
+        if key.base_url.is_some() || key.environment != Environment::Production {
+            info!(
+                "Key {} uses {}",
+                key_name,
+                key.base_url.as_deref().unwrap_or("testnet")
+            );
+        }
+
         // 根据 ClientBuilder 特征创建客户端
-        // 根据 ClientBuilder 特征创建客户端
-        let client: T::ApiClient = T::build(rest_conf);
+        let client: T::ApiClient = T::build(rest_conf, key.environment);
         rest_clients.insert(key_name.clone(), client);
     }
 
     Ok(Arc::new(Mutex::new(rest_clients)))
 }
 
+// 筛选出母账户 key, 子账户接口没有 testnet, 未配置 base_url 的 testnet 母账户 key 会被跳过
+fn master_keys(keys: &HashMap<String, Key>) -> HashMap<String, Key> {
+    keys.iter()
+        .filter(|(_, key)| key.master)
+        .filter(|(key_name, key)| {
+            let supported = key.base_url.is_some() || key.environment == Environment::Production;
+            if !supported {
+                warn!("Master key {} is on testnet, /sub_account is disabled for it", key_name);
+            }
+            supported
+        })
+        .map(|(key_name, key)| (key_name.clone(), key.clone()))
+        .collect()
+}
+
 pub async fn run() -> std::io::Result<()> {
     // 初始化 tracing 日志收集器
     fmt().with_max_level(tracing::Level::INFO).init();
 
-    let config = load_config().map_err(|e| {
-        std::io::Error::new(std::io::ErrorKind::Other, format!("Config error: {}", e))
-    })?;
+    let config =
+        load_config().map_err(|e| std::io::Error::other(format!("Config error: {}", e)))?;
 
-    let keys = load_keys().map_err(|e| {
-        std::io::Error::new(std::io::ErrorKind::Other, format!("Keys error: {}", e))
-    })?;
+    let keys = load_keys().map_err(|e| std::io::Error::other(format!("Keys error: {}", e)))?;
 
     // 初始化 USDS 期货客户端
     let rest_usds_future_clients =
         init_rest_clients::<DerivativesTradingUsdsFuturesRestApi>(&keys, &config)?;
     // 初始化现货客户端
     let rest_spot_clients = init_rest_clients::<SpotRestApi>(&keys, &config)?;
+    // 初始化子账户客户端, 只为母账户 key 创建
+    let master_keys = master_keys(&keys);
+    let rest_sub_account_clients = init_rest_clients::<SubAccountRestApi>(&master_keys, &config)?;
+    // 初始化公开行情客户端
+    let public_usds_future_client =
+        init_public_client::<DerivativesTradingUsdsFuturesRestApi>(&config)?;
+    let public_spot_client = init_public_client::<SpotRestApi>(&config)?;
+    // 初始化本地限流
+    let rate_limiter = Arc::new(RateLimiter::new(&keys, &config.rate_limit));
+    // 初始化交易规则缓存, 后台定期刷新
+    let exchange_info = Arc::new(ExchangeInfoCache::new(
+        &keys,
+        public_usds_future_client.clone(),
+        public_spot_client.clone(),
+        rate_limiter.clone(),
+    ));
+    tokio::spawn(exchange_info.clone().refresh_periodically());
+    // 初始化本地 K 线存储, 后台增量同步
+    let kline_store = match &config.kline_store {
+        Some(store_config) => {
+            let store = Arc::new(KlineStore::open(store_config, &keys)?);
+            tokio::spawn(store.clone().sync_periodically(
+                rate_limiter.clone(),
+                KlineClient::UsdsFuture(Box::new(public_usds_future_client.clone())),
+                KlineClient::Spot(Box::new(public_spot_client.clone())),
+            ));
+            Some(store)
+        }
+        None => None,
+    };
+    // 初始化重试策略
+    let retry = Arc::new(RetryPolicy::new(&config.retry));
+    let order_submissions = Arc::new(OrderSubmissions::new());
+    let risk = Arc::new(RiskEngine::new(config.risk));
+    // 初始化用户数据流
+    let usds_future_user_streams =
+        Arc::new(UserDataStreams::new(&keys, rate_limiter.clone())?);
+    let usds_future_account_cache = Arc::new(AccountCache::new(
+        usds_future_user_streams.clone(),
+        rate_limiter.clone(),
+    ));
+    // 初始化公开行情流
+    let market_streams = Arc::new(MarketStreams::new()?);
+    // SDK 的 WebSocket 连接不支持代理, 需要直连币安
+    if let Some(proxy) = &config.proxy {
+        warn!(
+            "[proxy] {}:{} only applies to REST requests, WebSocket streams (/ws and the account cache) connect to Binance directly",
+            proxy.host, proxy.port
+        );
+    }
+
+    if config.auth.tokens.is_empty() {
+        warn!(
+            "No [[auth.tokens]] configured, every /usds_future, /spot, /sub_account, /portfolio, /klines, /limits, /metrics and /ws request will be rejected"
+        );
+    }
+    let auth = Arc::new(config.auth);
 
     info!(
         "Starting server at {}:{}",
@@ -146,12 +335,31 @@ pub async fn run() -> std::io::Result<()> {
             .app_data(web::Data::new(AppState {
                 rest_usds_future_clients: rest_usds_future_clients.clone(),
                 rest_spot_clients: rest_spot_clients.clone(),
+                rest_sub_account_clients: rest_sub_account_clients.clone(),
+                public_usds_future_client: public_usds_future_client.clone(),
+                public_spot_client: public_spot_client.clone(),
+                exchange_info: exchange_info.clone(),
+                kline_store: kline_store.clone(),
+                rate_limiter: rate_limiter.clone(),
+                retry: retry.clone(),
+                order_submissions: order_submissions.clone(),
+                risk: risk.clone(),
+                usds_future_user_streams: usds_future_user_streams.clone(),
+                usds_future_account_cache: usds_future_account_cache.clone(),
+                market_streams: market_streams.clone(),
+                auth: auth.clone(),
             }))
             .service(index)
             .service(health_check)
             .service(echo)
             .configure(usds_future_handler::routes)
             .configure(sport_handler::routes)
+            .configure(sub_account_handler::routes)
+            .configure(portfolio_handler::routes)
+            .configure(klines_handler::routes)
+            .configure(limits_handler::routes)
+            .configure(metrics_handler::routes)
+            .configure(ws_handler::routes)
     })
     // .bind((config.server.host, config.server.port))?
     .bind(("::", config.server.port))?
@@ -159,6 +367,52 @@ pub async fn run() -> std::io::Result<()> {
     .await
 }
 
+#[cfg(test)]
+impl AppState {
+    // 测试用: 不包含任何客户端, 只携带给定的鉴权配置
+    pub(crate) fn for_test(auth: AuthConfig) -> Self {
+        let public_usds_future_client = DerivativesTradingUsdsFuturesRestApi::build(
+            ConfigurationRestApi::builder().build().unwrap(),
+            Environment::Production,
+        );
+        let public_spot_client = SpotRestApi::build(
+            ConfigurationRestApi::builder().build().unwrap(),
+            Environment::Production,
+        );
+        let rate_limiter = Arc::new(RateLimiter::new(
+            &HashMap::new(),
+            &crate::config::RateLimitConfig::default(),
+        ));
+        let usds_future_user_streams =
+            Arc::new(UserDataStreams::new(&HashMap::new(), rate_limiter.clone()).unwrap());
+        AppState {
+            rest_usds_future_clients: Arc::new(Mutex::new(HashMap::new())),
+            rest_spot_clients: Arc::new(Mutex::new(HashMap::new())),
+            rest_sub_account_clients: Arc::new(Mutex::new(HashMap::new())),
+            public_usds_future_client: public_usds_future_client.clone(),
+            public_spot_client: public_spot_client.clone(),
+            exchange_info: Arc::new(ExchangeInfoCache::new(
+                &HashMap::new(),
+                public_usds_future_client,
+                public_spot_client,
+                rate_limiter.clone(),
+            )),
+            kline_store: None,
+            rate_limiter: rate_limiter.clone(),
+            retry: Arc::new(RetryPolicy::new(&crate::config::RetryConfig::default())),
+            order_submissions: Arc::new(OrderSubmissions::new()),
+            risk: Arc::new(RiskEngine::new(HashMap::new())),
+            usds_future_user_streams: usds_future_user_streams.clone(),
+            usds_future_account_cache: Arc::new(AccountCache::new(
+                usds_future_user_streams,
+                rate_limiter,
+            )),
+            market_streams: Arc::new(MarketStreams::new().unwrap()),
+            auth: Arc::new(auth),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -171,4 +425,60 @@ mod tests {
         let keys = result.unwrap();
         assert!(!keys.is_empty(), "No keys loaded");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_key_environment() {
+        let keys: HashMap<String, Key> = toml::from_str(
+            r#"
+            [binance1]
+            apiKey = "xxxx"
+            secret = "xxxx"
+
+            [paper1]
+            apiKey = "xxxx"
+            secret = "xxxx"
+            environment = "testnet"
+
+            [mock1]
+            api_key = "xxxx"
+            secret = "xxxx"
+            base_url = "http://127.0.0.1:8080"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(keys["binance1"].environment, Environment::Production);
+        assert_eq!(keys["paper1"].environment, Environment::Testnet);
+        assert_eq!(
+            keys["mock1"].base_url.as_deref(),
+            Some("http://127.0.0.1:8080")
+        );
+    }
+
+    #[test]
+    fn test_master_keys() {
+        let keys: HashMap<String, Key> = toml::from_str(
+            r#"
+            [binance1]
+            apiKey = "xxxx"
+            secret = "xxxx"
+            master = true
+
+            [sub1]
+            apiKey = "xxxx"
+            secret = "xxxx"
+
+            [paper1]
+            apiKey = "xxxx"
+            secret = "xxxx"
+            environment = "testnet"
+            master = true
+            "#,
+        )
+        .unwrap();
+
+        let master_keys = master_keys(&keys);
+        assert_eq!(master_keys.len(), 1);
+        assert!(master_keys.contains_key("binance1"));
+    }
+}
@@ -1 +1,9 @@
-pub mod params;
\ No newline at end of file
+pub mod exchange_info;
+pub mod kline_store;
+pub mod klines;
+pub mod paginate;
+pub mod params;
+pub mod rate_limit;
+pub mod retry;
+pub mod risk;
+pub mod submissions;
@@ -0,0 +1,762 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use binance_sdk::derivatives_trading_usds_futures::rest_api::{
+    self as usds_future_api, ExchangeInformationResponse, MarkPriceParams, MarkPriceResponse,
+};
+use binance_sdk::spot::rest_api::{
+    self as spot_api, AvgPriceParams, ExchangeInfoParams, ExchangeInfoResponse, TickerPriceParams,
+    TickerPriceResponse,
+};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::app::Key;
+use crate::common::rate_limit::{Cost, RateLimiter};
+use crate::handler::common::{ApiError, Validation};
+use crate::stream::market::Product;
+
+// 交易规则很少变化, 定期刷新即可
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+// 标记价格每秒更新, 同一笔订单的规则校验和风控检查共用一次查询
+const MARK_PRICE_TTL: Duration = Duration::from_secs(1);
+
+// 币安 exchangeInfo 中的 filters, 合约和现货共用同一组字段名
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawFilter {
+    filter_type: Option<String>,
+    min_price: Option<String>,
+    max_price: Option<String>,
+    tick_size: Option<String>,
+    min_qty: Option<String>,
+    max_qty: Option<String>,
+    step_size: Option<String>,
+    // 合约 MIN_NOTIONAL
+    notional: Option<String>,
+    // 现货 MIN_NOTIONAL / NOTIONAL
+    min_notional: Option<String>,
+    apply_to_market: Option<bool>,
+    apply_min_to_market: Option<bool>,
+    multiplier_up: Option<String>,
+    multiplier_down: Option<String>,
+    // 现货 PERCENT_PRICE_BY_SIDE
+    bid_multiplier_up: Option<String>,
+    bid_multiplier_down: Option<String>,
+    ask_multiplier_up: Option<String>,
+    ask_multiplier_down: Option<String>,
+}
+
+// 币安用 0 表示不限制
+fn limit(value: &Option<String>) -> Option<Decimal> {
+    value
+        .as_deref()
+        .and_then(|value| value.parse::<Decimal>().ok())
+        .filter(|value| !value.is_zero())
+}
+
+#[derive(Debug, Clone, Default)]
+struct Range {
+    min: Option<Decimal>,
+    max: Option<Decimal>,
+    step: Option<Decimal>,
+}
+
+impl Range {
+    fn check(&self, value: Decimal, field: &str, step_name: &str, validation: &mut Validation) {
+        if let Some(min) = self.min {
+            validation.check(value >= min, field, &format!("must be at least {}", min));
+        }
+        if let Some(max) = self.max {
+            validation.check(value <= max, field, &format!("must be at most {}", max));
+        }
+        if let Some(step) = self.step {
+            validation.check(
+                (value % step).is_zero(),
+                field,
+                &format!("must be a multiple of {} {}", step_name, step),
+            );
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MinNotional {
+    notional: Decimal,
+    apply_to_market: bool,
+}
+
+#[derive(Debug, Clone)]
+struct PercentPrice {
+    bid_up: Decimal,
+    bid_down: Decimal,
+    ask_up: Decimal,
+    ask_down: Decimal,
+}
+
+/// 单个交易对的下单规则, 取自 exchangeInfo 的 PRICE_FILTER, LOT_SIZE, MARKET_LOT_SIZE,
+/// MIN_NOTIONAL (现货还有 NOTIONAL) 和 PERCENT_PRICE (现货还有 PERCENT_PRICE_BY_SIDE)
+#[derive(Debug, Clone, Default)]
+pub struct SymbolFilters {
+    price: Option<Range>,
+    lot_size: Option<Range>,
+    market_lot_size: Option<Range>,
+    min_notional: Option<MinNotional>,
+    percent_price: Option<PercentPrice>,
+}
+
+/// 待校验的订单, price 为空时按市价单处理
+pub struct OrderCheck {
+    pub is_buy: bool,
+    pub price: Option<Decimal>,
+    pub stop_price: Option<Decimal>,
+    pub quantity: Option<Decimal>,
+    // 合约为标记价格, 现货为平均价格, 用于 PERCENT_PRICE 和市价单的 MIN_NOTIONAL
+    pub reference_price: Option<Decimal>,
+    // 合约的只减仓订单不受 MIN_NOTIONAL 限制
+    pub check_notional: bool,
+}
+
+impl SymbolFilters {
+    fn from_raw(filters: Vec<RawFilter>) -> Self {
+        let mut symbol_filters = SymbolFilters::default();
+        for filter in filters {
+            let range = |min, max, step| Range {
+                min: limit(min),
+                max: limit(max),
+                step: limit(step),
+            };
+            match filter.filter_type.as_deref() {
+                Some("PRICE_FILTER") => {
+                    symbol_filters.price = Some(range(
+                        &filter.min_price,
+                        &filter.max_price,
+                        &filter.tick_size,
+                    ));
+                }
+                Some("LOT_SIZE") => {
+                    symbol_filters.lot_size =
+                        Some(range(&filter.min_qty, &filter.max_qty, &filter.step_size));
+                }
+                Some("MARKET_LOT_SIZE") => {
+                    symbol_filters.market_lot_size =
+                        Some(range(&filter.min_qty, &filter.max_qty, &filter.step_size));
+                }
+                Some("MIN_NOTIONAL") | Some("NOTIONAL") => {
+                    let notional = limit(&filter.notional).or(limit(&filter.min_notional));
+                    symbol_filters.min_notional = notional.map(|notional| MinNotional {
+                        notional,
+                        apply_to_market: filter
+                            .apply_to_market
+                            .or(filter.apply_min_to_market)
+                            .unwrap_or(true),
+                    });
+                }
+                Some("PERCENT_PRICE") => {
+                    if let (Some(up), Some(down)) =
+                        (limit(&filter.multiplier_up), limit(&filter.multiplier_down))
+                    {
+                        symbol_filters.percent_price = Some(PercentPrice {
+                            bid_up: up,
+                            bid_down: down,
+                            ask_up: up,
+                            ask_down: down,
+                        });
+                    }
+                }
+                Some("PERCENT_PRICE_BY_SIDE") => {
+                    if let (Some(bid_up), Some(bid_down), Some(ask_up), Some(ask_down)) = (
+                        limit(&filter.bid_multiplier_up),
+                        limit(&filter.bid_multiplier_down),
+                        limit(&filter.ask_multiplier_up),
+                        limit(&filter.ask_multiplier_down),
+                    ) {
+                        symbol_filters.percent_price = Some(PercentPrice {
+                            bid_up,
+                            bid_down,
+                            ask_up,
+                            ask_down,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        symbol_filters
+    }
+
+    // SDK 中合约和现货的 filter 是不同的类型, 字段名相同, 经 JSON 转换为同一结构
+    fn from_sdk<T: Serialize>(filters: &[T]) -> Self {
+        let raw = serde_json::to_value(filters)
+            .and_then(serde_json::from_value::<Vec<RawFilter>>)
+            .unwrap_or_default();
+        Self::from_raw(raw)
+    }
+
+    fn lot_size(&self, is_market: bool) -> Option<&Range> {
+        let market = self
+            .market_lot_size
+            .as_ref()
+            .filter(|range| range.step.is_some());
+        if is_market {
+            market.or(self.lot_size.as_ref())
+        } else {
+            self.lot_size.as_ref()
+        }
+    }
+
+    /// 是否需要参考价格才能完成校验
+    pub fn needs_reference_price(&self, order: &OrderCheck) -> bool {
+        (self.percent_price.is_some() && order.price.is_some())
+            || (self.min_notional.is_some()
+                && order.check_notional
+                && order.price.is_none()
+                && order.stop_price.is_none())
+    }
+
+    /// price 和 stop_price 对齐到 tickSize (买单向下, 卖单向上, 不会比原价更差),
+    /// quantity 向下对齐到 stepSize
+    pub fn round(
+        &self,
+        is_buy: bool,
+        price: &mut Option<Decimal>,
+        stop_price: &mut Option<Decimal>,
+        quantity: &mut Option<Decimal>,
+    ) {
+        let round_to = |value: Decimal, step: Decimal, strategy| {
+            ((value / step).round_dp_with_strategy(0, strategy) * step).normalize()
+        };
+
+        if let Some(tick) = self.price.as_ref().and_then(|range| range.step) {
+            let strategy = if is_buy {
+                RoundingStrategy::ToZero
+            } else {
+                RoundingStrategy::AwayFromZero
+            };
+            *price = price.map(|price| round_to(price, tick, strategy));
+            *stop_price = stop_price.map(|price| round_to(price, tick, strategy));
+        }
+        if let Some(step) = self.lot_size(price.is_none()).and_then(|range| range.step) {
+            *quantity = quantity.map(|qty| round_to(qty, step, RoundingStrategy::ToZero));
+        }
+    }
+
+    /// 按交易规则校验订单, 不合法的字段记入 validation
+    pub fn check(&self, order: &OrderCheck, validation: &mut Validation) {
+        if let Some(range) = &self.price {
+            if let Some(price) = order.price {
+                range.check(price, "price", "tick size", validation);
+            }
+            if let Some(stop_price) = order.stop_price {
+                range.check(stop_price, "stop_price", "tick size", validation);
+            }
+        }
+
+        let is_market = order.price.is_none();
+        if let (Some(range), Some(quantity)) = (self.lot_size(is_market), order.quantity) {
+            range.check(quantity, "quantity", "step size", validation);
+        }
+
+        if let (Some(min_notional), Some(quantity)) = (&self.min_notional, order.quantity) {
+            let price = order.price.or(order.stop_price).or(order.reference_price);
+            if let Some(price) = price
+                && order.check_notional
+                && (!is_market || min_notional.apply_to_market)
+            {
+                validation.check(
+                    price * quantity >= min_notional.notional,
+                    "quantity",
+                    &format!("order notional must be at least {}", min_notional.notional),
+                );
+            }
+        }
+
+        if let (Some(percent_price), Some(price), Some(reference_price)) =
+            (&self.percent_price, order.price, order.reference_price)
+        {
+            let (up, down) = if order.is_buy {
+                (percent_price.bid_up, percent_price.bid_down)
+            } else {
+                (percent_price.ask_up, percent_price.ask_down)
+            };
+            let (max, min) = (reference_price * up, reference_price * down);
+            validation.check(
+                price >= min && price <= max,
+                "price",
+                &format!(
+                    "must be between {} and {} ({}% to {}% of the reference price {})",
+                    min.normalize(),
+                    max.normalize(),
+                    (down * Decimal::ONE_HUNDRED).normalize(),
+                    (up * Decimal::ONE_HUNDRED).normalize(),
+                    reference_price
+                ),
+            );
+        }
+    }
+}
+
+struct UsdsFutureSnapshot {
+    info: Arc<ExchangeInformationResponse>,
+    filters: HashMap<String, SymbolFilters>,
+    // 交易对到标的资产, BTCUSDT 和 BTCUSDC 都是 BTC
+    base_assets: HashMap<String, String>,
+}
+
+struct SpotSnapshot {
+    // /spot/exchange_information 返回的交易规则
+    info: Arc<ExchangeInfoResponse>,
+    // 全部交易对的下单规则
+    filters: HashMap<String, SymbolFilters>,
+}
+
+/// 合约和现货的交易规则缓存, 使用公开行情客户端从正式环境拉取并定期刷新
+/// 测试网和自定义 base_url 的 key 交易规则可能不同, 不使用缓存
+/// 请求与不带 key 的行情请求共用正式环境的限流额度
+pub struct ExchangeInfoCache {
+    production_keys: HashSet<String>,
+    usds_future_client: usds_future_api::RestApi,
+    spot_client: spot_api::RestApi,
+    rate_limiter: Arc<RateLimiter>,
+    usds_future: RwLock<Option<Arc<UsdsFutureSnapshot>>>,
+    spot: RwLock<Option<Arc<SpotSnapshot>>>,
+    // 交易对到 (查询时间, 标记价格)
+    mark_prices: Mutex<HashMap<String, (Instant, Decimal)>>,
+}
+
+impl ExchangeInfoCache {
+    pub fn new(
+        keys: &HashMap<String, Key>,
+        usds_future_client: usds_future_api::RestApi,
+        spot_client: spot_api::RestApi,
+        rate_limiter: Arc<RateLimiter>,
+    ) -> Self {
+        let production_keys = keys
+            .iter()
+            .filter(|(_, key)| key.is_production())
+            .map(|(key_name, _)| key_name.clone())
+            .collect();
+
+        ExchangeInfoCache {
+            production_keys,
+            usds_future_client,
+            spot_client,
+            rate_limiter,
+            usds_future: RwLock::new(None),
+            spot: RwLock::new(None),
+            mark_prices: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 缓存是否适用于该 key, 不传 key 时使用公开行情客户端, 同样适用
+    pub fn covers(&self, key_name: Option<&str>) -> bool {
+        key_name.is_none_or(|key_name| self.production_keys.contains(key_name))
+    }
+
+    async fn refresh_usds_future(&self) -> Result<Arc<UsdsFutureSnapshot>, ApiError> {
+        let response = self
+            .rate_limiter
+            .call(None, Cost::weight(Product::UsdsFuture, 1), async || {
+                self.usds_future_client.exchange_information().await
+            })
+            .await
+            .inspect_err(|e| error!("exchange info cache: usds_future: {}", e))?;
+        let info = response.data().await.map_err(|e| {
+            error!("exchange info cache: usds_future: {}", e);
+            ApiError::from(e)
+        })?;
+
+        let filters = info
+            .symbols
+            .iter()
+            .flatten()
+            .filter_map(|symbol| {
+                let filters =
+                    SymbolFilters::from_sdk(symbol.filters.as_deref().unwrap_or_default());
+                Some((symbol.symbol.clone()?, filters))
+            })
+            .collect();
+        let base_assets = info
+            .symbols
+            .iter()
+            .flatten()
+            .filter_map(|symbol| Some((symbol.symbol.clone()?, symbol.base_asset.clone()?)))
+            .collect();
+        let snapshot = Arc::new(UsdsFutureSnapshot {
+            info: Arc::new(info),
+            filters,
+            base_assets,
+        });
+        *self.usds_future.write().unwrap() = Some(snapshot.clone());
+        Ok(snapshot)
+    }
+
+    async fn refresh_spot(&self) -> Result<Arc<SpotSnapshot>, ApiError> {
+        let response = self
+            .rate_limiter
+            .call(None, Cost::weight(Product::Spot, 20), async || {
+                self.spot_client
+                    .exchange_info(ExchangeInfoParams::default())
+                    .await
+            })
+            .await
+            .inspect_err(|e| error!("exchange info cache: spot: {}", e))?;
+        let info = response.data().await.map_err(|e| {
+            error!("exchange info cache: spot: {}", e);
+            ApiError::from(e)
+        })?;
+
+        let filters = info
+            .symbols
+            .iter()
+            .flatten()
+            .filter_map(|symbol| {
+                let filters =
+                    SymbolFilters::from_sdk(symbol.filters.as_deref().unwrap_or_default());
+                Some((symbol.symbol.clone()?, filters))
+            })
+            .collect();
+        let snapshot = Arc::new(SpotSnapshot {
+            info: Arc::new(spot_trading_info(info)),
+            filters,
+        });
+        *self.spot.write().unwrap() = Some(snapshot.clone());
+        Ok(snapshot)
+    }
+
+    /// 后台定期刷新, 失败时保留上一次的数据
+    pub async fn refresh_periodically(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+        loop {
+            interval.tick().await;
+            let (usds_future, spot) =
+                futures::join!(self.refresh_usds_future(), self.refresh_spot());
+            match (usds_future, spot) {
+                (Ok(_), Ok(_)) => info!("exchange info cache: refreshed"),
+                (usds_future, spot) => warn!(
+                    "exchange info cache: refresh failed, usds_future: {}, spot: {}",
+                    usds_future.is_ok(),
+                    spot.is_ok()
+                ),
+            }
+        }
+    }
+
+    async fn usds_future_snapshot(&self) -> Result<Arc<UsdsFutureSnapshot>, ApiError> {
+        let snapshot = self.usds_future.read().unwrap().clone();
+        match snapshot {
+            Some(snapshot) => Ok(snapshot),
+            None => self.refresh_usds_future().await,
+        }
+    }
+
+    async fn spot_snapshot(&self) -> Result<Arc<SpotSnapshot>, ApiError> {
+        let snapshot = self.spot.read().unwrap().clone();
+        match snapshot {
+            Some(snapshot) => Ok(snapshot),
+            None => self.refresh_spot().await,
+        }
+    }
+
+    /// U 本位合约交易规则 (与 GET /fapi/v1/exchangeInfo 相同)
+    pub async fn usds_future_info(&self) -> Result<Arc<ExchangeInformationResponse>, ApiError> {
+        Ok(self.usds_future_snapshot().await?.info.clone())
+    }
+
+    /// 现货交易规则, 与 /spot/exchange_information 请求币安时的结果相同
+    pub async fn spot_info(&self) -> Result<Arc<ExchangeInfoResponse>, ApiError> {
+        Ok(self.spot_snapshot().await?.info.clone())
+    }
+
+    // 缓存不可用时返回 None, 由币安校验; 交易对不存在时返回 400
+    fn lookup(
+        filters: Result<&HashMap<String, SymbolFilters>, &ApiError>,
+        symbol: &str,
+    ) -> Result<Option<SymbolFilters>, ApiError> {
+        match filters {
+            Ok(filters) => match filters.get(symbol) {
+                Some(filters) => Ok(Some(filters.clone())),
+                None => Err(ApiError::bad_request(format!("Unknown symbol {}", symbol))),
+            },
+            Err(e) => {
+                warn!("exchange info cache unavailable, skip filter checks: {}", e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// U 本位合约交易对的下单规则, key 不适用缓存时返回 None
+    pub async fn usds_future_filters(
+        &self,
+        key_name: &str,
+        symbol: &str,
+    ) -> Result<Option<SymbolFilters>, ApiError> {
+        if !self.covers(Some(key_name)) {
+            return Ok(None);
+        }
+        let snapshot = self.usds_future_snapshot().await;
+        Self::lookup(snapshot.as_ref().map(|s| &s.filters), symbol)
+    }
+
+    /// 现货交易对的下单规则, key 不适用缓存时返回 None
+    pub async fn spot_filters(
+        &self,
+        key_name: &str,
+        symbol: &str,
+    ) -> Result<Option<SymbolFilters>, ApiError> {
+        if !self.covers(Some(key_name)) {
+            return Ok(None);
+        }
+        let snapshot = self.spot_snapshot().await;
+        Self::lookup(snapshot.as_ref().map(|s| &s.filters), symbol)
+    }
+
+    /// U 本位合约标记价格, MARK_PRICE_TTL 内重复查询使用缓存, 获取失败时返回 None
+    pub async fn usds_future_mark_price(&self, symbol: &str) -> Option<Decimal> {
+        if let Some((fetched_at, price)) = self.mark_prices.lock().unwrap().get(symbol)
+            && fetched_at.elapsed() < MARK_PRICE_TTL
+        {
+            return Some(*price);
+        }
+
+        let params = MarkPriceParams::builder()
+            .symbol(symbol.to_string())
+            .build()
+            .ok()?;
+        let response = self
+            .rate_limiter
+            .call(None, Cost::weight(Product::UsdsFuture, 1), async || {
+                self.usds_future_client.mark_price(params).await
+            })
+            .await
+            .ok()?;
+        let price: Decimal = match response.data().await.ok()? {
+            MarkPriceResponse::MarkPriceResponse1(price) => price.mark_price?.parse().ok()?,
+            _ => return None,
+        };
+
+        let mut mark_prices = self.mark_prices.lock().unwrap();
+        mark_prices.retain(|_, (fetched_at, _)| fetched_at.elapsed() < MARK_PRICE_TTL);
+        mark_prices.insert(symbol.to_string(), (Instant::now(), price));
+        Some(price)
+    }
+
+    /// U 本位合约交易对的标的资产, 缓存不可用或交易对不存在时返回 None
+    pub async fn usds_future_base_asset(&self, symbol: &str) -> Option<String> {
+        let snapshot = self.usds_future_snapshot().await.ok()?;
+        snapshot.base_assets.get(symbol).cloned()
+    }
+
+    /// 全部现货交易对的最新价格, 获取失败时返回 None
+    pub async fn spot_prices(&self) -> Option<HashMap<String, Decimal>> {
+        let response = self
+            .rate_limiter
+            .call(None, Cost::weight(Product::Spot, 4), async || {
+                self.spot_client
+                    .ticker_price(TickerPriceParams::default())
+                    .await
+            })
+            .await
+            .map_err(|e| warn!("spot prices: {}", e))
+            .ok()?;
+        match response.data().await.ok()? {
+            TickerPriceResponse::TickerPriceResponse2(prices) => Some(
+                prices
+                    .into_iter()
+                    .filter_map(|p| Some((p.symbol?, p.price?.parse().ok()?)))
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// 现货平均价格, 获取失败时返回 None
+    pub async fn spot_avg_price(&self, symbol: &str) -> Option<Decimal> {
+        let params = AvgPriceParams::builder(symbol.to_string()).build().ok()?;
+        let response = self
+            .rate_limiter
+            .call(None, Cost::weight(Product::Spot, 2), async || {
+                self.spot_client.avg_price(params).await
+            })
+            .await
+            .ok()?;
+        response.data().await.ok()?.price?.parse().ok()
+    }
+}
+
+// 缓存拉取全部交易对, 按 /spot/exchange_information 的请求参数筛选:
+// permissions=SPOT, symbolStatus=TRADING, showPermissionSets=false
+fn spot_trading_info(mut info: ExchangeInfoResponse) -> ExchangeInfoResponse {
+    info.symbols = info.symbols.map(|symbols| {
+        symbols
+            .into_iter()
+            .filter(|symbol| {
+                symbol.status.as_deref() == Some("TRADING")
+                    && symbol
+                        .permission_sets
+                        .iter()
+                        .flatten()
+                        .any(|set| set.iter().any(|permission| permission == "SPOT"))
+            })
+            .map(|mut symbol| {
+                symbol.permission_sets = None;
+                symbol
+            })
+            .collect()
+    });
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use binance_sdk::config::ConfigurationRestApi;
+    use binance_sdk::derivatives_trading_usds_futures::DerivativesTradingUsdsFuturesRestApi;
+    use binance_sdk::spot::SpotRestApi;
+    use serde_json::json;
+
+    use super::*;
+
+    fn filters(value: serde_json::Value) -> SymbolFilters {
+        SymbolFilters::from_raw(serde_json::from_value(value).unwrap())
+    }
+
+    fn usds_future_filters() -> SymbolFilters {
+        filters(json!([
+            {"filterType": "PRICE_FILTER", "minPrice": "556.80", "maxPrice": "4529764", "tickSize": "0.10"},
+            {"filterType": "LOT_SIZE", "minQty": "0.001", "maxQty": "1000", "stepSize": "0.001"},
+            {"filterType": "MARKET_LOT_SIZE", "minQty": "0.001", "maxQty": "120", "stepSize": "0.001"},
+            {"filterType": "MIN_NOTIONAL", "notional": "100"},
+            {"filterType": "PERCENT_PRICE", "multiplierUp": "1.0500", "multiplierDown": "0.9500", "multiplierDecimal": "4"}
+        ]))
+    }
+
+    fn order(price: Option<Decimal>, quantity: Decimal) -> OrderCheck {
+        OrderCheck {
+            is_buy: true,
+            price,
+            stop_price: None,
+            quantity: Some(quantity),
+            reference_price: Some(Decimal::from(60000)),
+            check_notional: true,
+        }
+    }
+
+    fn invalid_fields(filters: &SymbolFilters, order: &OrderCheck) -> Vec<String> {
+        let mut validation = Validation::default();
+        filters.check(order, &mut validation);
+        match validation.finish() {
+            Ok(()) => vec![],
+            Err(e) => e.fields.iter().map(|f| f.field.clone()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let filters = usds_future_filters();
+        let dec = |value: &str| value.parse::<Decimal>().unwrap();
+
+        let valid = order(Some(dec("60000.1")), dec("0.002"));
+        assert!(invalid_fields(&filters, &valid).is_empty());
+
+        // tickSize 和 stepSize 不对齐, 名义价值不足
+        let order_ = order(Some(dec("60000.15")), dec("0.0011"));
+        assert_eq!(
+            invalid_fields(&filters, &order_),
+            vec!["price", "quantity", "quantity"]
+        );
+
+        // 超出标记价格的 5%
+        let order_ = order(Some(dec("63000.1")), dec("0.002"));
+        assert_eq!(invalid_fields(&filters, &order_), vec!["price"]);
+
+        // 市价单使用 MARKET_LOT_SIZE, 名义价值按参考价格计算
+        let order_ = order(None, dec("121"));
+        assert_eq!(invalid_fields(&filters, &order_), vec!["quantity"]);
+        let order_ = order(None, dec("0.001"));
+        assert_eq!(invalid_fields(&filters, &order_), vec!["quantity"]);
+        let order_ = OrderCheck {
+            check_notional: false,
+            ..order(None, dec("0.001"))
+        };
+        assert!(invalid_fields(&filters, &order_).is_empty());
+    }
+
+    #[test]
+    fn test_round() {
+        let filters = usds_future_filters();
+        let dec = |value: &str| value.parse::<Decimal>().unwrap();
+
+        let mut price = Some(dec("60000.17"));
+        let mut stop_price = None;
+        let mut quantity = Some(dec("0.0019"));
+        filters.round(true, &mut price, &mut stop_price, &mut quantity);
+        assert_eq!(price, Some(dec("60000.1")));
+        assert_eq!(quantity, Some(dec("0.001")));
+
+        let mut price = Some(dec("60000.11"));
+        filters.round(false, &mut price, &mut stop_price, &mut quantity);
+        assert_eq!(price, Some(dec("60000.2")));
+    }
+
+    #[test]
+    fn test_spot_filters() {
+        let filters = filters(json!([
+            {"filterType": "NOTIONAL", "minNotional": "5", "applyMinToMarket": false,
+             "maxNotional": "9000000", "applyMaxToMarket": false, "avgPriceMins": 5},
+            {"filterType": "PERCENT_PRICE_BY_SIDE", "bidMultiplierUp": "5", "bidMultiplierDown": "0.2",
+             "askMultiplierUp": "5", "askMultiplierDown": "0.2", "avgPriceMins": 5},
+            {"filterType": "MAX_NUM_ORDERS", "maxNumOrders": 200}
+        ]));
+        let dec = |value: &str| value.parse::<Decimal>().unwrap();
+
+        let order_ = order(Some(dec("20000")), dec("0.0001"));
+        assert_eq!(invalid_fields(&filters, &order_), vec!["quantity"]);
+        // 市价单不受 NOTIONAL 最小值限制
+        let order_ = order(None, dec("0.00001"));
+        assert!(invalid_fields(&filters, &order_).is_empty());
+        let order_ = order(Some(dec("400000")), dec("1"));
+        assert_eq!(invalid_fields(&filters, &order_), vec!["price"]);
+    }
+
+    #[test]
+    fn test_spot_trading_info() {
+        let info: ExchangeInfoResponse = serde_json::from_value(json!({"symbols": [
+            {"symbol": "BTCUSDT", "status": "TRADING", "permissionSets": [["SPOT", "MARGIN"]]},
+            {"symbol": "LUNAUSDT", "status": "BREAK", "permissionSets": [["SPOT"]]},
+            {"symbol": "ETHBTC", "status": "TRADING", "permissionSets": [["MARGIN"]]}
+        ]}))
+        .unwrap();
+
+        let info = spot_trading_info(info);
+        let symbols = info.symbols.unwrap();
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].symbol.as_deref(), Some("BTCUSDT"));
+        assert!(symbols[0].permission_sets.is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_mark_price_cache() {
+        let cache = ExchangeInfoCache::new(
+            &HashMap::new(),
+            DerivativesTradingUsdsFuturesRestApi::production(
+                ConfigurationRestApi::builder().build().unwrap(),
+            ),
+            SpotRestApi::production(ConfigurationRestApi::builder().build().unwrap()),
+            Arc::new(RateLimiter::new(
+                &HashMap::new(),
+                &crate::config::RateLimitConfig::default(),
+            )),
+        );
+        // TTL 内直接使用缓存, 不请求币安
+        cache.mark_prices.lock().unwrap().insert(
+            "BTCUSDT".to_string(),
+            (Instant::now(), Decimal::from(60000)),
+        );
+        assert_eq!(
+            cache.usds_future_mark_price("BTCUSDT").await,
+            Some(Decimal::from(60000))
+        );
+    }
+}
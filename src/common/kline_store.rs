@@ -0,0 +1,493 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use actix_web::web;
+use binance_sdk::derivatives_trading_usds_futures::rest_api::KlineCandlestickDataParams;
+use binance_sdk::spot::rest_api::KlinesParams;
+use rusqlite::{Connection, params};
+use tracing::{error, info, warn};
+
+use crate::app::Key;
+use crate::common::klines::{Kline, KlineClient, KlinePager};
+use crate::common::paginate::now_ms;
+use crate::common::rate_limit::RateLimiter;
+use crate::config::{KlineSeries, KlineStoreConfig};
+use crate::handler::common::ApiError;
+use crate::stream::market::Product;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS klines (
+    product TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    interval TEXT NOT NULL,
+    open_time INTEGER NOT NULL,
+    open TEXT NOT NULL,
+    high TEXT NOT NULL,
+    low TEXT NOT NULL,
+    close TEXT NOT NULL,
+    volume TEXT NOT NULL,
+    close_time INTEGER NOT NULL,
+    quote_volume TEXT NOT NULL,
+    trades INTEGER NOT NULL,
+    taker_buy_base_volume TEXT NOT NULL,
+    taker_buy_quote_volume TEXT NOT NULL,
+    PRIMARY KEY (product, symbol, interval, open_time)
+) WITHOUT ROWID;
+CREATE TABLE IF NOT EXISTS kline_series (
+    product TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    interval TEXT NOT NULL,
+    start_time INTEGER NOT NULL,
+    synced_until INTEGER,
+    PRIMARY KEY (product, symbol, interval)
+);
+";
+
+type SeriesKey = (Product, String, String);
+
+// 币安 klines 接口的默认 limit
+const DEFAULT_LIMIT: i64 = 500;
+
+/// K 线查询, 字段与币安 klines 接口相同
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KlineQuery<'a> {
+    pub product: Product,
+    pub symbol: &'a str,
+    pub interval: &'a str,
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    pub limit: i64,
+}
+
+impl<'a> From<&'a KlineCandlestickDataParams> for KlineQuery<'a> {
+    fn from(params: &'a KlineCandlestickDataParams) -> Self {
+        KlineQuery {
+            product: Product::UsdsFuture,
+            symbol: &params.symbol,
+            interval: params.interval.as_str(),
+            start_time: params.start_time,
+            end_time: params.end_time,
+            limit: params.limit.unwrap_or(DEFAULT_LIMIT),
+        }
+    }
+}
+
+impl<'a> From<&'a KlinesParams> for KlineQuery<'a> {
+    fn from(params: &'a KlinesParams) -> Self {
+        KlineQuery {
+            product: Product::Spot,
+            symbol: &params.symbol,
+            interval: params.interval.as_str(),
+            start_time: params.start_time,
+            end_time: params.end_time,
+            limit: params.limit.map_or(DEFAULT_LIMIT, i64::from),
+        }
+    }
+}
+
+/// 本地已保存的范围, [start_time, synced_until] 内的 K 线都已收盘并保存
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Coverage {
+    start_time: i64,
+    synced_until: Option<i64>,
+}
+
+/// 本地 K 线存储 (SQLite)
+/// 后台按配置的交易对和周期, 从最后一根已保存的 K 线增量同步, 只保存已收盘的 K 线
+pub struct KlineStore {
+    conn: Arc<Mutex<Connection>>,
+    // K 线从正式环境同步, 只用于正式环境的 key 和不带 key 的请求
+    production_keys: HashSet<String>,
+    series: Vec<KlineSeries>,
+    sync_interval: Duration,
+    coverage: RwLock<HashMap<SeriesKey, Coverage>>,
+}
+
+impl KlineStore {
+    pub fn open(
+        config: &KlineStoreConfig,
+        keys: &HashMap<String, Key>,
+    ) -> Result<Self, std::io::Error> {
+        let conn = Connection::open(&config.path).map_err(|e| {
+            std::io::Error::other(format!("Failed to open kline store {}: {}", config.path, e))
+        })?;
+        Self::new(conn, config, keys)
+    }
+
+    fn new(
+        conn: Connection,
+        config: &KlineStoreConfig,
+        keys: &HashMap<String, Key>,
+    ) -> Result<Self, std::io::Error> {
+        for series in &config.series {
+            if !series
+                .product
+                .kline_intervals()
+                .contains(&series.interval.as_str())
+            {
+                return Err(std::io::Error::other(format!(
+                    "Invalid kline_store series {} {}: unsupported interval {}",
+                    series.product.as_str(),
+                    series.symbol,
+                    series.interval
+                )));
+            }
+        }
+
+        conn.execute_batch(SCHEMA)
+            .map_err(|e| std::io::Error::other(format!("Failed to create kline store: {}", e)))?;
+        let coverage = load_coverage(&conn)
+            .map_err(|e| std::io::Error::other(format!("Failed to load kline store: {}", e)))?;
+
+        let production_keys = keys
+            .iter()
+            .filter(|(_, key)| key.is_production())
+            .map(|(key_name, _)| key_name.clone())
+            .collect();
+
+        Ok(KlineStore {
+            conn: Arc::new(Mutex::new(conn)),
+            production_keys,
+            series: config.series.clone(),
+            sync_interval: Duration::from_secs(config.sync_interval_secs.max(1)),
+            coverage: RwLock::new(coverage),
+        })
+    }
+
+    /// 本地数据是否适用于该 key 的请求, 不传 key 时使用公开行情客户端, 同样适用
+    pub fn serves(&self, key_name: Option<&str>) -> bool {
+        key_name.is_none_or(|key_name| self.production_keys.contains(key_name))
+    }
+
+    /// 从本地读取 K 线, 语义与币安 klines 接口相同, 返回 [start_time, end_time] 内的前 limit 根
+    /// 请求的范围没有完全保存在本地时返回 None, 由调用方请求币安
+    pub async fn klines(&self, query: &KlineQuery<'_>) -> Option<Vec<Kline>> {
+        // 不传 start_time 时币安返回最新的 K 线, 本地数据不一定是最新的
+        let start_time = query.start_time?;
+        let (end_time, limit) = (query.end_time, query.limit);
+        let key = (
+            query.product,
+            query.symbol.to_string(),
+            query.interval.to_string(),
+        );
+        let coverage = *self.coverage.read().unwrap().get(&key)?;
+        let synced_until = coverage.synced_until?;
+        if start_time < coverage.start_time || end_time.is_some_and(|end| end > synced_until) {
+            return None;
+        }
+
+        let until = end_time.unwrap_or(synced_until);
+        let conn = self.conn.clone();
+        let klines = web::block(move || {
+            let conn = conn.lock().unwrap();
+            select_klines(&conn, &key, start_time, until, limit)
+        })
+        .await
+        .ok()?
+        .map_err(|e| error!("kline store: select: {}", e))
+        .ok()?;
+
+        // 没有传 end_time 时, 本地不足 limit 根说明后面还有未保存的 K 线
+        if end_time.is_some() || klines.len() as i64 >= limit {
+            Some(klines)
+        } else {
+            None
+        }
+    }
+
+    /// 后台定期增量同步, 使用公开行情客户端, 与不带 key 的行情请求共用限流额度
+    pub async fn sync_periodically(
+        self: Arc<Self>,
+        rate_limiter: Arc<RateLimiter>,
+        usds_future: KlineClient,
+        spot: KlineClient,
+    ) {
+        let mut interval = tokio::time::interval(self.sync_interval);
+        loop {
+            interval.tick().await;
+            for series in &self.series {
+                let client = match series.product {
+                    Product::UsdsFuture => usds_future.clone(),
+                    Product::Spot => spot.clone(),
+                };
+                match self.sync(series, client, rate_limiter.clone()).await {
+                    Ok(0) => {}
+                    Ok(count) => info!(
+                        "kline store: {} {} {} synced {} klines",
+                        series.product.as_str(),
+                        series.symbol,
+                        series.interval,
+                        count
+                    ),
+                    Err(e) => warn!(
+                        "kline store: {} {} {} sync: {}",
+                        series.product.as_str(),
+                        series.symbol,
+                        series.interval,
+                        e
+                    ),
+                }
+            }
+        }
+    }
+
+    // 从最后一根已保存的 K 线之后同步到当前时间, 配置的 start_time 提前时从头同步
+    async fn sync(
+        &self,
+        series: &KlineSeries,
+        client: KlineClient,
+        rate_limiter: Arc<RateLimiter>,
+    ) -> Result<usize, ApiError> {
+        let key = (
+            series.product,
+            series.symbol.clone(),
+            series.interval.clone(),
+        );
+        let stored = self.coverage.read().unwrap().get(&key).copied();
+        let mut coverage = match stored {
+            Some(coverage) if coverage.start_time <= series.start_time => coverage,
+            _ => Coverage {
+                start_time: series.start_time,
+                synced_until: None,
+            },
+        };
+        let start_time = coverage
+            .synced_until
+            .map_or(series.start_time, |synced_until| synced_until + 1);
+
+        let now = now_ms();
+        let mut pager = KlinePager::new(
+            client,
+            rate_limiter,
+            None,
+            series.symbol.clone(),
+            series.interval.clone(),
+            start_time,
+            now,
+        );
+        let mut count = 0;
+        while let Some(page) = pager.next_page().await? {
+            // 未收盘的 K 线还会变化, 不保存
+            let closed: Vec<Kline> = page.into_iter().filter(|k| k.close_time < now).collect();
+            let Some(last) = closed.last() else {
+                break;
+            };
+            coverage.synced_until = Some(last.close_time);
+            count += closed.len();
+
+            let conn = self.conn.clone();
+            let series_key = key.clone();
+            web::block(move || {
+                let mut conn = conn.lock().unwrap();
+                save_klines(&mut conn, &series_key, &closed, coverage)
+            })
+            .await
+            .map_err(|e| ApiError::from(anyhow::Error::from(e)))?
+            .map_err(|e| ApiError::from(anyhow::Error::from(e)))?;
+            self.coverage.write().unwrap().insert(key.clone(), coverage);
+        }
+
+        Ok(count)
+    }
+}
+
+fn load_coverage(conn: &Connection) -> rusqlite::Result<HashMap<SeriesKey, Coverage>> {
+    let mut stmt = conn
+        .prepare("SELECT product, symbol, interval, start_time, synced_until FROM kline_series")?;
+    let rows = stmt.query_map([], |row| {
+        let product: String = row.get(0)?;
+        Ok((
+            product,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            Coverage {
+                start_time: row.get(3)?,
+                synced_until: row.get(4)?,
+            },
+        ))
+    })?;
+
+    let mut coverage = HashMap::new();
+    for row in rows {
+        let (product, symbol, interval, series) = row?;
+        let product = match product.as_str() {
+            "usds_future" => Product::UsdsFuture,
+            "spot" => Product::Spot,
+            _ => continue,
+        };
+        coverage.insert((product, symbol, interval), series);
+    }
+    Ok(coverage)
+}
+
+fn save_klines(
+    conn: &mut Connection,
+    (product, symbol, interval): &SeriesKey,
+    klines: &[Kline],
+    coverage: Coverage,
+) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT OR REPLACE INTO klines VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        )?;
+        for k in klines {
+            stmt.execute(params![
+                product.as_str(),
+                symbol,
+                interval,
+                k.open_time,
+                k.open,
+                k.high,
+                k.low,
+                k.close,
+                k.volume,
+                k.close_time,
+                k.quote_volume,
+                k.trades,
+                k.taker_buy_base_volume,
+                k.taker_buy_quote_volume,
+            ])?;
+        }
+    }
+    tx.execute(
+        "INSERT OR REPLACE INTO kline_series VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            product.as_str(),
+            symbol,
+            interval,
+            coverage.start_time,
+            coverage.synced_until
+        ],
+    )?;
+    tx.commit()
+}
+
+fn select_klines(
+    conn: &Connection,
+    (product, symbol, interval): &SeriesKey,
+    start_time: i64,
+    end_time: i64,
+    limit: i64,
+) -> rusqlite::Result<Vec<Kline>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT open_time, open, high, low, close, volume, close_time, quote_volume, trades,
+                taker_buy_base_volume, taker_buy_quote_volume
+         FROM klines
+         WHERE product = ?1 AND symbol = ?2 AND interval = ?3 AND open_time >= ?4 AND open_time <= ?5
+         ORDER BY open_time
+         LIMIT ?6",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            product.as_str(),
+            symbol,
+            interval,
+            start_time,
+            end_time,
+            limit
+        ],
+        |row| {
+            Ok(Kline {
+                open_time: row.get(0)?,
+                open: row.get(1)?,
+                high: row.get(2)?,
+                low: row.get(3)?,
+                close: row.get(4)?,
+                volume: row.get(5)?,
+                close_time: row.get(6)?,
+                quote_volume: row.get(7)?,
+                trades: row.get(8)?,
+                taker_buy_base_volume: row.get(9)?,
+                taker_buy_quote_volume: row.get(10)?,
+            })
+        },
+    )?;
+    rows.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kline(open_time: i64) -> Kline {
+        Kline {
+            open_time,
+            open: "1".to_string(),
+            high: "2".to_string(),
+            low: "0.5".to_string(),
+            close: "1.5".to_string(),
+            volume: "10".to_string(),
+            close_time: open_time + 59_999,
+            quote_volume: "15".to_string(),
+            trades: 3,
+            taker_buy_base_volume: "5".to_string(),
+            taker_buy_quote_volume: "7.5".to_string(),
+        }
+    }
+
+    fn store() -> KlineStore {
+        let config = KlineStoreConfig {
+            path: ":memory:".to_string(),
+            sync_interval_secs: 60,
+            series: vec![],
+        };
+        KlineStore::new(
+            Connection::open_in_memory().unwrap(),
+            &config,
+            &HashMap::new(),
+        )
+        .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_klines_coverage() {
+        let store = store();
+        let key = (Product::UsdsFuture, "BTCUSDT".to_string(), "1m".to_string());
+        let klines: Vec<Kline> = (1..=10).map(|i| kline(i * 60_000)).collect();
+        let coverage = Coverage {
+            start_time: 60_000,
+            synced_until: Some(klines[9].close_time),
+        };
+        save_klines(&mut store.conn.lock().unwrap(), &key, &klines, coverage).unwrap();
+        store.coverage.write().unwrap().insert(key, coverage);
+
+        let query = |start_time, end_time, limit| KlineQuery {
+            product: Product::UsdsFuture,
+            symbol: "BTCUSDT",
+            interval: "1m",
+            start_time,
+            end_time,
+            limit,
+        };
+        let store = &store;
+        let get = |start, end, limit| {
+            let query = query(start, end, limit);
+            async move { store.klines(&query).await }
+        };
+
+        let result = get(Some(120_000), Some(300_000), 500).await.unwrap();
+        assert_eq!(result.first().map(|k| k.open_time), Some(120_000));
+        assert_eq!(result.len(), 4);
+        assert_eq!(result[0], kline(120_000));
+
+        // 不传 end_time 时, 本地有足够的 K 线才从本地返回
+        assert_eq!(get(Some(60_000), None, 5).await.unwrap().len(), 5);
+        assert!(get(Some(60_000), None, 500).await.is_none());
+
+        // 超出已同步的范围, 或不传 start_time
+        assert!(get(Some(0), Some(300_000), 500).await.is_none());
+        assert!(get(Some(60_000), Some(700_000), 500).await.is_none());
+        assert!(get(None, Some(300_000), 500).await.is_none());
+        let spot = KlineQuery {
+            product: Product::Spot,
+            ..query(Some(60_000), Some(300_000), 500)
+        };
+        assert!(store.klines(&spot).await.is_none());
+
+        // 重新打开后从数据库恢复已同步的范围
+        let conn = store.conn.lock().unwrap();
+        assert_eq!(load_coverage(&conn).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,331 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use binance_sdk::derivatives_trading_usds_futures::rest_api::{
+    self as usds_future_api, KlineCandlestickDataIntervalEnum, KlineCandlestickDataParams,
+};
+use binance_sdk::spot::rest_api::{self as spot_api, KlinesIntervalEnum, KlinesParams};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::error;
+
+use crate::common::rate_limit::{Cost, RateLimiter};
+use crate::handler::common::ApiError;
+use crate::stream::market::Product;
+
+// 每页条数, 合约 limit 超过 1000 时权重翻倍, 1000 条一页最省权重
+pub const PAGE_LIMIT: i64 = 1000;
+
+/// 一根 K 线, 字段顺序与币安返回的数组一致
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Kline {
+    pub open_time: i64,
+    pub open: String,
+    pub high: String,
+    pub low: String,
+    pub close: String,
+    pub volume: String,
+    pub close_time: i64,
+    pub quote_volume: String,
+    pub trades: i64,
+    pub taker_buy_base_volume: String,
+    pub taker_buy_quote_volume: String,
+}
+
+pub const CSV_HEADER: &str = "open_time,open,high,low,close,volume,close_time,quote_volume,trades,taker_buy_base_volume,taker_buy_quote_volume";
+
+impl Kline {
+    // 币安返回 [openTime, open, high, low, close, volume, closeTime, quoteVolume, trades, takerBuyBase, takerBuyQuote, ignore]
+    fn from_row(row: &[Value]) -> Option<Self> {
+        let int = |i: usize| row.get(i)?.as_i64();
+        let text = |i: usize| row.get(i)?.as_str().map(str::to_string);
+        Some(Kline {
+            open_time: int(0)?,
+            open: text(1)?,
+            high: text(2)?,
+            low: text(3)?,
+            close: text(4)?,
+            volume: text(5)?,
+            close_time: int(6)?,
+            quote_volume: text(7)?,
+            trades: int(8)?,
+            taker_buy_base_volume: text(9)?,
+            taker_buy_quote_volume: text(10)?,
+        })
+    }
+
+    // SDK 中合约和现货的 K 线是不同的类型, 经 JSON 转换后统一解析
+    fn from_sdk<T: Serialize>(rows: &[Vec<T>]) -> Result<Vec<Self>, ApiError> {
+        let rows: Vec<Vec<Value>> = serde_json::to_value(rows)
+            .and_then(serde_json::from_value)
+            .map_err(|e| ApiError::from(anyhow::Error::from(e)))?;
+        Ok(rows.iter().filter_map(|row| Self::from_row(row)).collect())
+    }
+
+    /// 转回币安返回的数组格式, 最后一个字段固定为 "0"
+    pub fn to_row(&self) -> Value {
+        serde_json::json!([
+            self.open_time,
+            self.open,
+            self.high,
+            self.low,
+            self.close,
+            self.volume,
+            self.close_time,
+            self.quote_volume,
+            self.trades,
+            self.taker_buy_base_volume,
+            self.taker_buy_quote_volume,
+            "0"
+        ])
+    }
+
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            self.open_time,
+            self.open,
+            self.high,
+            self.low,
+            self.close,
+            self.volume,
+            self.close_time,
+            self.quote_volume,
+            self.trades,
+            self.taker_buy_base_volume,
+            self.taker_buy_quote_volume
+        )
+    }
+}
+
+/// 按产品区分的 K 线客户端
+#[derive(Clone)]
+pub enum KlineClient {
+    UsdsFuture(Box<usds_future_api::RestApi>),
+    Spot(Box<spot_api::RestApi>),
+}
+
+impl KlineClient {
+    // 翻页间隔, 合约每页权重 5, 现货每页权重 2, 控制在 IP 权重上限的一半左右
+    fn page_interval(&self) -> Duration {
+        match self {
+            Self::UsdsFuture(_) => Duration::from_millis(250),
+            Self::Spot(_) => Duration::from_millis(100),
+        }
+    }
+
+    fn product(&self) -> Product {
+        match self {
+            Self::UsdsFuture(_) => Product::UsdsFuture,
+            Self::Spot(_) => Product::Spot,
+        }
+    }
+
+    /// 拉取 [start_time, end_time] 内的一页 K 线 (最多 PAGE_LIMIT 根), interval 需要先经过 `Product::kline_intervals` 校验
+    /// 请求按 key_name 的上游占用限流额度
+    pub async fn fetch(
+        &self,
+        rate_limiter: &RateLimiter,
+        key_name: Option<&str>,
+        symbol: &str,
+        interval: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<Kline>, ApiError> {
+        let invalid_interval = |_| ApiError::bad_request(format!("Invalid interval {}", interval));
+        let cost = Cost::klines(self.product(), Some(PAGE_LIMIT as u32));
+        match self {
+            Self::UsdsFuture(client) => {
+                let interval: KlineCandlestickDataIntervalEnum =
+                    serde_json::from_value(Value::from(interval)).map_err(invalid_interval)?;
+                let params = KlineCandlestickDataParams::builder(symbol.to_string(), interval)
+                    .start_time(start_time)
+                    .end_time(end_time)
+                    .limit(PAGE_LIMIT)
+                    .build()?;
+                let response = rate_limiter
+                    .call(key_name, cost, async || {
+                        client.kline_candlestick_data(params).await
+                    })
+                    .await
+                    .inspect_err(|e| error!("kline history: {} {}", symbol, e))?;
+                let rows = response.data().await.map_err(|e| {
+                    error!("Failed to get data from response: {}", e);
+                    ApiError::from(e)
+                })?;
+                Kline::from_sdk(&rows)
+            }
+            Self::Spot(client) => {
+                let interval: KlinesIntervalEnum =
+                    serde_json::from_value(Value::from(interval)).map_err(invalid_interval)?;
+                let params = KlinesParams::builder(symbol.to_string(), interval)
+                    .start_time(start_time)
+                    .end_time(end_time)
+                    .limit(PAGE_LIMIT as i32)
+                    .build()?;
+                let response = rate_limiter
+                    .call(key_name, cost, async || client.klines(params).await)
+                    .await
+                    .inspect_err(|e| error!("kline history: {} {}", symbol, e))?;
+                let rows = response.data().await.map_err(|e| {
+                    error!("Failed to get data from response: {}", e);
+                    ApiError::from(e)
+                })?;
+                Kline::from_sdk(&rows)
+            }
+        }
+    }
+}
+
+/// 按开盘时间翻页拉取 [start_time, end_time] 内的全部 K 线
+/// 下一页从上一页最后一根的开盘时间之后开始, 翻页边界重复的 K 线会被去掉
+pub struct KlinePager {
+    client: KlineClient,
+    rate_limiter: Arc<RateLimiter>,
+    key_name: Option<String>,
+    symbol: String,
+    interval: String,
+    next_start: i64,
+    end_time: i64,
+    last_open_time: Option<i64>,
+    done: bool,
+}
+
+impl KlinePager {
+    pub fn new(
+        client: KlineClient,
+        rate_limiter: Arc<RateLimiter>,
+        key_name: Option<String>,
+        symbol: String,
+        interval: String,
+        start_time: i64,
+        end_time: i64,
+    ) -> Self {
+        KlinePager {
+            client,
+            rate_limiter,
+            key_name,
+            symbol,
+            interval,
+            next_start: start_time,
+            end_time,
+            last_open_time: None,
+            done: start_time > end_time,
+        }
+    }
+
+    /// 停止翻页, 之后 `next_page` 返回 None
+    pub fn stop(mut self) -> Self {
+        self.done = true;
+        self
+    }
+
+    /// 下一页 K 线, 全部拉取完成后返回 None
+    pub async fn next_page(&mut self) -> Result<Option<Vec<Kline>>, ApiError> {
+        if self.done {
+            return Ok(None);
+        }
+        if self.last_open_time.is_some() {
+            tokio::time::sleep(self.client.page_interval()).await;
+        }
+
+        let page = self
+            .client
+            .fetch(
+                &self.rate_limiter,
+                self.key_name.as_deref(),
+                &self.symbol,
+                &self.interval,
+                self.next_start,
+                self.end_time,
+            )
+            .await?;
+        let full = page.len() as i64 >= PAGE_LIMIT;
+        let page = dedup_page(page, self.last_open_time);
+
+        match page.last() {
+            Some(last) => {
+                self.last_open_time = Some(last.open_time);
+                self.next_start = last.open_time + 1;
+                self.done = !full || self.next_start > self.end_time;
+                Ok(Some(page))
+            }
+            None => {
+                self.done = true;
+                Ok(None)
+            }
+        }
+    }
+}
+
+// 去掉不晚于上一页最后一根的 K 线
+fn dedup_page(page: Vec<Kline>, last_open_time: Option<i64>) -> Vec<Kline> {
+    match last_open_time {
+        Some(last) => page.into_iter().filter(|k| k.open_time > last).collect(),
+        None => page,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_from_sdk() {
+        let rows: Vec<Vec<Value>> = serde_json::from_value(json!([
+            [
+                1499040000000i64,
+                "0.01634790",
+                "0.80000000",
+                "0.01575800",
+                "0.01577100",
+                "148976.11427815",
+                1499644799999i64,
+                "2434.19055334",
+                308,
+                "1756.87402397",
+                "28.46694368",
+                "0"
+            ],
+            ["bad"]
+        ]))
+        .unwrap();
+        let klines = Kline::from_sdk(&rows).unwrap();
+        assert_eq!(klines.len(), 1);
+        assert_eq!(klines[0].open_time, 1499040000000);
+        assert_eq!(klines[0].trades, 308);
+        assert_eq!(
+            klines[0].to_csv(),
+            "1499040000000,0.01634790,0.80000000,0.01575800,0.01577100,148976.11427815,1499644799999,2434.19055334,308,1756.87402397,28.46694368"
+        );
+        assert_eq!(CSV_HEADER.split(',').count(), 11);
+        assert_eq!(
+            Kline::from_sdk(&[klines[0].to_row().as_array().unwrap().clone()]).unwrap(),
+            klines
+        );
+    }
+
+    #[test]
+    fn test_dedup_page() {
+        let kline = |open_time| Kline {
+            open_time,
+            open: "1".to_string(),
+            high: "1".to_string(),
+            low: "1".to_string(),
+            close: "1".to_string(),
+            volume: "1".to_string(),
+            close_time: open_time + 59_999,
+            quote_volume: "1".to_string(),
+            trades: 1,
+            taker_buy_base_volume: "1".to_string(),
+            taker_buy_quote_volume: "1".to_string(),
+        };
+        let page = vec![kline(60_000), kline(120_000), kline(180_000)];
+
+        assert_eq!(dedup_page(page.clone(), None).len(), 3);
+        let page = dedup_page(page, Some(120_000));
+        assert_eq!(page, vec![kline(180_000)]);
+    }
+}
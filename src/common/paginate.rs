@@ -0,0 +1,151 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::handler::common::ApiError;
+
+pub fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// 同一毫秒内的数据超过一页, 按时间无法翻页, 继续翻页会漏掉数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowSaturated {
+    pub time: i64,
+    pub limit: usize,
+}
+
+impl From<WindowSaturated> for ApiError {
+    fn from(e: WindowSaturated) -> Self {
+        ApiError::bad_request(format!(
+            "More than {} records at {}, unable to page by time without dropping records; use a larger limit or from_id",
+            e.limit, e.time
+        ))
+    }
+}
+
+/// 按时间分页拉取 [start_time, end_time] 内的全部数据
+/// 币安的历史接口单次查询有时间跨度限制 (window_ms), 每次最多返回 limit 条,
+/// 这里先按 window_ms 切分窗口, 窗口内返回满 limit 条时从最后一条的时间继续翻页
+pub struct TimePager {
+    pub start_time: i64,
+    pub end_time: i64,
+    pub window_ms: i64,
+    pub limit: usize,
+}
+
+impl TimePager {
+    /// fetch(start, end) 返回按时间升序的一页数据
+    /// time 取每条数据的时间, key 用于去重 (同一毫秒的数据可能跨页重复返回)
+    /// 一整页都在同一毫秒时返回 WindowSaturated
+    pub async fn collect<T, K, E, F, Fut>(
+        &self,
+        mut fetch: F,
+        time: impl Fn(&T) -> Option<i64>,
+        key: impl Fn(&T) -> K,
+    ) -> Result<Vec<T>, E>
+    where
+        K: Hash + Eq,
+        E: From<WindowSaturated>,
+        F: FnMut(i64, i64) -> Fut,
+        Fut: Future<Output = Result<Vec<T>, E>>,
+    {
+        let mut items = Vec::new();
+        let mut seen = HashSet::new();
+        let mut window_start = self.start_time;
+
+        while window_start <= self.end_time {
+            let window_end = (window_start + self.window_ms).min(self.end_time);
+            let mut page_start = window_start;
+
+            loop {
+                let page = fetch(page_start, window_end).await?;
+                let full = page.len() >= self.limit;
+                let last_time = page.last().and_then(&time);
+
+                items.extend(page.into_iter().filter(|item| seen.insert(key(item))));
+
+                match last_time {
+                    Some(last) if full && last <= page_start => {
+                        return Err(WindowSaturated {
+                            time: page_start,
+                            limit: self.limit,
+                        }
+                        .into());
+                    }
+                    Some(last) if full => page_start = last,
+                    _ => break,
+                }
+            }
+
+            window_start = window_end + 1;
+        }
+
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TimePager, WindowSaturated};
+
+    #[actix_web::test]
+    async fn test_time_pager() {
+        // 每毫秒一条数据, 共 0..=20
+        let pager = TimePager {
+            start_time: 0,
+            end_time: 20,
+            window_ms: 7,
+            limit: 3,
+        };
+        let mut calls = Vec::new();
+        let items = pager
+            .collect(
+                |start, end| {
+                    calls.push((start, end));
+                    let page: Vec<i64> = (start..=end).take(3).collect();
+                    async move { Ok::<_, WindowSaturated>(page) }
+                },
+                |item| Some(*item),
+                |item| *item,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(items, (0..=20).collect::<Vec<_>>());
+        assert!(calls.iter().all(|(start, end)| end - start <= 7));
+    }
+
+    #[actix_web::test]
+    async fn test_time_pager_saturated() {
+        // 第 5 毫秒有 4 条数据, 超过每页 3 条
+        let data = [1, 2, 5, 5, 5, 5, 6];
+        let pager = TimePager {
+            start_time: 0,
+            end_time: 10,
+            window_ms: 10,
+            limit: 3,
+        };
+        let result = pager
+            .collect(
+                |start, end| {
+                    let page: Vec<(usize, i64)> = data
+                        .iter()
+                        .copied()
+                        .enumerate()
+                        .filter(|(_, time)| (start..=end).contains(time))
+                        .take(3)
+                        .collect();
+                    async move { Ok::<_, WindowSaturated>(page) }
+                },
+                |(_, time)| Some(*time),
+                |(id, _)| *id,
+            )
+            .await;
+
+        assert_eq!(result, Err(WindowSaturated { time: 5, limit: 3 }));
+    }
+}
@@ -6,3 +6,37 @@ use serde::Deserialize;
 pub struct KeyName {
     pub key: String,
 }
+
+// 公开行情接口的 key 是可选的, 不传时使用共享的无 key 客户端
+#[derive(Deserialize)]
+pub struct OptionalKeyName {
+    pub key: Option<String>,
+}
+
+impl OptionalKeyName {
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref().filter(|key| !key.is_empty())
+    }
+}
+
+// 下单时是否按交易规则把价格和数量对齐到 tickSize / stepSize
+#[derive(Deserialize)]
+pub struct OrderRounding {
+    #[serde(default)]
+    pub round: bool,
+}
+
+// 重新提交之前结果未知的订单时传 resubmit=true
+// 网关没有该 client order id 的记录时 (如网关重启后), 先向币安查询订单是否已存在
+#[derive(Deserialize)]
+pub struct OrderResubmit {
+    #[serde(default)]
+    pub resubmit: bool,
+}
+
+// 从本地缓存读取的接口, fresh=true 时绕过缓存直接请求币安
+#[derive(Deserialize)]
+pub struct CacheControl {
+    #[serde(default)]
+    pub fresh: bool,
+}
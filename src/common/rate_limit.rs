@@ -0,0 +1,673 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use actix_web::http::StatusCode;
+use binance_sdk::models::{Interval, RateLimitType, RestApiRateLimit, RestApiResponse};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::app::{Environment, Key};
+use crate::common::paginate::now_ms;
+use crate::config::RateLimitConfig;
+use crate::handler::common::{ApiError, ErrorSource};
+use crate::stream::market::Product;
+
+const SECOND: i64 = 1000;
+const MINUTE: i64 = 60 * SECOND;
+const DAY: i64 = 24 * 60 * MINUTE;
+
+// 被币安返回 418 (IP 被封禁) 后暂停请求的时间, 币安首次封禁为 2 分钟
+const BAN_BACKOFF_MS: i64 = 2 * MINUTE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitKind {
+    // 请求权重, 按 IP 计
+    RequestWeight,
+    // 下单数, 按账户计
+    Orders,
+}
+
+struct Limit {
+    product: Product,
+    kind: LimitKind,
+    interval_ms: i64,
+    max: u32,
+}
+
+// 币安的限额, 窗口按自然时间对齐 (每分钟, 每 10 秒, 每天重置)
+const LIMITS: &[Limit] = &[
+    Limit {
+        product: Product::UsdsFuture,
+        kind: LimitKind::RequestWeight,
+        interval_ms: MINUTE,
+        max: 2400,
+    },
+    Limit {
+        product: Product::UsdsFuture,
+        kind: LimitKind::Orders,
+        interval_ms: 10 * SECOND,
+        max: 300,
+    },
+    Limit {
+        product: Product::UsdsFuture,
+        kind: LimitKind::Orders,
+        interval_ms: MINUTE,
+        max: 1200,
+    },
+    Limit {
+        product: Product::Spot,
+        kind: LimitKind::RequestWeight,
+        interval_ms: MINUTE,
+        max: 6000,
+    },
+    Limit {
+        product: Product::Spot,
+        kind: LimitKind::Orders,
+        interval_ms: 10 * SECOND,
+        max: 100,
+    },
+    Limit {
+        product: Product::Spot,
+        kind: LimitKind::Orders,
+        interval_ms: DAY,
+        max: 200_000,
+    },
+];
+
+/// 一次请求占用的权重和下单数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cost {
+    pub product: Product,
+    pub weight: u32,
+    pub orders: u32,
+}
+
+impl Cost {
+    pub fn weight(product: Product, weight: u32) -> Self {
+        Cost {
+            product,
+            weight,
+            orders: 0,
+        }
+    }
+
+    pub fn order(product: Product, weight: u32, orders: u32) -> Self {
+        Cost {
+            product,
+            weight,
+            orders,
+        }
+    }
+
+    /// K 线接口的权重, U 本位合约按 limit 计算
+    pub fn klines(product: Product, limit: Option<u32>) -> Self {
+        match product {
+            Product::UsdsFuture => Cost::weight(product, usds_future_kline_weight(limit)),
+            Product::Spot => Cost::weight(product, 2),
+        }
+    }
+
+    fn amount(&self, kind: LimitKind) -> u32 {
+        match kind {
+            LimitKind::RequestWeight => self.weight,
+            LimitKind::Orders => self.orders,
+        }
+    }
+}
+
+// K 线接口的权重按 limit 计算, 默认 limit 为 500
+fn usds_future_kline_weight(limit: Option<u32>) -> u32 {
+    match limit.unwrap_or(500) {
+        0..100 => 1,
+        100..500 => 2,
+        500..=1000 => 5,
+        _ => 10,
+    }
+}
+
+fn usds_future_depth_weight(limit: Option<u32>) -> u32 {
+    match limit.unwrap_or(500) {
+        0..=50 => 2,
+        51..=100 => 5,
+        101..=500 => 10,
+        _ => 20,
+    }
+}
+
+/// 网关路由对应的币安权重, 参见币安文档中各接口的 Weight
+/// 只能读取 query 参数, POST 表单中的 limit 等参数按默认值计算, 偏差由响应头中的已用权重校正
+/// 不需要限流的路由返回 None
+pub fn endpoint_cost(path: &str, query: &HashMap<String, String>) -> Option<Cost> {
+    let has = |name: &str| query.get(name).is_some_and(|v| !v.is_empty());
+    let limit = query.get("limit").and_then(|v| v.parse::<u32>().ok());
+    // 由网关缓存返回的接口不占用权重, 缓存未命中时的请求由响应头同步
+    let cached = query.get("fresh").is_none_or(|v| v != "true");
+
+    let (product, route) = if let Some(route) = path.strip_prefix("/usds_future/") {
+        (Product::UsdsFuture, route)
+    } else if let Some(route) = path.strip_prefix("/spot/") {
+        (Product::Spot, route)
+    } else {
+        return None;
+    };
+
+    let cost = match product {
+        Product::UsdsFuture => match route {
+            "position_information" | "account_balance" if cached => Cost::weight(product, 0),
+            "current_open_orders" if cached => Cost::weight(product, 0),
+            "exchange_information" if cached && !has("key") => Cost::weight(product, 0),
+            "account_information" | "account_balance" | "position_information" => {
+                Cost::weight(product, 5)
+            }
+            "current_open_orders" if !has("symbol") => Cost::weight(product, 40),
+            "all_orders" | "user_trades" | "trades" => Cost::weight(product, 5),
+            "income_history" => Cost::weight(product, 30),
+            "commission_rate" | "agg_trades" => Cost::weight(product, 20),
+            "kline" | "mark_price_kline" | "index_price_kline" | "premium_index_kline" => {
+                Cost::klines(product, limit)
+            }
+            "depth" => Cost::weight(product, usds_future_depth_weight(limit)),
+            "ticker_24hr" if !has("symbol") => Cost::weight(product, 40),
+            "book_ticker" if !has("symbol") => Cost::weight(product, 5),
+            "book_ticker" => Cost::weight(product, 2),
+            "mark_price" if !has("symbol") => Cost::weight(product, 10),
+            // 新订单不占用 IP 权重
+            "new_order" => Cost::order(product, 0, 1),
+            "cancel_order" | "cancel_all_open_orders" | "modify_order" => {
+                Cost::order(product, 1, 1)
+            }
+            // 批量下单最多 5 个订单
+            "batch_orders" => Cost::order(product, 5, 5),
+            _ => Cost::weight(product, 1),
+        },
+        Product::Spot => match route {
+            "exchange_information" if cached && !has("key") => Cost::weight(product, 0),
+            "exchange_information" | "all_orders" | "account" | "my_trades" | "commission_rate" => {
+                Cost::weight(product, 20)
+            }
+            "kline" => Cost::klines(product, limit),
+            "query_order" => Cost::weight(product, 4),
+            "current_open_orders" if !has("symbol") => Cost::weight(product, 80),
+            "current_open_orders" => Cost::weight(product, 6),
+            "order_rate_limit" => Cost::weight(product, 40),
+            "new_order" | "cancel_replace" => Cost::order(product, 1, 1),
+            _ => Cost::weight(product, 1),
+        },
+    };
+    Some(cost)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    start: i64,
+    used: u32,
+}
+
+// (产品, 类型, 窗口长度, 范围), 范围: 权重为上游地址, 下单数为 key 名称
+type Bucket = (Product, LimitKind, i64, String);
+
+#[derive(Default)]
+struct State {
+    windows: HashMap<Bucket, Window>,
+    // (产品, 上游地址) 暂停请求到该时间
+    blocked: HashMap<(Product, String), i64>,
+}
+
+/// 当前窗口的用量, 由 /limits 返回
+#[derive(Debug, Clone, Serialize)]
+pub struct LimitUsage {
+    pub product: &'static str,
+    pub kind: LimitKind,
+    pub scope: String,
+    pub interval: String,
+    pub used: u32,
+    pub limit: u32,
+    pub reset_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocked_until: Option<i64>,
+}
+
+/// 按币安限额在本地限流
+/// 请求权重按上游地址 (正式环境, 测试网或自定义 base_url) 计, 下单数按 key 计
+/// 请求前按接口权重占用额度, 响应后用币安返回的 X-MBX-USED-WEIGHT / X-MBX-ORDER-COUNT 校正
+pub struct RateLimiter {
+    // key 名称到上游地址
+    upstreams: HashMap<String, String>,
+    usage_ratio: f64,
+    max_wait: Duration,
+    state: Mutex<State>,
+}
+
+impl RateLimiter {
+    pub fn new(keys: &HashMap<String, Key>, config: &RateLimitConfig) -> Self {
+        let upstreams = keys
+            .iter()
+            .map(|(key_name, key)| {
+                let upstream = match (&key.base_url, key.environment) {
+                    (Some(base_url), _) => base_url.clone(),
+                    (None, Environment::Production) => "production".to_string(),
+                    (None, Environment::Testnet) => "testnet".to_string(),
+                };
+                (key_name.clone(), upstream)
+            })
+            .collect();
+
+        RateLimiter {
+            upstreams,
+            usage_ratio: config.usage_ratio.clamp(0.0, 1.0),
+            max_wait: Duration::from_millis(config.max_wait_ms),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    // 不传 key 时使用正式环境的公开客户端
+    fn upstream(&self, key_name: Option<&str>) -> String {
+        key_name
+            .and_then(|key_name| self.upstreams.get(key_name))
+            .cloned()
+            .unwrap_or_else(|| "production".to_string())
+    }
+
+    fn scope(&self, kind: LimitKind, key_name: Option<&str>) -> Option<String> {
+        match kind {
+            LimitKind::RequestWeight => Some(self.upstream(key_name)),
+            LimitKind::Orders => key_name.map(str::to_string),
+        }
+    }
+
+    fn capacity(&self, limit: &Limit) -> u32 {
+        ((limit.max as f64 * self.usage_ratio) as u32).max(1)
+    }
+
+    /// 占用额度, 额度不足且窗口在 max_wait 内重置时排队等待
+    /// 否则返回需要等待的时间, 由调用方以 429 和 Retry-After 拒绝
+    pub async fn acquire(&self, key_name: Option<&str>, cost: Cost) -> Result<(), Duration> {
+        let deadline = now_ms() + self.max_wait.as_millis() as i64;
+        loop {
+            let now = now_ms();
+            match self.try_acquire(key_name, cost, now) {
+                Ok(()) => return Ok(()),
+                Err(wait) if now + wait.as_millis() as i64 <= deadline => {
+                    tokio::time::sleep(wait).await
+                }
+                Err(wait) => return Err(wait),
+            }
+        }
+    }
+
+    fn try_acquire(&self, key_name: Option<&str>, cost: Cost, now: i64) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+
+        let upstream = (cost.product, self.upstream(key_name));
+        if let Some(&until) = state.blocked.get(&upstream)
+            && until > now
+        {
+            return Err(Duration::from_millis((until - now) as u64));
+        }
+
+        // 先检查全部窗口, 都有余量时再一起占用
+        let mut buckets = Vec::new();
+        let mut wait = 0;
+        for limit in LIMITS.iter().filter(|l| l.product == cost.product) {
+            let amount = cost.amount(limit.kind);
+            let Some(scope) = self.scope(limit.kind, key_name).filter(|_| amount > 0) else {
+                continue;
+            };
+            let start = now - now.rem_euclid(limit.interval_ms);
+            let bucket = (cost.product, limit.kind, limit.interval_ms, scope);
+            let used = state
+                .windows
+                .get(&bucket)
+                .filter(|w| w.start == start)
+                .map_or(0, |w| w.used);
+            // 空窗口总是放行, 避免单次权重超过额度的请求永远无法发出
+            if used > 0 && used + amount > self.capacity(limit) {
+                wait = wait.max(start + limit.interval_ms - now);
+            }
+            buckets.push((bucket, start, amount));
+        }
+        if wait > 0 {
+            return Err(Duration::from_millis(wait as u64));
+        }
+
+        for (bucket, start, amount) in buckets {
+            let window = state
+                .windows
+                .entry(bucket)
+                .or_insert(Window { start, used: 0 });
+            if window.start != start {
+                *window = Window { start, used: 0 };
+            }
+            window.used += amount;
+        }
+        Ok(())
+    }
+
+    /// 归还占用的额度, 用于在调用币安之前就被网关拒绝的请求 (参数校验, 风控等)
+    /// 只归还当前窗口的用量, 占用后窗口已经重置时无需归还
+    pub fn release(&self, key_name: Option<&str>, cost: Cost) {
+        self.release_at(key_name, cost, now_ms());
+    }
+
+    fn release_at(&self, key_name: Option<&str>, cost: Cost, now: i64) {
+        let mut state = self.state.lock().unwrap();
+        for limit in LIMITS.iter().filter(|l| l.product == cost.product) {
+            let amount = cost.amount(limit.kind);
+            let Some(scope) = self.scope(limit.kind, key_name).filter(|_| amount > 0) else {
+                continue;
+            };
+            let start = now - now.rem_euclid(limit.interval_ms);
+            if let Some(window) =
+                state
+                    .windows
+                    .get_mut(&(cost.product, limit.kind, limit.interval_ms, scope))
+                && window.start == start
+            {
+                window.used = window.used.saturating_sub(amount);
+            }
+        }
+    }
+
+    /// 网关自身发起的币安请求 (缓存刷新, 快照, 分页下载等), 与路由请求共用额度
+    /// 请求前占用 cost, 响应后校正用量, 币安返回 429 / 418 时暂停该上游
+    pub async fn call<T>(
+        &self,
+        key_name: Option<&str>,
+        cost: Cost,
+        call: impl AsyncFnOnce() -> anyhow::Result<RestApiResponse<T>>,
+    ) -> Result<RestApiResponse<T>, ApiError> {
+        if let Err(wait) = self.acquire(key_name, cost).await {
+            return Err(limited(cost.product, wait));
+        }
+        match call().await {
+            Ok(response) => {
+                self.record(cost.product, key_name, &response.rate_limits);
+                Ok(response)
+            }
+            Err(e) => {
+                let e = ApiError::from(e);
+                if e.source == ErrorSource::Binance
+                    && matches!(
+                        e.status,
+                        StatusCode::TOO_MANY_REQUESTS | StatusCode::IM_A_TEAPOT
+                    )
+                {
+                    self.back_off(cost.product, key_name, e.status == StatusCode::IM_A_TEAPOT);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// 用币安响应头中的用量校正本地计数
+    pub fn record(
+        &self,
+        product: Product,
+        key_name: Option<&str>,
+        rate_limits: &Option<Vec<RestApiRateLimit>>,
+    ) {
+        self.record_at(
+            product,
+            key_name,
+            rate_limits.as_deref().unwrap_or_default(),
+            now_ms(),
+        );
+    }
+
+    fn record_at(
+        &self,
+        product: Product,
+        key_name: Option<&str>,
+        rate_limits: &[RestApiRateLimit],
+        now: i64,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        for rate_limit in rate_limits {
+            let kind = match rate_limit.rate_limit_type {
+                RateLimitType::RequestWeight => LimitKind::RequestWeight,
+                RateLimitType::Orders => LimitKind::Orders,
+                RateLimitType::RawRequests => continue,
+            };
+            let unit = match rate_limit.interval {
+                Interval::Second => SECOND,
+                Interval::Minute => MINUTE,
+                Interval::Hour => 60 * MINUTE,
+                Interval::Day => DAY,
+            };
+            let interval_ms = rate_limit.interval_num as i64 * unit;
+            let tracked = LIMITS
+                .iter()
+                .any(|l| l.product == product && l.kind == kind && l.interval_ms == interval_ms);
+            let Some(scope) = self.scope(kind, key_name).filter(|_| tracked) else {
+                continue;
+            };
+
+            let start = now - now.rem_euclid(interval_ms);
+            let window = state
+                .windows
+                .entry((product, kind, interval_ms, scope))
+                .or_insert(Window { start, used: 0 });
+            if window.start != start {
+                *window = Window { start, used: 0 };
+            }
+            // 本地计数包含尚未返回的请求, 取两者中较大的值
+            window.used = window.used.max(rate_limit.count);
+
+            if let Some(retry_after) = rate_limit.retry_after {
+                state.blocked.insert(
+                    (product, self.upstream(key_name)),
+                    now + retry_after as i64 * SECOND,
+                );
+            }
+        }
+    }
+
+    /// 币安返回 429 或 418 后暂停该上游的请求
+    /// 429 暂停到当前分钟的权重窗口重置, 418 暂停 2 分钟
+    pub fn back_off(&self, product: Product, key_name: Option<&str>, banned: bool) {
+        let now = now_ms();
+        let until = if banned {
+            now + BAN_BACKOFF_MS
+        } else {
+            now - now.rem_euclid(MINUTE) + MINUTE
+        };
+        let upstream = self.upstream(key_name);
+        warn!(
+            "rate limit: {} {} rejected by Binance, pausing until {}",
+            product.as_str(),
+            upstream,
+            until
+        );
+        self.state
+            .lock()
+            .unwrap()
+            .blocked
+            .insert((product, upstream), until);
+    }
+
+    /// 当前窗口的用量, 过期的窗口不返回
+    pub fn usage(&self) -> Vec<LimitUsage> {
+        self.usage_at(now_ms())
+    }
+
+    fn usage_at(&self, now: i64) -> Vec<LimitUsage> {
+        let state = self.state.lock().unwrap();
+        let mut usage: Vec<LimitUsage> = state
+            .windows
+            .iter()
+            .filter_map(|((product, kind, interval_ms, scope), window)| {
+                let limit = LIMITS.iter().find(|l| {
+                    l.product == *product && l.kind == *kind && l.interval_ms == *interval_ms
+                })?;
+                if window.start + interval_ms <= now {
+                    return None;
+                }
+                let blocked_until = match kind {
+                    LimitKind::RequestWeight => state
+                        .blocked
+                        .get(&(*product, scope.clone()))
+                        .copied()
+                        .filter(|until| *until > now),
+                    LimitKind::Orders => None,
+                };
+                Some(LimitUsage {
+                    product: product.as_str(),
+                    kind: *kind,
+                    scope: scope.clone(),
+                    interval: interval_name(*interval_ms),
+                    used: window.used,
+                    limit: self.capacity(limit),
+                    reset_at: window.start + interval_ms,
+                    blocked_until,
+                })
+            })
+            .collect();
+        usage.sort_by(|a, b| {
+            (
+                a.product,
+                &a.scope,
+                a.kind == LimitKind::Orders,
+                &a.interval,
+            )
+                .cmp(&(
+                    b.product,
+                    &b.scope,
+                    b.kind == LimitKind::Orders,
+                    &b.interval,
+                ))
+        });
+        usage
+    }
+}
+
+/// 本地额度不足的错误, 需要等待 wait 后重试
+pub fn limited(product: Product, wait: Duration) -> ApiError {
+    ApiError::gateway(
+        StatusCode::TOO_MANY_REQUESTS,
+        format!(
+            "Local {} rate limit reached, retry after {}s",
+            product.as_str(),
+            retry_after(wait)
+        ),
+    )
+}
+
+/// Retry-After 的秒数, 向上取整
+pub fn retry_after(wait: Duration) -> u64 {
+    wait.as_secs_f64().ceil() as u64
+}
+
+fn interval_name(interval_ms: i64) -> String {
+    match interval_ms {
+        ms if ms % DAY == 0 => format!("{}d", ms / DAY),
+        ms if ms % MINUTE == 0 => format!("{}m", ms / MINUTE),
+        ms => format!("{}s", ms / SECOND),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter() -> RateLimiter {
+        let keys = HashMap::from([(
+            "sub1".to_string(),
+            Key {
+                api_key: String::new(),
+                secret: String::new(),
+                environment: Environment::Production,
+                base_url: None,
+                master: false,
+            },
+        )]);
+        RateLimiter::new(&keys, &RateLimitConfig::default())
+    }
+
+    fn query(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_endpoint_cost() {
+        let cost = |path, pairs: &[(&str, &str)]| endpoint_cost(path, &query(pairs)).unwrap();
+
+        assert_eq!(cost("/usds_future/kline", &[]).weight, 5);
+        assert_eq!(cost("/usds_future/kline", &[("limit", "1500")]).weight, 10);
+        assert_eq!(cost("/usds_future/depth", &[("limit", "5")]).weight, 2);
+        assert_eq!(cost("/usds_future/current_open_orders", &[]).weight, 0);
+        assert_eq!(
+            cost("/usds_future/current_open_orders", &[("fresh", "true")]).weight,
+            40
+        );
+        assert_eq!(
+            cost("/usds_future/new_order", &[("key", "sub1")]),
+            Cost::order(Product::UsdsFuture, 0, 1)
+        );
+        assert_eq!(
+            cost("/spot/current_open_orders", &[("symbol", "BTCUSDT")]).weight,
+            6
+        );
+        assert_eq!(cost("/spot/exchange_information", &[]).weight, 0);
+        assert_eq!(
+            cost("/spot/exchange_information", &[("fresh", "true")]).weight,
+            20
+        );
+        assert!(endpoint_cost("/klines/history", &query(&[])).is_none());
+    }
+
+    #[test]
+    fn test_try_acquire() {
+        let limiter = limiter();
+        let now = 10 * MINUTE + 30 * SECOND;
+        let order = Cost::order(Product::UsdsFuture, 0, 1);
+
+        // 下单数 10 秒窗口: 300 * 0.9 = 270
+        for _ in 0..270 {
+            assert!(limiter.try_acquire(Some("sub1"), order, now).is_ok());
+        }
+        assert_eq!(
+            limiter.try_acquire(Some("sub1"), order, now),
+            Err(Duration::from_millis(10 * SECOND as u64))
+        );
+        // 下单数按 key 计, 下一个 10 秒窗口重置
+        assert!(limiter.try_acquire(Some("sub2"), order, now).is_ok());
+        assert!(
+            limiter
+                .try_acquire(Some("sub1"), order, now + 10 * SECOND)
+                .is_ok()
+        );
+
+        // 响应头中的用量大于本地计数时以响应头为准
+        let weight = Cost::weight(Product::UsdsFuture, 5);
+        limiter.record_at(
+            Product::UsdsFuture,
+            None,
+            &[RestApiRateLimit {
+                rate_limit_type: RateLimitType::RequestWeight,
+                interval: Interval::Minute,
+                interval_num: 1,
+                count: 2158,
+                retry_after: None,
+            }],
+            now,
+        );
+        assert!(limiter.try_acquire(Some("sub1"), weight, now).is_err());
+
+        let usage = limiter.usage_at(now);
+        let used = usage
+            .iter()
+            .find(|u| u.kind == LimitKind::RequestWeight)
+            .unwrap();
+        assert_eq!((used.used, used.limit), (2158, 2160));
+        assert_eq!(used.scope, "production");
+        assert_eq!(used.interval, "1m");
+
+        // 权重按分钟对齐重置
+        assert!(limiter.try_acquire(None, weight, now + 30 * SECOND).is_ok());
+    }
+}
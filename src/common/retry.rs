@@ -0,0 +1,540 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use binance_sdk::errors::ConnectorError;
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+use crate::config::RetryConfig;
+use crate::handler::common::binance_code;
+
+// 失败的请求是否已被币安执行
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Failure {
+    // 币安明确拒绝, 请求没有被执行 (如 -1021 时间戳超出 recvWindow)
+    Rejected,
+    // 网络错误, 5xx 或后端超时, 请求可能已经被执行
+    Unknown,
+}
+
+// 可能立即成交的订单结果未知时查询订单的次数
+// 这类订单成交后不再是挂单, 币安不会因为 client order id 重复拒绝, 重复下单会再成交一次, 多等几次再确认订单不存在
+const MARKETABLE_ORDER_LOOKUPS: u32 = 3;
+
+// SDK 把网络错误包装为 ConnectorClientError, 只能按前缀识别
+const NETWORK_ERROR_PREFIXES: &[&str] = &["HTTP request failed", "Failed to get response bytes"];
+
+fn classify(e: &anyhow::Error) -> Option<Failure> {
+    match e.downcast_ref::<ConnectorError>()? {
+        ConnectorError::BadRequestError(msg) | ConnectorError::ConnectorClientError(msg) => {
+            match binance_code(msg) {
+                Some(-1021) => Some(Failure::Rejected),
+                Some(-1001 | -1007) => Some(Failure::Unknown),
+                _ if NETWORK_ERROR_PREFIXES.iter().any(|p| msg.starts_with(p)) => {
+                    Some(Failure::Unknown)
+                }
+                _ => None,
+            }
+        }
+        ConnectorError::NetworkError(_) => Some(Failure::Unknown),
+        ConnectorError::ServerError { status_code, .. }
+            if status_code.is_none_or(|status| (500..600).contains(&status)) =>
+        {
+            Some(Failure::Unknown)
+        }
+        _ => None,
+    }
+}
+
+/// 币安返回 -2013 Order does not exist
+pub fn is_order_not_found(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<ConnectorError>(),
+        Some(ConnectorError::BadRequestError(msg)) if binance_code(msg) == Some(-2013)
+    )
+}
+
+/// 币安因为 client order id 重复拒绝下单, 合约返回 -4116, 现货返回 -2010 Duplicate order sent.
+/// 币安只在同一个 id 的订单还没有结束时拒绝
+pub fn is_duplicate_order(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<ConnectorError>(),
+        Some(ConnectorError::BadRequestError(msg))
+            if binance_code(msg) == Some(-4116) || msg.starts_with("Duplicate order sent.")
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Idempotency {
+    // 查询等请求, 重复执行没有副作用, 临时错误都重试
+    Idempotent,
+    // 撤单, 调整杠杆等请求, 只在币安明确拒绝时重试
+    NonIdempotent,
+}
+
+/// 每个接口的重试统计, 由 /metrics 返回
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct RetryStats {
+    // 重试次数
+    pub retries: u64,
+    // 重试后成功的请求数
+    pub recovered: u64,
+    // 重试次数用完仍失败的请求数
+    pub exhausted: u64,
+    // 下单结果未知或币安返回 id 重复时, 按 client order id 查询到订单已存在, 没有重复下单的次数
+    pub duplicates: u64,
+}
+
+/// 下单结果: 新下的订单, 或重试前查询到的已存在订单
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Placed<T, Q> {
+    New(T),
+    Existing(Q),
+}
+
+/// 临时错误的重试策略, 等待时间按 initial_backoff 翻倍, 不超过 max_backoff
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    stats: Mutex<BTreeMap<&'static str, RetryStats>>,
+}
+
+impl RetryPolicy {
+    pub fn new(config: &RetryConfig) -> Self {
+        RetryPolicy {
+            max_retries: config.max_retries,
+            initial_backoff: Duration::from_millis(config.initial_backoff_ms),
+            max_backoff: Duration::from_millis(config.max_backoff_ms),
+            stats: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+
+    fn update(&self, operation: &'static str, f: impl FnOnce(&mut RetryStats)) {
+        f(self.stats.lock().unwrap().entry(operation).or_default());
+    }
+
+    pub fn stats(&self) -> BTreeMap<&'static str, RetryStats> {
+        self.stats.lock().unwrap().clone()
+    }
+
+    // 还可以重试时记录并等待, 返回 false 表示重试次数已用完
+    async fn before_retry(
+        &self,
+        operation: &'static str,
+        retries: &mut u32,
+        e: &anyhow::Error,
+    ) -> bool {
+        match self.next_retry(operation, retries, e) {
+            Some(backoff) => {
+                tokio::time::sleep(backoff).await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // 还可以重试时记录并返回需要等待的时间, 返回 None 表示重试次数已用完
+    fn next_retry(
+        &self,
+        operation: &'static str,
+        retries: &mut u32,
+        e: &anyhow::Error,
+    ) -> Option<Duration> {
+        if *retries >= self.max_retries {
+            if self.max_retries > 0 {
+                error!(
+                    "retry: {} failed after {} retries: {}",
+                    operation, retries, e
+                );
+                self.update(operation, |s| s.exhausted += 1);
+            }
+            return None;
+        }
+        *retries += 1;
+        let backoff = self.backoff(*retries);
+        warn!(
+            "retry: {} attempt {}/{} in {:?}: {}",
+            operation, retries, self.max_retries, backoff, e
+        );
+        self.update(operation, |s| s.retries += 1);
+        Some(backoff)
+    }
+
+    fn recovered(&self, operation: &'static str, retries: u32) {
+        if retries > 0 {
+            info!("retry: {} succeeded after {} retries", operation, retries);
+            self.update(operation, |s| s.recovered += 1);
+        }
+    }
+
+    /// 调用 SDK, 临时错误按 idempotency 决定是否重试
+    /// call 每次都会重新构建请求, 签名的 timestamp 随之更新
+    pub async fn call<T, F, Fut>(
+        &self,
+        operation: &'static str,
+        idempotency: Idempotency,
+        mut call: F,
+    ) -> anyhow::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let mut retries = 0;
+        loop {
+            let e = match call().await {
+                Ok(value) => {
+                    self.recovered(operation, retries);
+                    return Ok(value);
+                }
+                Err(e) => e,
+            };
+            let retryable = match classify(&e) {
+                Some(Failure::Rejected) => true,
+                Some(Failure::Unknown) => idempotency == Idempotency::Idempotent,
+                None => false,
+            };
+            if !retryable || !self.before_retry(operation, &mut retries, &e).await {
+                return Err(e);
+            }
+        }
+    }
+
+    /// 下单, 只有带 client order id 时才在结果未知后重试
+    /// 结果未知时先等待退避时间, 让币安处理完这笔下单, 再用 find 按 client order id 查询,
+    /// 订单已存在时直接返回, 仍然查询不到时才重新下单
+    /// marketable 为可能立即成交的订单 (市价单, 非只做 maker 的限价单), 会多查询几次
+    /// 币安返回 client order id 重复时同样按 id 查询并返回已存在的订单
+    /// find 在订单不存在时返回 None
+    /// 没有 client order id 时只在币安明确拒绝时重试
+    pub async fn place_order<T, Q, P, PFut, F, FFut>(
+        &self,
+        operation: &'static str,
+        client_order_id: Option<&str>,
+        marketable: bool,
+        mut place: P,
+        mut find: F,
+    ) -> anyhow::Result<Placed<T, Q>>
+    where
+        P: FnMut() -> PFut,
+        PFut: Future<Output = anyhow::Result<T>>,
+        F: FnMut(String) -> FFut,
+        FFut: Future<Output = anyhow::Result<Option<Q>>>,
+    {
+        let Some(client_order_id) = client_order_id else {
+            return self
+                .call(operation, Idempotency::NonIdempotent, place)
+                .await
+                .map(Placed::New);
+        };
+
+        let mut retries = 0;
+        loop {
+            let e = match place().await {
+                Ok(order) => {
+                    self.recovered(operation, retries);
+                    return Ok(Placed::New(order));
+                }
+                Err(e) => e,
+            };
+            if is_duplicate_order(&e) {
+                return match find(client_order_id.to_string()).await {
+                    Ok(Some(order)) => {
+                        warn!(
+                            "retry: {} {} is a duplicate, returning the existing order",
+                            operation, client_order_id
+                        );
+                        self.recovered(operation, retries);
+                        self.update(operation, |s| s.duplicates += 1);
+                        Ok(Placed::Existing(order))
+                    }
+                    _ => Err(e),
+                };
+            }
+            let Some(failure) = classify(&e) else {
+                return Err(e);
+            };
+
+            if failure == Failure::Rejected {
+                if !self.before_retry(operation, &mut retries, &e).await {
+                    return Err(e);
+                }
+                continue;
+            }
+
+            let lookups = if marketable {
+                MARKETABLE_ORDER_LOOKUPS
+            } else {
+                1
+            };
+            let wait = self.backoff(retries + 1);
+            for _ in 0..lookups {
+                tokio::time::sleep(wait).await;
+                match find(client_order_id.to_string()).await {
+                    Ok(Some(order)) => {
+                        warn!(
+                            "retry: {} {} was already placed, not placing it again",
+                            operation, client_order_id
+                        );
+                        self.update(operation, |s| {
+                            s.duplicates += 1;
+                            s.recovered += 1;
+                        });
+                        return Ok(Placed::Existing(order));
+                    }
+                    Ok(None) => {}
+                    // 无法确认订单状态时不能重试
+                    Err(find_error) => {
+                        error!(
+                            "retry: {} failed to look up {}: {}",
+                            operation, client_order_id, find_error
+                        );
+                        return Err(e);
+                    }
+                }
+            }
+
+            // 查询前已经等待过, 直接重新下单
+            if self.next_retry(operation, &mut retries, &e).is_none() {
+                return Err(e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::new(&RetryConfig {
+            max_retries: 2,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 2,
+        })
+    }
+
+    fn server_error() -> anyhow::Error {
+        ConnectorError::ServerError {
+            msg: "Server error: 503".to_string(),
+            status_code: Some(503),
+        }
+        .into()
+    }
+
+    fn timestamp_error() -> anyhow::Error {
+        ConnectorError::BadRequestError(
+            "Timestamp for this request is outside of the recvWindow.".to_string(),
+        )
+        .into()
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(&server_error()), Some(Failure::Unknown));
+        assert_eq!(classify(&timestamp_error()), Some(Failure::Rejected));
+        assert_eq!(
+            classify(
+                &ConnectorError::ConnectorClientError(
+                    "HTTP request failed: operation timed out".to_string()
+                )
+                .into()
+            ),
+            Some(Failure::Unknown)
+        );
+        assert_eq!(
+            classify(&ConnectorError::BadRequestError("Invalid symbol.".to_string()).into()),
+            None
+        );
+        assert!(is_order_not_found(
+            &ConnectorError::BadRequestError("Order does not exist.".to_string()).into()
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_call() {
+        let policy = policy();
+        let attempts = Cell::new(0);
+
+        // 查询: 5xx 重试后成功
+        let result = policy
+            .call("query", Idempotency::Idempotent, || async {
+                attempts.set(attempts.get() + 1);
+                if attempts.get() < 3 {
+                    Err(server_error())
+                } else {
+                    Ok(attempts.get())
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+
+        // 撤单: 5xx 结果未知, 不重试
+        attempts.set(0);
+        let result: anyhow::Result<()> = policy
+            .call("cancel", Idempotency::NonIdempotent, || async {
+                attempts.set(attempts.get() + 1);
+                Err(server_error())
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.get(), 1);
+
+        // -1021 没有被执行, 可以重试, 重试次数用完后返回错误
+        attempts.set(0);
+        let result: anyhow::Result<()> = policy
+            .call("cancel", Idempotency::NonIdempotent, || async {
+                attempts.set(attempts.get() + 1);
+                Err(timestamp_error())
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.get(), 3);
+
+        let stats = policy.stats();
+        assert_eq!(stats["query"].retries, 2);
+        assert_eq!(stats["query"].recovered, 1);
+        assert_eq!(stats["cancel"].exhausted, 1);
+    }
+
+    #[actix_web::test]
+    async fn test_place_order() {
+        let policy = policy();
+        let placed = Cell::new(0);
+        let exists = Cell::new(false);
+
+        // 第一次下单超时但实际已成交, 查询到订单后不再重复下单
+        let result = policy
+            .place_order(
+                "new_order",
+                Some("bot1-1"),
+                false,
+                || async {
+                    placed.set(placed.get() + 1);
+                    exists.set(true);
+                    Err::<&str, _>(server_error())
+                },
+                |_| async { Ok(exists.get().then_some("existing")) },
+            )
+            .await
+            .unwrap();
+        assert!(matches!(result, Placed::Existing("existing")));
+        assert_eq!(placed.get(), 1);
+
+        // 订单不存在时重新下单
+        placed.set(0);
+        let result = policy
+            .place_order(
+                "new_order",
+                Some("bot1-2"),
+                false,
+                || async {
+                    placed.set(placed.get() + 1);
+                    if placed.get() == 1 {
+                        Err(server_error())
+                    } else {
+                        Ok("new")
+                    }
+                },
+                |_| async { Ok::<Option<&str>, _>(None) },
+            )
+            .await
+            .unwrap();
+        assert!(matches!(result, Placed::New("new")));
+        assert_eq!(placed.get(), 2);
+
+        // 没有 client order id 时结果未知不重试
+        placed.set(0);
+        let result = policy
+            .place_order(
+                "new_order",
+                None,
+                false,
+                || async {
+                    placed.set(placed.get() + 1);
+                    Err::<&str, _>(server_error())
+                },
+                |_| async { Ok::<Option<&str>, _>(None) },
+            )
+            .await;
+        assert!(result.is_err());
+        assert_eq!(placed.get(), 1);
+
+        assert_eq!(policy.stats()["new_order"].duplicates, 1);
+    }
+
+    #[actix_web::test]
+    async fn test_place_order_duplicate() {
+        let policy = policy();
+        let duplicate = || async {
+            Err::<&str, _>(
+                ConnectorError::BadRequestError("ClientOrderId is duplicated.".to_string()).into(),
+            )
+        };
+
+        // 币安返回 id 重复时按 id 查询, 返回已存在的订单
+        let result = policy
+            .place_order("new_order", Some("bot1-5"), false, duplicate, |_| async {
+                Ok(Some("existing"))
+            })
+            .await
+            .unwrap();
+        assert!(matches!(result, Placed::Existing("existing")));
+
+        // 查询不到时返回币安的错误
+        let result = policy
+            .place_order("new_order", Some("bot1-6"), false, duplicate, |_| async {
+                Ok::<Option<&str>, _>(None)
+            })
+            .await;
+        assert!(is_duplicate_order(&result.unwrap_err()));
+        assert!(is_duplicate_order(
+            &ConnectorError::BadRequestError("Duplicate order sent.".to_string()).into()
+        ));
+        assert_eq!(policy.stats()["new_order"].duplicates, 1);
+    }
+
+    #[actix_web::test]
+    async fn test_place_order_lookup_delay() {
+        let policy = policy();
+        let placed = Cell::new(0);
+        let lookups = Cell::new(0);
+        let place = || async {
+            placed.set(placed.get() + 1);
+            lookups.set(0);
+            Err::<&str, _>(server_error())
+        };
+        // 币安处理下单有延迟, 第三次查询时订单才出现
+        let find = |_| async {
+            lookups.set(lookups.get() + 1);
+            Ok((lookups.get() >= 3).then_some("existing"))
+        };
+
+        // 可能立即成交的订单多次查询, 订单出现后不再重复下单
+        let result = policy
+            .place_order("marketable_order", Some("bot1-3"), true, place, find)
+            .await
+            .unwrap();
+        assert!(matches!(result, Placed::Existing("existing")));
+        assert_eq!(placed.get(), 1);
+
+        // 只做 maker 的订单不会立即成交, 只查询一次, 查询不到时重新下单
+        placed.set(0);
+        let result = policy
+            .place_order("post_only_order", Some("bot1-4"), false, place, find)
+            .await;
+        assert!(result.is_err());
+        assert_eq!(placed.get(), 3);
+        assert_eq!(policy.stats()["post_only_order"].exhausted, 1);
+    }
+}
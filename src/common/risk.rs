@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use actix_web::http::StatusCode;
+use rust_decimal::Decimal;
+
+use crate::config::RiskRules;
+use crate::handler::common::ApiError;
+
+/// 下单前需要检查的订单, 持仓, 挂单数和标记价格由调用方按 `RiskRules::needs_*` 按需查询
+#[derive(Debug, Clone, Default)]
+pub struct OrderRisk {
+    pub symbol: String,
+    pub is_buy: bool,
+    // reduce_only 或 close_position, 只会减少持仓
+    pub reduce_only: bool,
+    pub quantity: Option<Decimal>,
+    pub price: Option<Decimal>,
+    pub stop_price: Option<Decimal>,
+    pub mark_price: Option<Decimal>,
+    // BOTH / LONG / SHORT
+    pub position_side: String,
+    // 同一持仓方向的当前持仓数量, 空头为负数
+    pub position_amt: Decimal,
+    // 全部交易对的当前挂单数
+    pub open_orders: usize,
+    // 同一交易对, 同一方向和持仓方向的挂单未成交数量, 不含只减仓的挂单
+    pub open_order_qty: Decimal,
+}
+
+// 下单结束后预留保持的时间, 等待用户数据流把订单同步到账户缓存
+const RESERVATION_TTL: Duration = Duration::from_secs(2);
+
+// 已通过检查但还没有同步到账户缓存的订单
+struct Pending {
+    id: u64,
+    symbol: String,
+    is_buy: bool,
+    position_side: String,
+    quantity: Decimal,
+    // 下单结束前为 None, 不会过期
+    expires_at: Option<Instant>,
+}
+
+/// 按 key 配置的风控规则
+/// 检查通过的订单在下单期间和结束后的 RESERVATION_TTL 内计入挂单数和挂单数量,
+/// 避免同一个 key 的并发下单读到相同的账户数据而同时通过检查
+pub struct RiskEngine {
+    rules: HashMap<String, RiskRules>,
+    pending: Mutex<HashMap<String, Vec<Pending>>>,
+    next_id: AtomicU64,
+}
+
+impl RiskEngine {
+    pub fn new(rules: HashMap<String, RiskRules>) -> Self {
+        RiskEngine {
+            rules,
+            pending: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// key 的风控规则, 没有单独配置时使用 "*", 都没有时为 None
+    pub fn rules(&self, key_name: &str) -> Option<&RiskRules> {
+        self.rules.get(key_name).or_else(|| self.rules.get("*"))
+    }
+
+    /// 计入该 key 预留的订单后检查, 通过时为订单预留挂单名额和数量
+    /// 预留的订单可能已经同步到账户缓存, 过期前会被重复计入, 结果偏保守
+    pub fn reserve(
+        &self,
+        key_name: &str,
+        rules: &RiskRules,
+        order: &OrderRisk,
+    ) -> Result<Reservation<'_>, ApiError> {
+        let mut pending = self.pending.lock().unwrap();
+        let reserved = pending.entry(key_name.to_string()).or_default();
+        let now = Instant::now();
+        reserved.retain(|p| p.expires_at.is_none_or(|expires_at| expires_at > now));
+
+        let mut order = order.clone();
+        order.open_orders += reserved.len();
+        order.open_order_qty += reserved
+            .iter()
+            .filter(|p| {
+                p.symbol == order.symbol
+                    && p.is_buy == order.is_buy
+                    && p.position_side == order.position_side
+            })
+            .map(|p| p.quantity)
+            .sum::<Decimal>();
+        rules.check_order(&order)?;
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        reserved.push(Pending {
+            id,
+            quantity: order
+                .quantity
+                .filter(|_| !order.reduce_only)
+                .unwrap_or_default(),
+            symbol: order.symbol,
+            is_buy: order.is_buy,
+            position_side: order.position_side,
+            expires_at: None,
+        });
+        Ok(Reservation {
+            engine: self,
+            key_name: key_name.to_string(),
+            id,
+            keep: true,
+        })
+    }
+
+    fn release(&self, key_name: &str, id: u64, keep: bool) {
+        let mut pending = self.pending.lock().unwrap();
+        let Some(reserved) = pending.get_mut(key_name) else {
+            return;
+        };
+        if keep {
+            if let Some(p) = reserved.iter_mut().find(|p| p.id == id) {
+                p.expires_at = Some(Instant::now() + RESERVATION_TTL);
+            }
+        } else {
+            reserved.retain(|p| p.id != id);
+        }
+    }
+}
+
+/// RiskEngine::reserve 预留的订单, drop 时开始计算过期时间
+/// 请求被取消时订单可能已经发送到币安, 所以默认保留到过期, 确认没有发送时调用 cancel 立即释放
+pub struct Reservation<'a> {
+    engine: &'a RiskEngine,
+    key_name: String,
+    id: u64,
+    keep: bool,
+}
+
+impl Reservation<'_> {
+    pub fn cancel(mut self) {
+        self.keep = false;
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.engine.release(&self.key_name, self.id, self.keep);
+    }
+}
+
+impl RiskRules {
+    pub fn needs_open_orders(&self) -> bool {
+        self.max_open_orders.is_some()
+    }
+
+    pub fn needs_position(&self, order: &OrderRisk) -> bool {
+        self.max_position_notional.is_some() && !order.reduce_only
+    }
+
+    pub fn needs_mark_price(&self, order: &OrderRisk) -> bool {
+        let has_price = order.price.is_some() || order.stop_price.is_some();
+        (self.price_band.is_some() && has_price)
+            || (self.max_order_notional.is_some() && !order.reduce_only && order.price.is_none())
+            || self.needs_position(order)
+    }
+
+    fn check_symbol(&self, symbol: &str) -> Result<(), ApiError> {
+        if self.denied_symbols.iter().any(|s| s == symbol) {
+            return Err(ApiError::risk(
+                "denied_symbols",
+                format!("{} is in denied_symbols", symbol),
+            ));
+        }
+        if !self.allowed_symbols.is_empty() && !self.allowed_symbols.iter().any(|s| s == symbol) {
+            return Err(ApiError::risk(
+                "allowed_symbols",
+                format!("{} is not in allowed_symbols", symbol),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 按顺序检查交易对, 挂单数, 价格偏离, 订单名义价值和持仓名义价值, 返回第一条不满足的规则
+    /// 需要标记价格但获取失败时返回 503
+    pub fn check_order(&self, order: &OrderRisk) -> Result<(), ApiError> {
+        self.check_symbol(&order.symbol)?;
+
+        if let Some(max) = self.max_open_orders
+            && order.open_orders >= max
+        {
+            return Err(ApiError::risk(
+                "max_open_orders",
+                format!("{} open orders, at most {} allowed", order.open_orders, max),
+            ));
+        }
+
+        let mark_price = match order.mark_price {
+            Some(mark_price) if !mark_price.is_zero() => Some(mark_price),
+            _ if self.needs_mark_price(order) => {
+                return Err(ApiError::gateway(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    format!(
+                        "Mark price of {} is unavailable, unable to check risk rules",
+                        order.symbol
+                    ),
+                ));
+            }
+            _ => None,
+        };
+
+        if let (Some(band), Some(mark_price)) = (self.price_band, mark_price) {
+            for (field, value) in [("price", order.price), ("stop_price", order.stop_price)] {
+                if let Some(value) = value
+                    && (value - mark_price).abs() / mark_price > band
+                {
+                    return Err(ApiError::risk(
+                        "price_band",
+                        format!(
+                            "{} {} is more than {}% away from mark price {}",
+                            field,
+                            value,
+                            (band * Decimal::ONE_HUNDRED).normalize(),
+                            mark_price
+                        ),
+                    ));
+                }
+            }
+        }
+
+        let Some(quantity) = order.quantity.filter(|_| !order.reduce_only) else {
+            return Ok(());
+        };
+
+        if let Some(max) = self.max_order_notional
+            && let Some(price) = order.price.or(mark_price)
+            && quantity * price > max
+        {
+            return Err(ApiError::risk(
+                "max_order_notional",
+                format!(
+                    "order notional {} exceeds {}",
+                    (quantity * price).normalize(),
+                    max
+                ),
+            ));
+        }
+
+        // 按同方向的挂单和该订单全部成交后的持仓计算, 只检查会增加持仓的订单
+        if let Some(max) = self.max_position_notional
+            && let Some(mark_price) = mark_price
+        {
+            let filled = order.open_order_qty + quantity;
+            let after = if order.is_buy {
+                order.position_amt + filled
+            } else {
+                order.position_amt - filled
+            };
+            if after.abs() > order.position_amt.abs() && after.abs() * mark_price > max {
+                return Err(ApiError::risk(
+                    "max_position_notional",
+                    format!(
+                        "{} position notional {} after open orders and this order exceeds {}",
+                        order.symbol,
+                        (after.abs() * mark_price).normalize(),
+                        max
+                    ),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn check_leverage(&self, symbol: &str, leverage: i64) -> Result<(), ApiError> {
+        self.check_symbol(symbol)?;
+
+        if let Some(max) = self.max_leverage
+            && leverage > max
+        {
+            return Err(ApiError::risk(
+                "max_leverage",
+                format!("leverage {} exceeds {}", leverage, max),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> RiskRules {
+        RiskRules {
+            max_order_notional: Some(Decimal::from(10_000)),
+            max_position_notional: Some(Decimal::from(50_000)),
+            max_open_orders: Some(10),
+            allowed_symbols: vec!["BTCUSDT".to_string(), "ETHUSDT".to_string()],
+            denied_symbols: vec!["ETHUSDT".to_string()],
+            max_leverage: Some(20),
+            price_band: Some(Decimal::new(5, 2)),
+        }
+    }
+
+    fn order() -> OrderRisk {
+        OrderRisk {
+            symbol: "BTCUSDT".to_string(),
+            is_buy: true,
+            quantity: Some(Decimal::new(1, 1)),
+            price: Some(Decimal::from(60_000)),
+            mark_price: Some(Decimal::from(60_000)),
+            ..Default::default()
+        }
+    }
+
+    fn rule(result: Result<(), ApiError>) -> Option<&'static str> {
+        result.err().map(|e| e.rule.unwrap_or("none"))
+    }
+
+    #[test]
+    fn test_check_order() {
+        let rules = rules();
+        assert_eq!(rule(rules.check_order(&order())), None);
+
+        for (order, expected) in [
+            (
+                OrderRisk {
+                    symbol: "ETHUSDT".to_string(),
+                    ..order()
+                },
+                "denied_symbols",
+            ),
+            (
+                OrderRisk {
+                    symbol: "SOLUSDT".to_string(),
+                    ..order()
+                },
+                "allowed_symbols",
+            ),
+            (
+                OrderRisk {
+                    open_orders: 10,
+                    ..order()
+                },
+                "max_open_orders",
+            ),
+            (
+                OrderRisk {
+                    price: Some(Decimal::from(66_000)),
+                    quantity: Some(Decimal::new(1, 2)),
+                    ..order()
+                },
+                "price_band",
+            ),
+            (
+                OrderRisk {
+                    quantity: Some(Decimal::new(2, 1)),
+                    ..order()
+                },
+                "max_order_notional",
+            ),
+            (
+                OrderRisk {
+                    position_amt: Decimal::new(8, 1),
+                    ..order()
+                },
+                "max_position_notional",
+            ),
+            (
+                OrderRisk {
+                    open_order_qty: Decimal::new(8, 1),
+                    ..order()
+                },
+                "max_position_notional",
+            ),
+        ] {
+            assert_eq!(rule(rules.check_order(&order)), Some(expected));
+        }
+
+        // 减仓的订单不检查名义价值
+        let reduce = OrderRisk {
+            is_buy: false,
+            reduce_only: true,
+            quantity: Some(Decimal::ONE),
+            position_amt: Decimal::ONE,
+            ..order()
+        };
+        assert!(!rules.needs_position(&reduce));
+        assert_eq!(rule(rules.check_order(&reduce)), None);
+
+        // 市价单用标记价格计算名义价值, 标记价格不可用时拒绝
+        let market = OrderRisk {
+            price: None,
+            mark_price: None,
+            ..order()
+        };
+        assert!(rules.needs_mark_price(&market));
+        let err = rules.check_order(&market).unwrap_err();
+        assert_eq!(err.status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[test]
+    fn test_reserve() {
+        let rules = RiskRules {
+            max_open_orders: Some(2),
+            max_position_notional: Some(Decimal::from(50_000)),
+            ..Default::default()
+        };
+        let engine = RiskEngine::new(HashMap::new());
+        let order = OrderRisk {
+            quantity: Some(Decimal::new(5, 1)),
+            ..order()
+        };
+
+        // 预留的订单计入之后的检查, 其他 key 不受影响
+        let first = engine.reserve("bot1", &rules, &order).unwrap();
+        assert_eq!(
+            rule(engine.reserve("bot1", &rules, &order).map(drop)),
+            Some("max_position_notional")
+        );
+        let short = OrderRisk {
+            is_buy: false,
+            ..order.clone()
+        };
+        let second = engine.reserve("bot1", &rules, &short).unwrap();
+        assert_eq!(
+            rule(engine.reserve("bot1", &rules, &short).map(drop)),
+            Some("max_open_orders")
+        );
+        assert!(engine.reserve("bot2", &rules, &order).is_ok());
+
+        // cancel 立即释放, drop 后保留到过期
+        second.cancel();
+        drop(first);
+        assert_eq!(
+            rule(engine.reserve("bot1", &rules, &order).map(drop)),
+            Some("max_position_notional")
+        );
+        assert!(engine.reserve("bot1", &rules, &short).is_ok());
+    }
+
+    #[test]
+    fn test_check_leverage() {
+        let rules = rules();
+        assert_eq!(rule(rules.check_leverage("BTCUSDT", 20)), None);
+        assert_eq!(
+            rule(rules.check_leverage("BTCUSDT", 21)),
+            Some("max_leverage")
+        );
+        assert_eq!(
+            rule(rules.check_leverage("ETHUSDT", 5)),
+            Some("denied_symbols")
+        );
+
+        let engine = RiskEngine::new(HashMap::from([("*".to_string(), rules)]));
+        assert!(engine.rules("bot1").is_some());
+        assert!(RiskEngine::new(HashMap::new()).rules("bot1").is_none());
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::common::paginate::now_ms;
+use crate::stream::market::Product;
+
+// 下单结果保留的时间, 超过后重复的请求改为向币安查询
+const SUBMISSION_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// 生成 client order id, 格式 qe-{毫秒时间戳}-{随机数}, 符合合约和现货的字符集和 36 位长度限制
+pub fn new_client_order_id() -> String {
+    let random = RandomState::new().build_hasher().finish();
+    format!("qe-{:x}-{:016x}", now_ms(), random)
+}
+
+type SubmissionKey = (Product, String, String);
+
+enum State {
+    // 正在下单
+    Pending,
+    // 下单完成, 保存返回给调用方的响应
+    Done(Value),
+}
+
+struct Entry {
+    state: State,
+    created_at: Instant,
+}
+
+/// 开始下单前查询 client order id 的状态
+pub enum Begin<'a> {
+    // 第一次提交, 下单完成后调用 Submission::finish 保存结果
+    New(Submission<'a>),
+    // 同一个 id 的请求正在下单
+    Pending,
+    // 已经下过单, 返回当时的响应
+    Done(Value),
+}
+
+/// 按 (产品, key, client order id) 记录最近的下单请求, 重复的请求直接返回第一次的结果
+pub struct OrderSubmissions {
+    entries: Mutex<HashMap<SubmissionKey, Entry>>,
+    // 按创建时间排序, 用于清理过期的记录
+    expiry: Mutex<VecDeque<(Instant, SubmissionKey)>>,
+    ttl: Duration,
+}
+
+impl OrderSubmissions {
+    pub fn new() -> Self {
+        Self::with_ttl(SUBMISSION_TTL)
+    }
+
+    fn with_ttl(ttl: Duration) -> Self {
+        OrderSubmissions {
+            entries: Mutex::new(HashMap::new()),
+            expiry: Mutex::new(VecDeque::new()),
+            ttl,
+        }
+    }
+
+    pub fn begin(&self, product: Product, key_name: &str, client_order_id: &str) -> Begin<'_> {
+        let now = Instant::now();
+        let key = (product, key_name.to_string(), client_order_id.to_string());
+        let mut entries = self.entries.lock().unwrap();
+        let mut expiry = self.expiry.lock().unwrap();
+
+        while let Some((created_at, _)) = expiry.front() {
+            if now.duration_since(*created_at) < self.ttl {
+                break;
+            }
+            let (created_at, key) = expiry.pop_front().unwrap();
+            // 记录被删除后可能又以同一个 id 重新创建, 只删除过期的那一条
+            if entries
+                .get(&key)
+                .is_some_and(|e| e.created_at == created_at)
+            {
+                entries.remove(&key);
+            }
+        }
+
+        match entries.get(&key).map(|e| &e.state) {
+            Some(State::Pending) => Begin::Pending,
+            Some(State::Done(response)) => Begin::Done(response.clone()),
+            None => {
+                entries.insert(
+                    key.clone(),
+                    Entry {
+                        state: State::Pending,
+                        created_at: now,
+                    },
+                );
+                expiry.push_back((now, key.clone()));
+                Begin::New(Submission {
+                    submissions: self,
+                    key: Some(key),
+                })
+            }
+        }
+    }
+}
+
+impl Default for OrderSubmissions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 正在进行的下单, 没有调用 finish 就被丢弃时删除记录, 同一个 id 可以重新提交
+/// 下单失败时丢弃, 客户端断开连接时 actix 取消 handler 同样会丢弃, 此时订单可能已经发到币安,
+/// 调用方需要用同一个 id 带 resubmit=true 重新提交, 由网关向币安查询, 不能换新的 id
+pub struct Submission<'a> {
+    submissions: &'a OrderSubmissions,
+    key: Option<SubmissionKey>,
+}
+
+impl Submission<'_> {
+    /// 保存下单成功的响应
+    pub fn finish(mut self, response: &Value) {
+        if let Some(key) = self.key.take()
+            && let Some(entry) = self.submissions.entries.lock().unwrap().get_mut(&key)
+        {
+            entry.state = State::Done(response.clone());
+        }
+    }
+}
+
+impl Drop for Submission<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.submissions.entries.lock().unwrap().remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_new_client_order_id() {
+        let id = new_client_order_id();
+        assert!(id.len() <= 36);
+        assert!(
+            id.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        );
+        assert_ne!(id, new_client_order_id());
+    }
+
+    #[test]
+    fn test_begin() {
+        let submissions = OrderSubmissions::new();
+
+        let Begin::New(submission) = submissions.begin(Product::UsdsFuture, "bot1", "a") else {
+            panic!("expected a new submission");
+        };
+        assert!(matches!(
+            submissions.begin(Product::UsdsFuture, "bot1", "a"),
+            Begin::Pending
+        ));
+        // 不同 key 或产品的同名 id 互不影响
+        assert!(matches!(
+            submissions.begin(Product::UsdsFuture, "bot2", "a"),
+            Begin::New(_)
+        ));
+        assert!(matches!(
+            submissions.begin(Product::Spot, "bot1", "a"),
+            Begin::New(_)
+        ));
+
+        submission.finish(&json!({"orderId": 1}));
+        assert!(matches!(
+            submissions.begin(Product::UsdsFuture, "bot1", "a"),
+            Begin::Done(response) if response == json!({"orderId": 1})
+        ));
+
+        // 下单失败时删除记录, 可以用同一个 id 重新提交
+        let Begin::New(submission) = submissions.begin(Product::UsdsFuture, "bot1", "b") else {
+            panic!("expected a new submission");
+        };
+        drop(submission);
+        assert!(matches!(
+            submissions.begin(Product::UsdsFuture, "bot1", "b"),
+            Begin::New(_)
+        ));
+    }
+
+    #[test]
+    fn test_expiry() {
+        let submissions = OrderSubmissions::with_ttl(Duration::ZERO);
+        if let Begin::New(submission) = submissions.begin(Product::UsdsFuture, "bot1", "a") {
+            submission.finish(&json!({"orderId": 1}));
+        }
+        assert!(matches!(
+            submissions.begin(Product::UsdsFuture, "bot1", "a"),
+            Begin::New(_)
+        ));
+    }
+}
@@ -1,6 +1,11 @@
+use std::collections::HashMap;
+
 use config::{Config, File};
+use rust_decimal::Decimal;
 use serde::Deserialize;
 
+use crate::stream::market::Product;
+
 #[derive(Debug, Deserialize)]
 pub struct ServerConfig {
     pub host: String,
@@ -21,10 +26,188 @@ pub struct ProxyConfig {
     pub auth: Option<ProxyAuth>,
 }
 
+// 权限等级, 高等级包含低等级的全部权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    // 公开行情数据
+    MarketData,
+    // 账户, 持仓等只读信息
+    Account,
+    // 下单, 调整杠杆等交易操作
+    Trade,
+    // 母子账户之间的资金划转
+    Transfer,
+}
+
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::MarketData => "market_data",
+            Permission::Account => "account",
+            Permission::Trade => "trade",
+            Permission::Transfer => "transfer",
+        }
+    }
+}
+
+fn default_permission() -> Permission {
+    Permission::MarketData
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthToken {
+    pub name: String,
+    pub token: String,
+    // 允许使用的 keys.toml 中的 key 名称, "*" 表示全部
+    #[serde(default)]
+    pub keys: Vec<String>,
+    #[serde(default = "default_permission")]
+    pub permission: Permission,
+}
+
+impl AuthToken {
+    pub fn allows_key(&self, key_name: &str) -> bool {
+        self.keys.iter().any(|k| k == "*" || k == key_name)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub tokens: Vec<AuthToken>,
+}
+
+// 需要保存到本地的 K 线
+#[derive(Debug, Clone, Deserialize)]
+pub struct KlineSeries {
+    #[serde(default)]
+    pub product: Product,
+    pub symbol: String,
+    pub interval: String,
+    // 从该时间开始保存 (毫秒)
+    pub start_time: i64,
+}
+
+fn default_kline_store_path() -> String {
+    "klines.db".to_string()
+}
+
+fn default_sync_interval_secs() -> u64 {
+    60
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct KlineStoreConfig {
+    // SQLite 文件路径
+    #[serde(default = "default_kline_store_path")]
+    pub path: String,
+    // 增量同步间隔
+    #[serde(default = "default_sync_interval_secs")]
+    pub sync_interval_secs: u64,
+    #[serde(default)]
+    pub series: Vec<KlineSeries>,
+}
+
+fn default_usage_ratio() -> f64 {
+    0.9
+}
+
+fn default_max_wait_ms() -> u64 {
+    1000
+}
+
+// 本地限流, 在币安的权重和下单数限额之前拦截请求
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimitConfig {
+    // 只使用币安限额的这一比例, 余下的留给后台任务 (交易规则刷新, K 线同步等)
+    #[serde(default = "default_usage_ratio")]
+    pub usage_ratio: f64,
+    // 额度不足时, 窗口在该时间内重置则排队等待, 否则直接返回 429
+    #[serde(default = "default_max_wait_ms")]
+    pub max_wait_ms: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            usage_ratio: default_usage_ratio(),
+            max_wait_ms: default_max_wait_ms(),
+        }
+    }
+}
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    200
+}
+
+fn default_max_backoff_ms() -> u64 {
+    2000
+}
+
+// 网络错误, 5xx 和 -1021 时间戳错误的重试策略
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetryConfig {
+    // 首次请求之外最多重试的次数, 0 表示不重试
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    // 每次重试前等待的时间翻倍, 不超过 max_backoff_ms
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_retries: default_max_retries(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+        }
+    }
+}
+
+// 单个 key 的 U 本位合约风控规则, 未设置的规则不检查
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RiskRules {
+    // 单笔订单名义价值上限 (USDT)
+    pub max_order_notional: Option<Decimal>,
+    // 订单成交后单个交易对的持仓名义价值上限 (USDT)
+    pub max_position_notional: Option<Decimal>,
+    // 全部交易对的挂单数上限
+    pub max_open_orders: Option<usize>,
+    // 允许交易的交易对, 为空表示不限制
+    #[serde(default)]
+    pub allowed_symbols: Vec<String>,
+    // 禁止交易的交易对
+    #[serde(default)]
+    pub denied_symbols: Vec<String>,
+    // change_initial_leverage 允许设置的最大杠杆
+    pub max_leverage: Option<i64>,
+    // price / stop_price 偏离标记价格的最大比例, 0.05 表示 ±5%
+    pub price_band: Option<Decimal>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct AppConfig {
     pub server: ServerConfig,
     pub proxy: Option<ProxyConfig>,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    // 不配置时不保存 K 线
+    pub kline_store: Option<KlineStoreConfig>,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    // key 名称到风控规则的映射, "*" 用于没有单独配置的 key
+    #[serde(default)]
+    pub risk: HashMap<String, RiskRules>,
 }
 
 pub fn load_config() -> Result<AppConfig, config::ConfigError> {
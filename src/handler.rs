@@ -2,7 +2,13 @@ use actix_web::{HttpResponse, Responder, get, post};
 
 pub mod usds_future;
 pub mod spot;
-mod common;
+pub mod sub_account;
+pub mod klines;
+pub mod limits;
+pub mod metrics;
+pub mod portfolio;
+pub mod ws;
+pub(crate) mod common;
 
 #[get("/")]
 async fn index() -> impl Responder {
@@ -1,7 +1,308 @@
 use crate::app::AppState;
-use actix_web::web;
+use crate::common::kline_store::KlineQuery;
+use actix_web::error::{JsonPayloadError, QueryPayloadError, UrlencodedError};
+use actix_web::http::StatusCode;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{HttpResponse, ResponseError, web};
+use binance_sdk::errors::ConnectorError;
+use binance_sdk::models::ParamBuildError;
+use serde::Serialize;
 use tracing::error;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorSource {
+    // 币安返回的错误
+    Binance,
+    // 网关自身产生的错误 (鉴权, 参数校验等)
+    Gateway,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub reason: String,
+}
+
+/// 统一的错误响应, 序列化为 `{"code": -2019, "msg": "...", "source": "binance"}`
+/// code 为币安错误码, 无法识别时为 null
+/// 参数校验失败时额外返回 `fields`, 列出每个缺失或不合法的参数
+/// 风控拒绝时额外返回 `rule`, 即触发的规则名称
+#[derive(Debug, Serialize)]
+pub struct ApiError {
+    #[serde(skip)]
+    pub status: StatusCode,
+    pub code: Option<i64>,
+    pub msg: String,
+    pub source: ErrorSource,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<&'static str>,
+}
+
+impl ApiError {
+    pub fn gateway(status: StatusCode, msg: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            code: None,
+            msg: msg.into(),
+            source: ErrorSource::Gateway,
+            fields: Vec::new(),
+            rule: None,
+        }
+    }
+
+    pub fn invalid_params(fields: Vec<FieldError>) -> Self {
+        let detail = fields
+            .iter()
+            .map(|f| format!("{} ({})", f.field, f.reason))
+            .collect::<Vec<_>>()
+            .join(", ");
+        ApiError {
+            fields,
+            ..Self::bad_request(format!("Invalid parameters: {}", detail))
+        }
+    }
+
+    pub fn bad_request(msg: impl Into<String>) -> Self {
+        Self::gateway(StatusCode::BAD_REQUEST, msg)
+    }
+
+    pub fn unauthorized(msg: impl Into<String>) -> Self {
+        Self::gateway(StatusCode::UNAUTHORIZED, msg)
+    }
+
+    pub fn forbidden(msg: impl Into<String>) -> Self {
+        Self::gateway(StatusCode::FORBIDDEN, msg)
+    }
+
+    pub fn conflict(msg: impl Into<String>) -> Self {
+        Self::gateway(StatusCode::CONFLICT, msg)
+    }
+
+    pub fn risk(rule: &'static str, msg: impl Into<String>) -> Self {
+        ApiError {
+            rule: Some(rule),
+            ..Self::gateway(StatusCode::UNPROCESSABLE_ENTITY, msg)
+        }
+    }
+
+    fn binance(status: StatusCode, msg: String) -> Self {
+        ApiError {
+            status,
+            code: binance_code(&msg),
+            msg,
+            source: ErrorSource::Binance,
+            fields: Vec::new(),
+            rule: None,
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "{} ({})", self.msg, code),
+            None => write!(f, "{}", self.msg),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status).json(self)
+    }
+}
+
+impl From<ConnectorError> for ApiError {
+    fn from(e: ConnectorError) -> Self {
+        match e {
+            ConnectorError::BadRequestError(msg) => Self::binance(StatusCode::BAD_REQUEST, msg),
+            ConnectorError::UnauthorizedError(msg) => Self::binance(StatusCode::UNAUTHORIZED, msg),
+            ConnectorError::ForbiddenError(msg) => Self::binance(StatusCode::FORBIDDEN, msg),
+            ConnectorError::TooManyRequestsError(msg) => {
+                Self::binance(StatusCode::TOO_MANY_REQUESTS, msg)
+            }
+            ConnectorError::RateLimitBanError(msg) => Self::binance(StatusCode::IM_A_TEAPOT, msg),
+            ConnectorError::ServerError { msg, .. } => Self::binance(StatusCode::BAD_GATEWAY, msg),
+            ConnectorError::NotFoundError(msg)
+            | ConnectorError::NetworkError(msg)
+            | ConnectorError::ConnectorClientError(msg) => {
+                Self::binance(StatusCode::BAD_GATEWAY, msg)
+            }
+        }
+    }
+}
+
+// SDK 的 API 方法返回 anyhow::Error, 其中包装的是 ConnectorError
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<ConnectorError>() {
+            Ok(e) => e.into(),
+            Err(e) => Self::gateway(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        }
+    }
+}
+
+// SDK 的参数 builder 只会因为缺少必填字段而失败
+impl From<ParamBuildError> for ApiError {
+    fn from(e: ParamBuildError) -> Self {
+        match e {
+            ParamBuildError::UninitializedField(field) => Self::invalid_params(vec![FieldError {
+                field: field.to_string(),
+                reason: "missing".to_string(),
+            }]),
+        }
+    }
+}
+
+/// 收集参数校验错误, 一次返回全部不合法的字段
+#[derive(Default)]
+pub struct Validation {
+    errors: Vec<FieldError>,
+}
+
+impl Validation {
+    pub fn check(&mut self, ok: bool, field: &str, reason: &str) -> &mut Self {
+        if !ok {
+            self.errors.push(FieldError {
+                field: field.to_string(),
+                reason: reason.to_string(),
+            });
+        }
+        self
+    }
+
+    pub fn finish(&mut self) -> Result<(), ApiError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ApiError::invalid_params(std::mem::take(&mut self.errors)))
+        }
+    }
+}
+
+// Form / Query 反序列化失败时 (缺少字段, 枚举值不合法等) 同样返回 JSON 错误
+fn deserialize_error(msg: String) -> actix_web::Error {
+    // serde 的错误信息形如 "missing field `symbol`"
+    let field = msg
+        .split('`')
+        .nth(1)
+        .filter(|_| msg.starts_with("missing field"))
+        .map(str::to_string);
+    let error = match field {
+        Some(field) => ApiError::invalid_params(vec![FieldError {
+            field,
+            reason: "missing".to_string(),
+        }]),
+        None => ApiError::bad_request(msg),
+    };
+    error.into()
+}
+
+pub fn form_config() -> web::FormConfig {
+    web::FormConfig::default().error_handler(|e, _| match e {
+        UrlencodedError::Parse(e) => deserialize_error(e.to_string()),
+        e => deserialize_error(e.to_string()),
+    })
+}
+
+pub fn query_config() -> web::QueryConfig {
+    web::QueryConfig::default().error_handler(|e, _| match e {
+        QueryPayloadError::Deserialize(e) => deserialize_error(e.to_string()),
+        e => deserialize_error(e.to_string()),
+    })
+}
+
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|e, _| match e {
+        JsonPayloadError::Deserialize(e) => deserialize_error(e.to_string()),
+        e => deserialize_error(e.to_string()),
+    })
+}
+
+/// 下单接口返回本次使用的 client order id (包括网关生成的) 的响应头
+pub const CLIENT_ORDER_ID_HEADER: &str = "x-client-order-id";
+
+/// 在下单接口的响应 (包括错误响应) 中加上 client order id
+/// 调用方没有收到下单结果时, 可以用这个 id 查询订单或带 resubmit=true 重新提交
+pub fn with_client_order_id(
+    result: Result<HttpResponse, ApiError>,
+    client_order_id: &str,
+) -> HttpResponse {
+    let mut response = result.unwrap_or_else(HttpResponse::from_error);
+    if let Ok(value) = HeaderValue::from_str(client_order_id) {
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static(CLIENT_ORDER_ID_HEADER), value);
+    }
+    response
+}
+
+// SDK 在解析错误响应时只保留了 msg, 丢弃了 code, 这里按照币安文档中的错误信息还原错误码
+// https://developers.binance.com/docs/derivatives/usds-margined-futures/error-code
+const BINANCE_ERROR_CODES: &[(&str, i64)] = &[
+    ("Internal error; unable to process your request.", -1001),
+    ("Too many requests", -1003),
+    ("Way too many requests", -1003),
+    ("Timeout waiting for response from backend server.", -1007),
+    ("Too many new orders", -1015),
+    ("This IP cannot access this route.", -1016),
+    ("Timestamp for this request", -1021),
+    ("Signature for this request is not valid.", -1022),
+    ("Illegal characters found in parameter", -1100),
+    ("Too many parameters", -1101),
+    ("Mandatory parameter", -1102),
+    ("An unknown parameter was sent.", -1103),
+    ("Not all sent parameters were read", -1104),
+    ("Parameter '", -1106),
+    ("Invalid timeInForce.", -1115),
+    ("Invalid orderType.", -1116),
+    ("Invalid side.", -1117),
+    ("Invalid interval.", -1120),
+    ("Invalid symbol.", -1121),
+    (
+        "Precision is over the maximum defined for this asset.",
+        -1111,
+    ),
+    ("Filter failure:", -1013),
+    (
+        "Account has insufficient balance for requested action.",
+        -2010,
+    ),
+    ("Duplicate order sent.", -2010),
+    ("Unknown order sent.", -2011),
+    ("Order does not exist.", -2013),
+    ("API-key format invalid.", -2014),
+    ("Invalid API-key, IP, or permissions for action.", -2015),
+    ("Margin is insufficient.", -2019),
+    ("Unable to fill.", -2020),
+    ("Order would immediately trigger.", -2021),
+    ("ReduceOnly Order is rejected.", -2022),
+    ("Quantity less than or equal to zero.", -4003),
+    ("No need to change margin type.", -4046),
+    ("No need to change position side.", -4059),
+    (
+        "Order's position side does not match user's setting.",
+        -4061,
+    ),
+    ("ClientOrderId is duplicated.", -4116),
+    ("Order's notional must be no smaller than", -4164),
+    ("Due to the order could not be executed as maker", -5022),
+];
+
+pub(crate) fn binance_code(msg: &str) -> Option<i64> {
+    BINANCE_ERROR_CODES
+        .iter()
+        .find(|(prefix, _)| msg.starts_with(prefix))
+        .map(|(_, code)| *code)
+}
+
 // 定义特征，用于标识不同的客户端类型
 pub trait ClientSelector: Clone {
     fn get_clients(
@@ -9,6 +310,11 @@ pub trait ClientSelector: Clone {
     ) -> std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, Self>>>;
 }
 
+// 有公开行情接口的产品, 提供共享的无 key 客户端
+pub trait MarketClientSelector: ClientSelector {
+    fn get_public_client(data: &web::Data<AppState>) -> Self;
+}
+
 // 为不同的客户端类型实现 ClientSelector 特征
 impl ClientSelector for binance_sdk::derivatives_trading_usds_futures::rest_api::RestApi {
     fn get_clients(
@@ -18,6 +324,12 @@ impl ClientSelector for binance_sdk::derivatives_trading_usds_futures::rest_api:
     }
 }
 
+impl MarketClientSelector for binance_sdk::derivatives_trading_usds_futures::rest_api::RestApi {
+    fn get_public_client(data: &web::Data<AppState>) -> Self {
+        data.public_usds_future_client.clone()
+    }
+}
+
 impl ClientSelector for binance_sdk::spot::rest_api::RestApi {
     fn get_clients(
         data: &web::Data<AppState>,
@@ -26,21 +338,268 @@ impl ClientSelector for binance_sdk::spot::rest_api::RestApi {
     }
 }
 
+impl MarketClientSelector for binance_sdk::spot::rest_api::RestApi {
+    fn get_public_client(data: &web::Data<AppState>) -> Self {
+        data.public_spot_client.clone()
+    }
+}
+
+impl ClientSelector for binance_sdk::sub_account::rest_api::RestApi {
+    fn get_clients(
+        data: &web::Data<AppState>,
+    ) -> std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, Self>>> {
+        data.rest_sub_account_clients.clone()
+    }
+}
+
 // 使用泛型重写 get_client_from_state 函数
 pub fn get_client_from_state<T: ClientSelector + Clone>(
     data: &web::Data<AppState>,
     key_name: &str,
-) -> Result<T, actix_web::Error> {
+) -> Result<T, ApiError> {
     let rest_clients = T::get_clients(data);
     let locked_clients = rest_clients.lock().unwrap();
-    locked_clients
-        .get(key_name)
-        .cloned()
-        .ok_or_else(|| {
-            error!("Failed to get client for key_name: {}", key_name);
-            actix_web::error::ErrorBadRequest(format!(
-                "Client not found for key_name: {}",
-                key_name
-            ))
+    locked_clients.get(key_name).cloned().ok_or_else(|| {
+        error!("Failed to get client for key_name: {}", key_name);
+        ApiError::bad_request(format!("Client not found for key_name: {}", key_name))
+    })
+}
+
+// 行情接口: 传了 key 时使用该 key 的客户端 (保持 testnet 等配置), 否则使用共享的无 key 客户端
+pub fn get_market_client<T: MarketClientSelector>(
+    data: &web::Data<AppState>,
+    key_name: Option<&str>,
+) -> Result<T, ApiError> {
+    match key_name {
+        Some(key_name) => get_client_from_state(data, key_name),
+        None => Ok(T::get_public_client(data)),
+    }
+}
+
+// K 线接口: 正式环境的请求在本地 K 线存储覆盖请求范围时直接从本地返回, 格式与币安相同
+pub async fn stored_klines(
+    data: &web::Data<AppState>,
+    key_name: Option<&str>,
+    query: KlineQuery<'_>,
+) -> Option<Vec<serde_json::Value>> {
+    let store = data.kline_store.as_ref()?;
+    if !store.serves(key_name) {
+        return None;
+    }
+    let klines = store.klines(&query).await?;
+    Some(klines.iter().map(|kline| kline.to_row()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connector_error_mapping() {
+        let e = ApiError::from(ConnectorError::BadRequestError(
+            "Margin is insufficient.".to_string(),
+        ));
+        assert_eq!(e.status, StatusCode::BAD_REQUEST);
+        assert_eq!(e.code, Some(-2019));
+        assert_eq!(e.source, ErrorSource::Binance);
+
+        let e = ApiError::from(anyhow::Error::new(ConnectorError::TooManyRequestsError(
+            "Too many requests; current limit is 2400 requests per minute.".to_string(),
+        )));
+        assert_eq!(e.status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(e.code, Some(-1003));
+
+        let e = ApiError::from(ConnectorError::ServerError {
+            msg: "Server error: 503".to_string(),
+            status_code: Some(503),
+        });
+        assert_eq!(e.status, StatusCode::BAD_GATEWAY);
+        assert_eq!(e.code, None);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_util {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use actix_web::http::header::{AUTHORIZATION, ContentType};
+    use actix_web::{
+        App, HttpResponse, HttpServer, body::to_bytes, dev::ServiceResponse, test, web,
+    };
+    use binance_sdk::config::ConfigurationRestApi;
+    use binance_sdk::derivatives_trading_usds_futures::{
+        self, DerivativesTradingUsdsFuturesRestApi,
+    };
+    use binance_sdk::spot::{self, SpotRestApi};
+    use serde_json::{Value, json};
+
+    use crate::app::{AppState, Key};
+    use crate::common::exchange_info::ExchangeInfoCache;
+    use crate::config::{AuthConfig, AuthToken, Permission};
+
+    pub const TEST_TOKEN: &str = "test-token";
+
+    pub const MINUTE: i64 = 60_000;
+
+    // 拥有全部权限的测试状态, 不包含任何币安客户端
+    pub fn test_state() -> web::Data<AppState> {
+        test_state_with(|_| {})
+    }
+
+    // 在 test_state 的基础上修改部分字段
+    pub fn test_state_with(f: impl FnOnce(&mut AppState)) -> web::Data<AppState> {
+        let mut state = AppState::for_test(AuthConfig {
+            tokens: vec![AuthToken {
+                name: "test".to_string(),
+                token: TEST_TOKEN.to_string(),
+                keys: vec!["*".to_string()],
+                permission: Permission::Transfer,
+            }],
+        });
+        f(&mut state);
+        web::Data::new(state)
+    }
+
+    pub fn get(uri: &str) -> test::TestRequest {
+        test::TestRequest::get()
+            .uri(uri)
+            .insert_header((AUTHORIZATION, format!("Bearer {}", TEST_TOKEN)))
+    }
+
+    pub fn post_json(uri: &str, json: serde_json::Value) -> test::TestRequest {
+        test::TestRequest::post()
+            .uri(uri)
+            .insert_header((AUTHORIZATION, format!("Bearer {}", TEST_TOKEN)))
+            .set_json(json)
+    }
+
+    pub fn post_form(uri: &str, form: &str) -> test::TestRequest {
+        test::TestRequest::post()
+            .uri(uri)
+            .insert_header((AUTHORIZATION, format!("Bearer {}", TEST_TOKEN)))
+            .insert_header(ContentType::form_url_encoded())
+            .set_payload(form.to_string())
+    }
+
+    // 返回错误响应中 fields 列出的字段名
+    pub async fn invalid_fields(resp: ServiceResponse) -> Vec<String> {
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        json["fields"]
+            .as_array()
+            .map(|fields| {
+                fields
+                    .iter()
+                    .map(|f| f["field"].as_str().unwrap().to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // 本地的模拟币安 REST 服务, 由测试配置需要的接口, 返回 base_url
+    pub async fn stub_upstream(
+        routes: impl Fn(&mut web::ServiceConfig) + Clone + Send + 'static,
+    ) -> String {
+        let server = HttpServer::new(move || App::new().configure(routes.clone()))
+            .workers(1)
+            .bind(("127.0.0.1", 0))
+            .unwrap();
+        let base_url = format!("http://{}", server.addrs()[0]);
+        actix_web::rt::spawn(server.run());
+        base_url
+    }
+
+    fn stub_conf(base_url: &str) -> ConfigurationRestApi {
+        ConfigurationRestApi::builder()
+            .api_key("test-key".to_string())
+            .api_secret("test-secret".to_string())
+            .base_path(base_url.to_string())
+            .build()
+            .unwrap()
+    }
+
+    pub fn usds_future_client(
+        base_url: &str,
+    ) -> derivatives_trading_usds_futures::rest_api::RestApi {
+        DerivativesTradingUsdsFuturesRestApi::from_config(stub_conf(base_url))
+    }
+
+    pub fn spot_client(base_url: &str) -> spot::rest_api::RestApi {
+        SpotRestApi::from_config(stub_conf(base_url))
+    }
+
+    // 模拟币安 U 本位合约的测试状态, binance1 和无 key 的客户端都指向模拟服务
+    // f 修改其余字段后再创建覆盖 binance1 的交易规则缓存, 与状态共用限流器
+    pub async fn state_with_usds_future_stub(
+        routes: impl Fn(&mut web::ServiceConfig) + Clone + Send + 'static,
+        f: impl FnOnce(&mut AppState),
+    ) -> web::Data<AppState> {
+        let base_url = stub_upstream(routes).await;
+        test_state_with(|state| {
+            state
+                .rest_usds_future_clients
+                .lock()
+                .unwrap()
+                .insert("binance1".to_string(), usds_future_client(&base_url));
+            state.public_usds_future_client = usds_future_client(&base_url);
+            f(state);
+            let key = Key {
+                api_key: String::new(),
+                secret: String::new(),
+                environment: Default::default(),
+                base_url: None,
+                master: false,
+            };
+            state.exchange_info = Arc::new(ExchangeInfoCache::new(
+                &HashMap::from([("binance1".to_string(), key)]),
+                usds_future_client(&base_url),
+                spot_client(&base_url),
+                state.rate_limiter.clone(),
+            ));
         })
-}
\ No newline at end of file
+    }
+
+    // 币安 1m K 线接口返回的 [start_time, end_time] 内最多 limit 根
+    pub fn kline_rows(start_time: i64, end_time: i64, limit: usize) -> Vec<Value> {
+        let first = (start_time + MINUTE - 1) / MINUTE * MINUTE;
+        (first..=end_time)
+            .step_by(MINUTE as usize)
+            .take(limit)
+            .map(|t| {
+                json!([
+                    t,
+                    "1",
+                    "2",
+                    "0.5",
+                    "1.5",
+                    "10",
+                    t + MINUTE - 1,
+                    "15",
+                    3,
+                    "5",
+                    "7.5",
+                    "0"
+                ])
+            })
+            .collect()
+    }
+
+    // 模拟币安的 1m K 线接口, 记录每次请求的 startTime
+    pub async fn stub_klines(
+        starts: web::Data<Mutex<Vec<i64>>>,
+        query: web::Query<HashMap<String, String>>,
+    ) -> HttpResponse {
+        let param = |name: &str| query.get(name).and_then(|value| value.parse::<i64>().ok());
+        let start_time = param("startTime").unwrap();
+        starts.lock().unwrap().push(start_time);
+        // 币安的 limit 默认 500
+        let rows = kline_rows(
+            start_time,
+            param("endTime").unwrap(),
+            param("limit").unwrap_or(500) as usize,
+        );
+        HttpResponse::Ok().json(rows)
+    }
+}
@@ -0,0 +1,258 @@
+use actix_web::middleware::from_fn;
+use actix_web::web::Bytes;
+use actix_web::{HttpResponse, get, web};
+use binance_sdk::derivatives_trading_usds_futures::rest_api as usds_future_api;
+use binance_sdk::spot::rest_api as spot_api;
+use serde::Deserialize;
+use tracing::error;
+
+use crate::app::AppState;
+use crate::common::klines::{CSV_HEADER, Kline, KlineClient, KlinePager};
+use crate::common::paginate::now_ms;
+use crate::common::params::OptionalKeyName;
+use crate::handler::common::{ApiError, Validation, get_market_client, query_config};
+use crate::middleware::auth::{bearer_auth, require_market_data};
+use crate::stream::market::Product;
+
+pub fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/klines")
+            .wrap(from_fn(bearer_auth))
+            .app_data(query_config())
+            // GET method
+            .service(history),
+    );
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Format {
+    #[default]
+    Jsonl,
+    Csv,
+}
+
+impl Format {
+    fn content_type(&self) -> &'static str {
+        match self {
+            Self::Jsonl => "application/x-ndjson",
+            Self::Csv => "text/csv",
+        }
+    }
+
+    fn encode(&self, klines: &[Kline]) -> Bytes {
+        let mut body = String::new();
+        for kline in klines {
+            match self {
+                Self::Jsonl => body.push_str(&serde_json::to_string(kline).unwrap_or_default()),
+                Self::Csv => body.push_str(&kline.to_csv()),
+            }
+            body.push('\n');
+        }
+        Bytes::from(body)
+    }
+}
+
+#[derive(Deserialize)]
+struct HistoryParamsWrapper {
+    product: Option<Product>,
+    symbol: String,
+    interval: String,
+    start_time: i64,
+    end_time: Option<i64>,
+    format: Option<Format>,
+}
+
+struct HistoryParams {
+    product: Product,
+    symbol: String,
+    interval: String,
+    start_time: i64,
+    end_time: i64,
+    format: Format,
+}
+
+impl TryFrom<HistoryParamsWrapper> for HistoryParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: HistoryParamsWrapper) -> Result<Self, Self::Error> {
+        let product = wrapper.product.unwrap_or_default();
+        let end_time = wrapper.end_time.unwrap_or_else(now_ms);
+
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                product
+                    .kline_intervals()
+                    .contains(&wrapper.interval.as_str()),
+                "interval",
+                &format!("must be one of {}", product.kline_intervals().join(", ")),
+            )
+            .check(
+                wrapper.start_time >= 0,
+                "start_time",
+                "must not be negative",
+            )
+            .check(
+                wrapper.start_time <= end_time,
+                "start_time",
+                "must not be after end_time",
+            )
+            .finish()?;
+
+        Ok(HistoryParams {
+            product,
+            symbol: wrapper.symbol,
+            interval: wrapper.interval,
+            start_time: wrapper.start_time,
+            end_time,
+            format: wrapper.format.unwrap_or_default(),
+        })
+    }
+}
+
+/// 历史 K 线, 自动翻页拉取任意长度的时间范围
+/// GET /history
+/// 参数:
+/// - product: usds_future / spot, 默认 usds_future (可选)
+/// - symbol: 交易对 (必填)
+/// - interval: K 线周期 (必填)
+/// - start_time: 开始时间 (必填)
+/// - end_time: 结束时间 (可选, 默认当前时间)
+/// - format: jsonl / csv, 默认 jsonl (可选)
+/// - key: 使用该 key 的客户端请求 (可选, 默认使用公开行情客户端)
+///
+/// 每页 1000 根, 翻页之间按权重限速, 结果按开盘时间升序逐页返回
+/// 第一页失败时返回错误响应, 之后的页失败时中断响应
+#[get("/history", wrap = "from_fn(require_market_data)")]
+pub async fn history(
+    data: web::Data<AppState>,
+    query: web::Query<OptionalKeyName>,
+    param: web::Query<HistoryParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = HistoryParams::try_from(param.into_inner())?;
+
+    // 调用辅助函数获取客户端
+    let client = match params.product {
+        Product::UsdsFuture => KlineClient::UsdsFuture(Box::new(get_market_client::<
+            usds_future_api::RestApi,
+        >(&data, query.key())?)),
+        Product::Spot => KlineClient::Spot(Box::new(get_market_client::<spot_api::RestApi>(
+            &data,
+            query.key(),
+        )?)),
+    };
+
+    let format = params.format;
+    let mut pager = KlinePager::new(
+        client,
+        data.rate_limiter.clone(),
+        query.key().map(str::to_string),
+        params.symbol,
+        params.interval,
+        params.start_time,
+        params.end_time,
+    );
+    // 先拉取第一页, 参数错误 (如交易对不存在) 时返回正常的错误响应
+    let first = pager.next_page().await?;
+
+    let mut head = match format {
+        Format::Csv => format!("{}\n", CSV_HEADER).into_bytes(),
+        Format::Jsonl => Vec::new(),
+    };
+    head.extend_from_slice(&format.encode(first.as_deref().unwrap_or_default()));
+
+    let rest = futures::stream::unfold(pager, move |mut pager| async move {
+        match pager.next_page().await {
+            Ok(Some(page)) => Some((Ok(format.encode(&page)), pager)),
+            Ok(None) => None,
+            Err(e) => {
+                error!("kline history: {}", e);
+                Some((Err(std::io::Error::other(e.to_string())), pager.stop()))
+            }
+        }
+    });
+    let body = futures::StreamExt::chain(
+        futures::stream::once(async move { Ok::<_, std::io::Error>(Bytes::from(head)) }),
+        rest,
+    );
+
+    // 返回响应
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .streaming(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use actix_web::{App, test, web};
+    use serde_json::Value;
+
+    use crate::handler::common::test_util::{
+        MINUTE, get, invalid_fields, state_with_usds_future_stub, stub_klines, test_state,
+    };
+    use crate::handler::klines::routes;
+
+    #[actix_web::test]
+    async fn test_history_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req =
+            get("/klines/history?symbol=BTCUSDT&interval=1s&start_time=2&end_time=1").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["interval", "start_time"]);
+
+        let req =
+            get("/klines/history?product=spot&symbol=BTCUSDT&interval=1s&start_time=0&format=xml")
+                .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        let req = get("/klines/history?symbol=BTCUSDT&interval=1m").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["start_time"]);
+    }
+
+    #[actix_web::test]
+    async fn test_history_pagination() {
+        let starts = web::Data::new(Mutex::new(Vec::<i64>::new()));
+        let state = state_with_usds_future_stub(
+            {
+                let starts = starts.clone();
+                move |cfg| {
+                    cfg.app_data(starts.clone())
+                        .route("/fapi/v1/klines", web::get().to(stub_klines));
+                }
+            },
+            |_| {},
+        )
+        .await;
+        let app = test::init_service(App::new().app_data(state).configure(routes)).await;
+
+        // 1500 根 K 线分两页拉取, 第二页从第一页最后一根之后开始
+        let req = get(&format!(
+            "/klines/history?key=binance1&symbol=BTCUSDT&interval=1m&start_time=0&end_time={}",
+            1500 * MINUTE - 1
+        ))
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body = test::read_body(resp).await;
+        let open_times: Vec<i64> = std::str::from_utf8(&body)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<Value>(line).unwrap()["open_time"]
+                    .as_i64()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(
+            open_times,
+            (0..1500).map(|i| i * MINUTE).collect::<Vec<_>>()
+        );
+        assert_eq!(*starts.lock().unwrap(), vec![0, 999 * MINUTE + 1]);
+    }
+}
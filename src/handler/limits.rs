@@ -0,0 +1,76 @@
+use actix_web::middleware::from_fn;
+use actix_web::{HttpResponse, get, web};
+
+use crate::app::AppState;
+use crate::common::rate_limit::LimitKind;
+use crate::config::AuthToken;
+use crate::middleware::auth::{bearer_auth, require_market_data};
+
+pub fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/limits")
+            .wrap(from_fn(bearer_auth))
+            // GET method
+            .service(limits),
+    );
+}
+
+/// 本地限流的当前用量
+/// GET /limits
+///
+/// 返回每个窗口的 product, kind (request_weight / orders), scope (权重为上游环境, 下单数为 key),
+/// interval, used, limit (本地限额), reset_at, 以及被币安拒绝后暂停到的时间 blocked_until
+/// 下单数只返回 token 允许使用的 key
+#[get("", wrap = "from_fn(require_market_data)")]
+pub async fn limits(
+    data: web::Data<AppState>,
+    caller: web::ReqData<AuthToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let usage: Vec<_> = data
+        .rate_limiter
+        .usage()
+        .into_iter()
+        .filter(|usage| usage.kind == LimitKind::RequestWeight || caller.allows_key(&usage.scope))
+        .collect();
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(usage))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test};
+    use serde_json::Value;
+
+    use crate::handler::common::test_util::{get, test_state};
+    use crate::handler::limits::routes;
+    use crate::handler::usds_future::routes as usds_future_routes;
+
+    #[actix_web::test]
+    async fn test_limits() {
+        let app = test::init_service(
+            App::new()
+                .app_data(test_state())
+                .configure(routes)
+                .configure(usds_future_routes),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/limits").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+
+        // 参数校验失败的请求没有发往币安, 归还占用的额度
+        let req = get("/usds_future/kline?symbol=BTCUSDT&interval=1m&limit=0").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        let req = get("/limits").to_request();
+        let usage: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(usage[0]["product"], "usds_future");
+        assert_eq!(usage[0]["kind"], "request_weight");
+        assert_eq!(usage[0]["scope"], "production");
+        assert_eq!(usage[0]["used"], 0);
+        assert_eq!(usage[0]["limit"], 2160);
+    }
+}
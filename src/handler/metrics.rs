@@ -0,0 +1,49 @@
+use actix_web::middleware::from_fn;
+use actix_web::{HttpResponse, get, web};
+use serde_json::json;
+
+use crate::app::AppState;
+use crate::middleware::auth::{bearer_auth, require_market_data};
+
+pub fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/metrics")
+            .wrap(from_fn(bearer_auth))
+            // GET method
+            .service(metrics),
+    );
+}
+
+/// 网关运行统计
+/// GET /metrics
+///
+/// retries: 按接口统计的 retries (重试次数), recovered (重试后成功), exhausted (重试次数用完仍失败),
+/// duplicates (下单结果未知时查询到订单已存在, 没有重复下单)
+/// 只返回发生过重试的接口, 计数从网关启动开始累计
+#[get("", wrap = "from_fn(require_market_data)")]
+pub async fn metrics(data: web::Data<AppState>) -> Result<HttpResponse, actix_web::Error> {
+    // 返回响应
+    Ok(HttpResponse::Ok().json(json!({ "retries": data.retry.stats() })))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test};
+    use serde_json::{Value, json};
+
+    use crate::handler::common::test_util::{get, test_state};
+    use crate::handler::metrics::routes;
+
+    #[actix_web::test]
+    async fn test_metrics() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = test::TestRequest::get().uri("/metrics").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+
+        let req = get("/metrics").to_request();
+        let metrics: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(metrics, json!({ "retries": {} }));
+    }
+}
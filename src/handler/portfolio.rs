@@ -0,0 +1,597 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use actix_web::middleware::from_fn;
+use actix_web::{HttpResponse, get, web};
+use binance_sdk::derivatives_trading_usds_futures::rest_api as usds_future_api;
+use binance_sdk::spot::rest_api as spot_api;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, warn};
+
+use crate::app::AppState;
+use crate::common::rate_limit::Cost;
+use crate::config::AuthToken;
+use crate::handler::common::{ApiError, get_client_from_state, query_config};
+use crate::middleware::auth::{bearer_auth, require_account};
+use crate::stream::market::Product;
+
+pub fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/portfolio")
+            .wrap(from_fn(bearer_auth))
+            .app_data(query_config())
+            // GET method
+            .service(summary),
+    );
+}
+
+// 币安返回的数值都是字符串, 解析失败按 0 处理
+fn decimal(value: &Option<String>) -> Decimal {
+    value
+        .as_deref()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Serialize)]
+struct FuturesAsset {
+    asset: String,
+    wallet_balance: Decimal,
+    unrealized_pnl: Decimal,
+    margin_balance: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+struct FuturesPosition {
+    symbol: String,
+    position_side: String,
+    position_amt: Decimal,
+    notional: Decimal,
+    unrealized_pnl: Decimal,
+}
+
+// U 本位合约账户, total_* 为币安按 USD 折算的账户合计
+#[derive(Debug, Serialize)]
+struct FuturesSummary {
+    wallet_balance: Decimal,
+    unrealized_pnl: Decimal,
+    margin_balance: Decimal,
+    assets: Vec<FuturesAsset>,
+    positions: Vec<FuturesPosition>,
+}
+
+impl From<usds_future_api::AccountInformationV3Response> for FuturesSummary {
+    fn from(account: usds_future_api::AccountInformationV3Response) -> Self {
+        let assets = account
+            .assets
+            .unwrap_or_default()
+            .into_iter()
+            .map(|asset| FuturesAsset {
+                wallet_balance: decimal(&asset.wallet_balance),
+                unrealized_pnl: decimal(&asset.unrealized_profit),
+                margin_balance: decimal(&asset.margin_balance),
+                asset: asset.asset.unwrap_or_default(),
+            })
+            .filter(|asset| !asset.wallet_balance.is_zero() || !asset.margin_balance.is_zero())
+            .collect();
+
+        let positions = account
+            .positions
+            .unwrap_or_default()
+            .into_iter()
+            .map(|position| FuturesPosition {
+                position_amt: decimal(&position.position_amt),
+                notional: decimal(&position.notional),
+                unrealized_pnl: decimal(&position.unrealized_profit),
+                symbol: position.symbol.unwrap_or_default(),
+                position_side: position.position_side.unwrap_or_default(),
+            })
+            .filter(|position| !position.position_amt.is_zero())
+            .collect();
+
+        FuturesSummary {
+            wallet_balance: decimal(&account.total_wallet_balance),
+            unrealized_pnl: decimal(&account.total_unrealized_profit),
+            margin_balance: decimal(&account.total_margin_balance),
+            assets,
+            positions,
+        }
+    }
+}
+
+// 现货余额和汇总权益的计价资产
+const QUOTE_ASSET: &str = "USDT";
+
+#[derive(Debug, Serialize)]
+struct SpotBalance {
+    asset: String,
+    free: Decimal,
+    locked: Decimal,
+    // 按 QUOTE_ASSET 计价的价值, 没有 {asset}USDT 现货价格时为 null
+    value: Option<Decimal>,
+}
+
+fn spot_balances(account: spot_api::GetAccountResponse) -> Vec<SpotBalance> {
+    account
+        .balances
+        .unwrap_or_default()
+        .into_iter()
+        .map(|balance| SpotBalance {
+            free: decimal(&balance.free),
+            locked: decimal(&balance.locked),
+            asset: balance.asset.unwrap_or_default(),
+            value: None,
+        })
+        .filter(|balance| !balance.free.is_zero() || !balance.locked.is_zero())
+        .collect()
+}
+
+// 资产按 QUOTE_ASSET 计价的价格, 计价资产本身为 1
+fn spot_price(prices: &HashMap<String, Decimal>, asset: &str) -> Option<Decimal> {
+    if asset == QUOTE_ASSET {
+        return Some(Decimal::ONE);
+    }
+    prices.get(&format!("{}{}", asset, QUOTE_ASSET)).copied()
+}
+
+#[derive(Debug, Serialize)]
+struct ProductError {
+    product: &'static str,
+    status: u16,
+    #[serde(flatten)]
+    error: ApiError,
+}
+
+// 单个 key 的查询结果, 某个产品查询失败时对应字段为 null, 错误放在 errors 中
+#[derive(Debug, Serialize)]
+struct AccountSummary {
+    key: String,
+    futures: Option<FuturesSummary>,
+    spot: Option<Vec<SpotBalance>>,
+    // 现货余额按 QUOTE_ASSET 计价的合计, 不含没有价格的资产
+    spot_value: Decimal,
+    // 合约保证金余额 + spot_value
+    equity: Decimal,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<ProductError>,
+}
+
+impl AccountSummary {
+    fn new(
+        key: String,
+        futures: Result<FuturesSummary, ApiError>,
+        spot: Result<Vec<SpotBalance>, ApiError>,
+    ) -> Self {
+        let mut errors = Vec::new();
+        let futures = futures
+            .map_err(|error| {
+                errors.push(ProductError {
+                    product: "usds_future",
+                    status: error.status.as_u16(),
+                    error,
+                })
+            })
+            .ok();
+        let spot = spot
+            .map_err(|error| {
+                errors.push(ProductError {
+                    product: "spot",
+                    status: error.status.as_u16(),
+                    error,
+                })
+            })
+            .ok();
+        let equity = futures.as_ref().map_or(Decimal::ZERO, |f| f.margin_balance);
+        AccountSummary {
+            key,
+            futures,
+            spot,
+            spot_value: Decimal::ZERO,
+            equity,
+            errors,
+        }
+    }
+
+    // 按现货价格计算每个余额的价值, 计入 spot_value 和 equity
+    fn value_spot(&mut self, prices: &HashMap<String, Decimal>) {
+        for balance in self.spot.iter_mut().flatten() {
+            balance.value = spot_price(prices, &balance.asset)
+                .map(|price| (balance.free + balance.locked) * price);
+            self.spot_value += balance.value.unwrap_or_default();
+        }
+        self.equity += self.spot_value;
+    }
+}
+
+// 全部账户的合计, 合约部分为币安按 USD 折算的金额, 现货部分按 QUOTE_ASSET 计价
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct Total {
+    futures_wallet_balance: Decimal,
+    futures_unrealized_pnl: Decimal,
+    futures_margin_balance: Decimal,
+    spot_value: Decimal,
+    // futures_margin_balance + spot_value
+    equity: Decimal,
+}
+
+// 按资产汇总的数量, 单位都是该资产本身, spot_value 按 QUOTE_ASSET 计价
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct AssetTotal {
+    futures_wallet_balance: Decimal,
+    futures_unrealized_pnl: Decimal,
+    futures_margin_balance: Decimal,
+    spot_free: Decimal,
+    spot_locked: Decimal,
+    spot_value: Option<Decimal>,
+}
+
+// 按标的资产汇总的合约敞口, BTCUSDT 和 BTCUSDC 都计入 BTC, notional 多头为正, 空头为负
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct Exposure {
+    symbols: BTreeSet<String>,
+    long_notional: Decimal,
+    short_notional: Decimal,
+    net_notional: Decimal,
+    unrealized_pnl: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+struct PortfolioSummary {
+    accounts: Vec<AccountSummary>,
+    total: Total,
+    assets: BTreeMap<String, AssetTotal>,
+    exposure: BTreeMap<String, Exposure>,
+}
+
+impl PortfolioSummary {
+    /// prices: 现货交易对价格, 用于给现货余额计价
+    /// base_assets: 合约交易对到标的资产, 缺少的交易对按交易对名称单独汇总
+    fn new(
+        mut accounts: Vec<AccountSummary>,
+        prices: &HashMap<String, Decimal>,
+        base_assets: &HashMap<String, String>,
+    ) -> Self {
+        let mut total = Total::default();
+        let mut assets = BTreeMap::<String, AssetTotal>::new();
+        let mut exposure = BTreeMap::<String, Exposure>::new();
+
+        for account in &mut accounts {
+            account.value_spot(prices);
+            total.spot_value += account.spot_value;
+            total.equity += account.equity;
+
+            if let Some(futures) = &account.futures {
+                total.futures_wallet_balance += futures.wallet_balance;
+                total.futures_unrealized_pnl += futures.unrealized_pnl;
+                total.futures_margin_balance += futures.margin_balance;
+
+                for asset in &futures.assets {
+                    let entry = assets.entry(asset.asset.clone()).or_default();
+                    entry.futures_wallet_balance += asset.wallet_balance;
+                    entry.futures_unrealized_pnl += asset.unrealized_pnl;
+                    entry.futures_margin_balance += asset.margin_balance;
+                }
+
+                for position in &futures.positions {
+                    let base_asset = base_assets
+                        .get(&position.symbol)
+                        .unwrap_or(&position.symbol);
+                    let entry = exposure.entry(base_asset.clone()).or_default();
+                    entry.symbols.insert(position.symbol.clone());
+                    if position.notional.is_sign_negative() {
+                        entry.short_notional += position.notional;
+                    } else {
+                        entry.long_notional += position.notional;
+                    }
+                    entry.net_notional += position.notional;
+                    entry.unrealized_pnl += position.unrealized_pnl;
+                }
+            }
+
+            for balance in account.spot.iter().flatten() {
+                let entry = assets.entry(balance.asset.clone()).or_default();
+                entry.spot_free += balance.free;
+                entry.spot_locked += balance.locked;
+                if let Some(value) = balance.value {
+                    *entry.spot_value.get_or_insert_default() += value;
+                }
+            }
+        }
+
+        PortfolioSummary {
+            accounts,
+            total,
+            assets,
+            exposure,
+        }
+    }
+}
+
+async fn futures_summary(
+    data: &web::Data<AppState>,
+    key: &str,
+) -> Result<FuturesSummary, ApiError> {
+    let client = get_client_from_state::<usds_future_api::RestApi>(data, key)?;
+
+    let params = usds_future_api::AccountInformationV3Params::default();
+    let response = data
+        .rate_limiter
+        .call(
+            Some(key),
+            Cost::weight(Product::UsdsFuture, 5),
+            async || client.account_information_v3(params).await,
+        )
+        .await
+        .inspect_err(|e| error!("summary: {} usds_future: {}", key, e))?;
+
+    let account = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    Ok(account.into())
+}
+
+async fn spot_summary(data: &web::Data<AppState>, key: &str) -> Result<Vec<SpotBalance>, ApiError> {
+    let client = get_client_from_state::<spot_api::RestApi>(data, key)?;
+
+    let params = spot_api::GetAccountParams::builder()
+        .omit_zero_balances(Some(true))
+        .build()?;
+    let response = data
+        .rate_limiter
+        .call(Some(key), Cost::weight(Product::Spot, 20), async || {
+            client.get_account(params).await
+        })
+        .await
+        .inspect_err(|e| error!("summary: {} spot: {}", key, e))?;
+
+    let account = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    Ok(spot_balances(account))
+}
+
+async fn account_summary(data: &web::Data<AppState>, key: String) -> AccountSummary {
+    let (futures, spot) = futures::join!(futures_summary(data, &key), spot_summary(data, &key));
+    AccountSummary::new(key, futures, spot)
+}
+
+#[derive(Deserialize)]
+struct SummaryParamsWrapper {
+    keys: Option<String>,
+}
+
+// 不传 keys 时汇总 token 允许使用的全部 key, 传了 keys 时每个 key 都必须已配置且被 token 允许
+fn summary_keys(
+    data: &web::Data<AppState>,
+    caller: &AuthToken,
+    wrapper: SummaryParamsWrapper,
+) -> Result<Vec<String>, ApiError> {
+    let configured: BTreeSet<String> = data
+        .rest_usds_future_clients
+        .lock()
+        .unwrap()
+        .keys()
+        .chain(data.rest_spot_clients.lock().unwrap().keys())
+        .cloned()
+        .collect();
+
+    let requested: BTreeSet<String> = wrapper
+        .keys
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    if requested.is_empty() {
+        return Ok(configured
+            .into_iter()
+            .filter(|key| caller.allows_key(key))
+            .collect());
+    }
+
+    if let Some(key) = requested.iter().find(|key| !configured.contains(*key)) {
+        return Err(ApiError::bad_request(format!(
+            "Client not found for key_name: {}",
+            key
+        )));
+    }
+    if let Some(key) = requested.iter().find(|key| !caller.allows_key(key)) {
+        let msg = format!("Token {} is not allowed to use key {}", caller.name, key);
+        warn!("summary: {}", msg);
+        return Err(ApiError::forbidden(msg));
+    }
+
+    Ok(requested.into_iter().collect())
+}
+
+/// 多账户资产汇总, 并发查询每个 key 的 U 本位合约账户 (余额和持仓) 和现货余额
+/// GET /summary
+/// 参数:
+/// - keys: 逗号分隔的 key 列表 (可选, 默认 token 允许使用的全部 key)
+///
+/// 返回:
+/// - accounts: 每个 key 的合约余额, 持仓, 现货余额和权益 (合约保证金余额 + 现货按 USDT 计价), 查询失败的产品放在该 key 的 errors 中
+/// - total: 合约账户按 USD 折算的合计, 现货按 USDT 计价的合计和总权益
+/// - assets: 按资产汇总合约和现货余额, 数量不跨资产相加
+/// - exposure: 按标的资产汇总的合约多空名义价值和未实现盈亏
+#[get("/summary", wrap = "from_fn(require_account)")]
+pub async fn summary(
+    data: web::Data<AppState>,
+    caller: web::ReqData<AuthToken>,
+    param: web::Query<SummaryParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let keys = summary_keys(&data, &caller, param.into_inner())?;
+
+    let accounts =
+        futures::future::join_all(keys.into_iter().map(|key| account_summary(&data, key))).await;
+    debug!(
+        "summary: {} accounts, {} with errors",
+        accounts.len(),
+        accounts.iter().filter(|a| !a.errors.is_empty()).count()
+    );
+
+    // 只在需要时查询现货价格和合约交易对的标的资产
+    let needs_prices = accounts
+        .iter()
+        .flat_map(|a| a.spot.iter().flatten())
+        .any(|balance| balance.asset != QUOTE_ASSET);
+    let prices = match needs_prices {
+        true => data.exchange_info.spot_prices().await.unwrap_or_else(|| {
+            warn!("summary: spot prices unavailable, spot balances are not valued");
+            HashMap::new()
+        }),
+        false => HashMap::new(),
+    };
+    let symbols: BTreeSet<&str> = accounts
+        .iter()
+        .flat_map(|a| a.futures.iter().flat_map(|f| &f.positions))
+        .map(|position| position.symbol.as_str())
+        .collect();
+    let mut base_assets = HashMap::new();
+    for symbol in symbols {
+        if let Some(base_asset) = data.exchange_info.usds_future_base_asset(symbol).await {
+            base_assets.insert(symbol.to_string(), base_asset);
+        }
+    }
+
+    // 部分 key 查询失败时仍返回 200 和其余 key 的结果
+    Ok(HttpResponse::Ok().json(PortfolioSummary::new(accounts, &prices, &base_assets)))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test};
+    use binance_sdk::derivatives_trading_usds_futures::rest_api::AccountInformationV3Response;
+    use binance_sdk::spot::rest_api::GetAccountResponse;
+    use rust_decimal::Decimal;
+    use serde_json::json;
+
+    use super::*;
+    use crate::handler::common::test_util::{get, test_state};
+
+    fn dec(v: &str) -> Decimal {
+        v.parse().unwrap()
+    }
+
+    fn futures_account(symbol: &str, notional: &str, pnl: &str) -> FuturesSummary {
+        let account: AccountInformationV3Response = serde_json::from_value(json!({
+            "totalWalletBalance": "100",
+            "totalUnrealizedProfit": pnl,
+            "totalMarginBalance": (dec("100") + dec(pnl)).to_string(),
+            "assets": [
+                {"asset": "USDT", "walletBalance": "100", "unrealizedProfit": pnl,
+                 "marginBalance": (dec("100") + dec(pnl)).to_string()},
+                {"asset": "BNB", "walletBalance": "0", "unrealizedProfit": "0", "marginBalance": "0"}
+            ],
+            "positions": [
+                {"symbol": symbol, "positionSide": "BOTH", "positionAmt": "0.01",
+                 "notional": notional, "unrealizedProfit": pnl},
+                {"symbol": "ETHUSDT", "positionSide": "BOTH", "positionAmt": "0",
+                 "notional": "0", "unrealizedProfit": "0"}
+            ]
+        }))
+        .unwrap();
+        account.into()
+    }
+
+    #[actix_web::test]
+    async fn test_portfolio_aggregate() {
+        let spot: GetAccountResponse = serde_json::from_value(json!({
+            "balances": [
+                {"asset": "USDT", "free": "50", "locked": "5"},
+                {"asset": "BTC", "free": "0.001", "locked": "0"},
+                {"asset": "XYZ", "free": "3", "locked": "0"},
+                {"asset": "ETH", "free": "0", "locked": "0"}
+            ]
+        }))
+        .unwrap();
+
+        let accounts = vec![
+            AccountSummary::new(
+                "a".to_string(),
+                Ok(futures_account("BTCUSDT", "1000", "10")),
+                Ok(spot_balances(spot)),
+            ),
+            AccountSummary::new(
+                "b".to_string(),
+                Ok(futures_account("BTCUSDC", "-400", "-4")),
+                Err(ApiError::bad_request("boom")),
+            ),
+        ];
+        let prices = HashMap::from([("BTCUSDT".to_string(), dec("60000"))]);
+        let base_assets = HashMap::from([
+            ("BTCUSDT".to_string(), "BTC".to_string()),
+            ("BTCUSDC".to_string(), "BTC".to_string()),
+        ]);
+        let portfolio = PortfolioSummary::new(accounts, &prices, &base_assets);
+
+        let account = &portfolio.accounts[0];
+        assert_eq!(account.futures.as_ref().unwrap().assets.len(), 1);
+        assert_eq!(account.futures.as_ref().unwrap().positions.len(), 1);
+        assert_eq!(account.spot.as_ref().unwrap().len(), 3);
+        // 没有价格的资产不计价, 也不计入权益
+        assert_eq!(account.spot.as_ref().unwrap()[2].value, None);
+        assert_eq!(account.spot_value, dec("115"));
+        assert_eq!(account.equity, dec("225"));
+        assert!(portfolio.accounts[1].spot.is_none());
+        assert_eq!(portfolio.accounts[1].errors[0].product, "spot");
+        assert_eq!(portfolio.accounts[1].equity, dec("96"));
+
+        assert_eq!(
+            portfolio.total,
+            Total {
+                futures_wallet_balance: dec("200"),
+                futures_unrealized_pnl: dec("6"),
+                futures_margin_balance: dec("206"),
+                spot_value: dec("115"),
+                equity: dec("321"),
+            }
+        );
+        assert_eq!(
+            portfolio.assets["USDT"],
+            AssetTotal {
+                futures_wallet_balance: dec("200"),
+                futures_unrealized_pnl: dec("6"),
+                futures_margin_balance: dec("206"),
+                spot_free: dec("50"),
+                spot_locked: dec("5"),
+                spot_value: Some(dec("55")),
+            }
+        );
+        assert_eq!(portfolio.assets["BTC"].spot_value, Some(dec("60")));
+        assert_eq!(portfolio.assets["XYZ"].spot_value, None);
+        assert_eq!(
+            portfolio.exposure["BTC"],
+            Exposure {
+                symbols: BTreeSet::from(["BTCUSDC".to_string(), "BTCUSDT".to_string()]),
+                long_notional: dec("1000"),
+                short_notional: dec("-400"),
+                net_notional: dec("600"),
+                unrealized_pnl: dec("6"),
+            }
+        );
+        assert_eq!(portfolio.exposure.len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_summary_keys() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = get("/portfolio/summary?keys=binance1,unknown").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        // 没有配置任何 key 时返回空的汇总
+        let req = get("/portfolio/summary").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["accounts"], json!([]));
+        assert_eq!(resp["total"]["equity"], json!("0"));
+    }
+}
@@ -1,14 +1,35 @@
 pub mod get;
 pub mod post;
 
+use actix_web::middleware::from_fn;
 use actix_web::web;
 
+use crate::handler::common::{form_config, json_config, query_config};
+use crate::middleware::auth::bearer_auth;
+
 pub fn routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/spot")
+            .wrap(from_fn(bearer_auth))
+            .app_data(form_config())
+            .app_data(query_config())
+            .app_data(json_config())
             // GET method
             .service(get::exchange_information)
+            .service(get::kline)
+            .service(get::account::account)
+            .service(get::account::my_trades)
+            .service(get::account::order_rate_limit)
+            .service(get::account::commission_rate)
+            .service(get::order::query_order)
+            .service(get::order::current_open_orders)
+            .service(get::order::all_orders)
             // POST method
-            .service(post::kline),
+            .service(post::kline)
+            .service(post::order::new_order)
+            .service(post::order::test_order)
+            .service(post::order::cancel_order)
+            .service(post::order::cancel_all_open_orders)
+            .service(post::order::cancel_replace),
     );
 }
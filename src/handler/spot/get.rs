@@ -1,17 +1,38 @@
+pub mod account;
+pub mod order;
+
 use actix_web::HttpResponse;
+use actix_web::middleware::from_fn;
 use actix_web::{get, web};
-use binance_sdk::spot::rest_api::{ExchangeInfoParams, ExchangeInfoSymbolStatusEnum};
+use binance_sdk::spot::rest_api::{ExchangeInfoParams, ExchangeInfoSymbolStatusEnum, KlinesParams};
 use tracing::error;
 
-use crate::{app::AppState, common::params::KeyName};
+use super::post::KlinesParamsWrapper;
+use crate::{app::AppState, common::params::OptionalKeyName, common::retry::Idempotency};
 
-use crate::handler::common::get_client_from_state;
+use crate::common::kline_store::KlineQuery;
+use crate::common::params::CacheControl;
+use crate::handler::common::{ApiError, get_market_client, stored_klines};
+use crate::middleware::auth::require_market_data;
+use crate::stream::market::Product;
 
-#[get("/exchange_information")]
+/// 交易规则, 只返回可现货交易且状态为 TRADING 的交易对
+/// GET /exchange_information
+/// 参数:
+/// - fresh: true 时绕过本地缓存直接请求币安 (可选)
+///
+/// 正式环境的交易规则由网关缓存并定期刷新, 测试网和自定义 base_url 的 key 直接请求币安
+#[get("/exchange_information", wrap = "from_fn(require_market_data)")]
 async fn exchange_information(
     data: web::Data<AppState>,
-    query: web::Query<KeyName>,
+    query: web::Query<OptionalKeyName>,
+    cache: web::Query<CacheControl>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    if !cache.fresh && data.exchange_info.covers(query.key()) {
+        let info = data.exchange_info.spot_info().await?;
+        return Ok(HttpResponse::Ok().json(info.as_ref()));
+    }
+
     /*
     https://developers.binance.com/docs/derivatives/usds-margined-futures/market-data/rest-api/Kline-Candlestick-Data
     kline candlestick data 和 continuous contract kline candlestick data 传参不一样, 获取的数据是一样的
@@ -19,7 +40,7 @@ async fn exchange_information(
     */
 
     // 调用辅助函数获取客户端
-    let client = get_client_from_state::<binance_sdk::spot::rest_api::RestApi>(&data, &query.key)?;
+    let client = get_market_client::<binance_sdk::spot::rest_api::RestApi>(&data, query.key())?;
 
     // Setup the API parameters
     let params = ExchangeInfoParams::builder()
@@ -27,17 +48,64 @@ async fn exchange_information(
         .show_permission_sets(false)
         .symbol_status(ExchangeInfoSymbolStatusEnum::Trading)
         .build()
-        .unwrap();
+        .map_err(ApiError::from)?;
 
     // 调用 API 方法，替换为实际存在的 get_account_info 方法
-    let response = client.exchange_info(params).await.map_err(|e| {
-        error!("exchange_information: {}", e);
-        actix_web::error::ErrorInternalServerError(e)
+    let response = data
+        .retry
+        .call("spot/exchange_information", Idempotency::Idempotent, || {
+            client.exchange_info(params.clone())
+        })
+        .await
+        .map_err(|e| {
+            error!("exchange_information: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::Spot, query.key(), &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
     })?;
 
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// K 线数据, 参数与 POST /kline 相同, 通过 query 传递
+/// GET /kline?symbol=BTCUSDT&interval=1m
+///
+/// 配置了 [kline_store] 且请求范围已同步到本地时, 从本地 K 线存储返回
+#[get("/kline", wrap = "from_fn(require_market_data)")]
+async fn kline(
+    data: web::Data<AppState>,
+    query: web::Query<OptionalKeyName>,
+    param: web::Query<KlinesParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let param = KlinesParams::try_from(param.into_inner())?;
+
+    if let Some(rows) = stored_klines(&data, query.key(), KlineQuery::from(&param)).await {
+        return Ok(HttpResponse::Ok().json(rows));
+    }
+
+    // 调用辅助函数获取客户端
+    let client = get_market_client::<binance_sdk::spot::rest_api::RestApi>(&data, query.key())?;
+
+    let response = data
+        .retry
+        .call("spot/kline", Idempotency::Idempotent, || client.klines(param.clone()))
+        .await
+        .map_err(|e| {
+            error!("kline - {} {:?}", e, param);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::Spot, query.key(), &response.rate_limits);
+
     let data = response.data().await.map_err(|e| {
         error!("Failed to get data from response: {}", e);
-        actix_web::error::ErrorInternalServerError(e)
+        ApiError::from(e)
     })?;
 
     // 返回响应
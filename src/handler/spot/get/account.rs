@@ -0,0 +1,344 @@
+use actix_web::middleware::from_fn;
+use actix_web::{HttpResponse, get, web};
+use binance_sdk::spot::rest_api::{
+    self, AccountCommissionParams, GetAccountParams, MyTradesParams, MyTradesResponseInner,
+    RateLimitOrderParams,
+};
+use serde::Deserialize;
+use tracing::{debug, error};
+
+use crate::app::AppState;
+use crate::common::paginate::{TimePager, now_ms};
+use crate::common::params::KeyName;
+use crate::common::retry::Idempotency;
+
+use crate::handler::common::{ApiError, Validation, get_client_from_state};
+use crate::middleware::auth::require_account;
+use crate::stream::market::Product;
+
+// 币安 myTrades 单次查询的时间跨度不能超过 24 小时
+const MY_TRADES_WINDOW_MS: i64 = 24 * 60 * 60 * 1000;
+// 网关分页查询允许的最大时间跨度, 避免一次请求打出过多权重
+const MAX_MY_TRADES_RANGE_MS: i64 = 90 * MY_TRADES_WINDOW_MS;
+const MAX_MY_TRADES_LIMIT: i32 = 1000;
+
+#[derive(Deserialize)]
+struct AccountParamsWrapper {
+    omit_zero_balances: Option<bool>,
+}
+
+impl TryFrom<AccountParamsWrapper> for GetAccountParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: AccountParamsWrapper) -> Result<Self, Self::Error> {
+        Ok(Self::builder()
+            .omit_zero_balances(wrapper.omit_zero_balances)
+            .build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct MyTradesParamsWrapper {
+    symbol: String,
+    order_id: Option<i64>,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    from_id: Option<i64>,
+    limit: Option<i32>,
+}
+
+impl TryFrom<MyTradesParamsWrapper> for MyTradesParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: MyTradesParamsWrapper) -> Result<Self, Self::Error> {
+        // 只传 start_time 时, 结束时间默认为当前时间
+        let end_time = match (wrapper.start_time, wrapper.end_time) {
+            (Some(_), None) => Some(now_ms()),
+            (_, end_time) => end_time,
+        };
+        let range = wrapper
+            .start_time
+            .zip(end_time)
+            .map(|(start, end)| end - start);
+
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                wrapper
+                    .limit
+                    .is_none_or(|limit| (1..=MAX_MY_TRADES_LIMIT).contains(&limit)),
+                "limit",
+                "must be between 1 and 1000",
+            )
+            .check(
+                range.is_none_or(|range| (0..=MAX_MY_TRADES_RANGE_MS).contains(&range)),
+                "end_time",
+                "must be within 90 days after start_time",
+            )
+            .check(
+                wrapper.from_id.is_none()
+                    || (wrapper.start_time.is_none() && wrapper.end_time.is_none()),
+                "from_id",
+                "cannot be sent together with start_time or end_time",
+            )
+            .finish()?;
+
+        Ok(Self::builder(wrapper.symbol)
+            .order_id(wrapper.order_id)
+            .start_time(wrapper.start_time)
+            .end_time(end_time)
+            .from_id(wrapper.from_id)
+            .limit(wrapper.limit)
+            .build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct CommissionRateParamsWrapper {
+    symbol: String,
+}
+
+impl TryFrom<CommissionRateParamsWrapper> for AccountCommissionParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: CommissionRateParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .finish()?;
+
+        Ok(Self::builder(wrapper.symbol).build()?)
+    }
+}
+
+async fn my_trades_page(
+    client: &rest_api::RestApi,
+    data: &AppState,
+    key_name: &str,
+    params: MyTradesParams,
+) -> Result<Vec<MyTradesResponseInner>, ApiError> {
+    let response = data
+        .retry
+        .call("spot/my_trades", Idempotency::Idempotent, || {
+            client.my_trades(params.clone())
+        })
+        .await
+        .map_err(|e| {
+            error!("my_trades: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::Spot, Some(key_name), &response.rate_limits);
+
+    response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })
+}
+
+// 按 24 小时切分时间窗口, 自动翻页拉取整个时间段的成交
+async fn my_trades_by_window(
+    client: &rest_api::RestApi,
+    data: &AppState,
+    key_name: &str,
+    params: MyTradesParams,
+    start_time: i64,
+    end_time: i64,
+) -> Result<Vec<MyTradesResponseInner>, ApiError> {
+    let limit = params.limit.unwrap_or(MAX_MY_TRADES_LIMIT);
+    let pager = TimePager {
+        start_time,
+        end_time,
+        window_ms: MY_TRADES_WINDOW_MS,
+        limit: limit as usize,
+    };
+
+    let trades = pager
+        .collect(
+            |start, end| {
+                let mut page_params = params.clone();
+                page_params.start_time = Some(start);
+                page_params.end_time = Some(end);
+                page_params.limit = Some(limit);
+                my_trades_page(client, data, key_name, page_params)
+            },
+            |trade| trade.time,
+            |trade| trade.id,
+        )
+        .await?;
+
+    debug!(
+        "my_trades: {} trades between {} and {}",
+        trades.len(),
+        start_time,
+        end_time
+    );
+    Ok(trades)
+}
+
+/// 查询现货账户信息
+/// GET /account
+/// 参数:
+/// - omit_zero_balances: true 时不返回余额为 0 的资产 (可选)
+#[get("/account", wrap = "from_fn(require_account)")]
+pub async fn account(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Query<AccountParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = GetAccountParams::try_from(param.into_inner())?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    let response = data
+        .retry
+        .call("spot/account", Idempotency::Idempotent, || {
+            client.get_account(params.clone())
+        })
+        .await
+        .map_err(|e| {
+            error!("account: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::Spot, Some(&query.key), &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// 查询账户成交历史
+/// GET /my_trades
+/// 参数:
+/// - symbol: 交易对 (必填)
+/// - order_id / from_id: 按订单或成交 id 查询 (可选, 此时只查询一页)
+/// - start_time / end_time: 时间跨度不超过 90 天, 网关按 24 小时窗口自动分页 (可选, end_time 默认当前时间)
+/// - limit: 每页数量, 默认 500, 分页时默认 1000, 最大 1000 (可选)
+#[get("/my_trades", wrap = "from_fn(require_account)")]
+pub async fn my_trades(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Query<MyTradesParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = MyTradesParams::try_from(param.into_inner())?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    let trades = match (params.start_time, params.end_time, params.order_id) {
+        (Some(start_time), Some(end_time), None) => {
+            my_trades_by_window(&client, &data, &query.key, params, start_time, end_time).await?
+        }
+        _ => my_trades_page(&client, &data, &query.key, params).await?,
+    };
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(trades))
+}
+
+/// 查询当前的下单频率限制使用情况
+/// GET /order_rate_limit
+#[get("/order_rate_limit", wrap = "from_fn(require_account)")]
+pub async fn order_rate_limit(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    let params = RateLimitOrderParams::builder()
+        .build()
+        .map_err(ApiError::from)?;
+
+    let response = data
+        .retry
+        .call("spot/order_rate_limit", Idempotency::Idempotent, || {
+            client.rate_limit_order(params.clone())
+        })
+        .await
+        .map_err(|e| {
+            error!("order_rate_limit: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::Spot, Some(&query.key), &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// 查询交易对的手续费率
+/// GET /commission_rate
+/// 参数:
+/// - symbol: 交易对 (必填)
+#[get("/commission_rate", wrap = "from_fn(require_account)")]
+pub async fn commission_rate(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Query<CommissionRateParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = AccountCommissionParams::try_from(param.into_inner())?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    let response = data
+        .retry
+        .call("spot/commission_rate", Idempotency::Idempotent, || {
+            client.account_commission(params.clone())
+        })
+        .await
+        .map_err(|e| {
+            error!("commission_rate: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::Spot, Some(&query.key), &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test};
+
+    use crate::handler::common::test_util::{get, invalid_fields, test_state};
+    use crate::handler::spot::routes;
+
+    #[actix_web::test]
+    async fn test_account_queries_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = get(
+            "/spot/my_trades?key=binance1&symbol=BTCUSDT&limit=0&start_time=0&end_time=7776000001",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["limit", "end_time"]);
+
+        let req =
+            get("/spot/my_trades?key=binance1&symbol=BTCUSDT&from_id=1&end_time=1").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["from_id"]);
+
+        let req = get("/spot/commission_rate?key=binance1&symbol=").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["symbol"]);
+    }
+}
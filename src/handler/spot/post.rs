@@ -1,3 +1,6 @@
+pub mod order;
+
+use actix_web::middleware::from_fn;
 use actix_web::{HttpResponse, post, web, web::Query};
 use binance_sdk::spot::rest_api::{KlinesIntervalEnum, KlinesParams};
 use serde::Deserialize;
@@ -5,11 +8,15 @@ use tracing::error;
 use web::Form;
 
 use crate::app::AppState;
-use crate::common::params::KeyName;
-use crate::handler::common::get_client_from_state;
+use crate::common::kline_store::KlineQuery;
+use crate::common::params::OptionalKeyName;
+use crate::common::retry::Idempotency;
+use crate::handler::common::{ApiError, Validation, get_market_client, stored_klines};
+use crate::middleware::auth::require_market_data;
+use crate::stream::market::Product;
 
 #[derive(Deserialize)]
-struct KlinesParamsWrapper {
+pub(crate) struct KlinesParamsWrapper {
     symbol: String,
     interval: KlinesIntervalEnum,
     start_time: Option<i64>,
@@ -17,21 +24,41 @@ struct KlinesParamsWrapper {
     limit: Option<i32>,
 }
 
-impl From<KlinesParamsWrapper> for KlinesParams {
-    fn from(wrapper: KlinesParamsWrapper) -> Self {
-        KlinesParams::builder(wrapper.symbol, wrapper.interval)
+impl TryFrom<KlinesParamsWrapper> for KlinesParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: KlinesParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                wrapper
+                    .limit
+                    .is_none_or(|limit| (1..=1000).contains(&limit)),
+                "limit",
+                "must be between 1 and 1000",
+            )
+            .check(
+                match (wrapper.start_time, wrapper.end_time) {
+                    (Some(start), Some(end)) => start <= end,
+                    _ => true,
+                },
+                "start_time",
+                "must not be after end_time",
+            )
+            .finish()?;
+
+        Ok(KlinesParams::builder(wrapper.symbol, wrapper.interval)
             .start_time(wrapper.start_time)
             .end_time(wrapper.end_time)
             .limit(wrapper.limit)
-            .build()
-            .unwrap()
+            .build()?)
     }
 }
 
-#[post("/kline")]
+#[post("/kline", wrap = "from_fn(require_market_data)")]
 async fn kline(
     data: web::Data<AppState>,
-    query: Query<KeyName>,
+    query: Query<OptionalKeyName>,
     param: Form<KlinesParamsWrapper>,
 ) -> Result<HttpResponse, actix_web::Error> {
     /*
@@ -40,21 +67,58 @@ async fn kline(
     之所以有这样的区别, 论坛上给出的答复是为了给使用 stream 的人方便调用的, 我们这里使用 kline candlestick data
     */
 
+    let param = KlinesParams::try_from(param.into_inner())?;
+
+    if let Some(rows) = stored_klines(&data, query.key(), KlineQuery::from(&param)).await {
+        return Ok(HttpResponse::Ok().json(rows));
+    }
+
     // 调用辅助函数获取客户端
-    let client = get_client_from_state::<binance_sdk::spot::rest_api::RestApi>(&data, &query.key)?;
+    let client = get_market_client::<binance_sdk::spot::rest_api::RestApi>(&data, query.key())?;
 
-    let param: KlinesParams = param.into_inner().into();
     // 调用 API 方法，替换为实际存在的 get_account_info 方法
-    let response = client.klines(param.clone()).await.map_err(|e| {
-        error!("kline - {} {:?}", e, param);
-        actix_web::error::ErrorInternalServerError(e)
-    })?;
+    let response = data
+        .retry
+        .call("spot/kline", Idempotency::Idempotent, || client.klines(param.clone()))
+        .await
+        .map_err(|e| {
+            error!("kline - {} {:?}", e, param);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::Spot, query.key(), &response.rate_limits);
 
     let data = response.data().await.map_err(|e| {
         error!("Failed to get data from response: {}", e);
-        actix_web::error::ErrorInternalServerError(e)
+        ApiError::from(e)
     })?;
 
     // 返回响应
     Ok(HttpResponse::Ok().json(data))
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test};
+
+    use crate::handler::common::test_util::{get, invalid_fields, post_form, test_state};
+    use crate::handler::spot::routes;
+
+    #[actix_web::test]
+    async fn test_kline_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = post_form("/spot/kline?key=binance1", "symbol=BTCUSDT").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["interval"]);
+
+        let req = post_form("/spot/kline?key=binance1", "symbol=&interval=1m&limit=0").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["symbol", "limit"]);
+
+        // 公开行情不需要 key, 也可以通过 GET 调用
+        let req = get("/spot/kline?symbol=BTCUSDT&interval=1m&limit=1001").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["limit"]);
+    }
+}
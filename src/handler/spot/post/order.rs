@@ -0,0 +1,797 @@
+use std::collections::BTreeMap;
+
+use actix_web::middleware::from_fn;
+use actix_web::{HttpResponse, post, web};
+use binance_sdk::spot::rest_api::{
+    self, DeleteOpenOrdersParams, DeleteOrderCancelRestrictionsEnum, DeleteOrderParams,
+    GetOrderParams, GetOrderResponse, NewOrderNewOrderRespTypeEnum, NewOrderParams,
+    NewOrderSelfTradePreventionModeEnum, NewOrderSideEnum, NewOrderTimeInForceEnum,
+    NewOrderTypeEnum, OrderCancelReplaceCancelReplaceModeEnum,
+    OrderCancelReplaceCancelRestrictionsEnum, OrderCancelReplaceNewOrderRespTypeEnum,
+    OrderCancelReplaceOrderRateLimitExceededModeEnum, OrderCancelReplaceParams,
+    OrderCancelReplaceSelfTradePreventionModeEnum, OrderCancelReplaceSideEnum,
+    OrderCancelReplaceTimeInForceEnum, OrderCancelReplaceTypeEnum,
+};
+use reqwest::Method;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tracing::error;
+
+use crate::{
+    app::AppState,
+    common::exchange_info::OrderCheck,
+    common::params::{KeyName, OrderResubmit, OrderRounding},
+    common::retry::{Idempotency, Placed, is_order_not_found},
+    common::submissions::{Begin, new_client_order_id},
+    handler::common::{ApiError, Validation, get_client_from_state, with_client_order_id},
+    middleware::auth::require_trade,
+    stream::market::Product,
+};
+
+// 现货支持的订单类型, 不接受 SDK 中的 NON_REPRESENTABLE
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum OrderTypeEnum {
+    Market,
+    Limit,
+    StopLoss,
+    StopLossLimit,
+    TakeProfit,
+    TakeProfitLimit,
+    LimitMaker,
+}
+
+impl OrderTypeEnum {
+    // 需要 timeInForce 的订单类型
+    fn is_limit(&self) -> bool {
+        matches!(
+            self,
+            Self::Limit | Self::StopLossLimit | Self::TakeProfitLimit
+        )
+    }
+
+    // 需要 price 的订单类型
+    fn has_price(&self) -> bool {
+        self.is_limit() || *self == Self::LimitMaker
+    }
+
+    // 需要 stopPrice 或 trailingDelta 的订单类型
+    fn has_trigger(&self) -> bool {
+        !matches!(self, Self::Market | Self::Limit | Self::LimitMaker)
+    }
+}
+
+impl From<OrderTypeEnum> for NewOrderTypeEnum {
+    fn from(value: OrderTypeEnum) -> Self {
+        match value {
+            OrderTypeEnum::Market => Self::Market,
+            OrderTypeEnum::Limit => Self::Limit,
+            OrderTypeEnum::StopLoss => Self::StopLoss,
+            OrderTypeEnum::StopLossLimit => Self::StopLossLimit,
+            OrderTypeEnum::TakeProfit => Self::TakeProfit,
+            OrderTypeEnum::TakeProfitLimit => Self::TakeProfitLimit,
+            OrderTypeEnum::LimitMaker => Self::LimitMaker,
+        }
+    }
+}
+
+impl From<OrderTypeEnum> for OrderCancelReplaceTypeEnum {
+    fn from(value: OrderTypeEnum) -> Self {
+        match value {
+            OrderTypeEnum::Market => Self::Market,
+            OrderTypeEnum::Limit => Self::Limit,
+            OrderTypeEnum::StopLoss => Self::StopLoss,
+            OrderTypeEnum::StopLossLimit => Self::StopLossLimit,
+            OrderTypeEnum::TakeProfit => Self::TakeProfit,
+            OrderTypeEnum::TakeProfitLimit => Self::TakeProfitLimit,
+            OrderTypeEnum::LimitMaker => Self::LimitMaker,
+        }
+    }
+}
+
+// 下单和撤单再下单共用的订单参数校验
+struct OrderSpec<'a> {
+    symbol: &'a str,
+    order_type: OrderTypeEnum,
+    has_time_in_force: bool,
+    quantity: Option<Decimal>,
+    quote_order_qty: Option<Decimal>,
+    price: Option<Decimal>,
+    stop_price: Option<Decimal>,
+    trailing_delta: Option<i64>,
+    iceberg_qty: Option<Decimal>,
+    new_client_order_id: Option<&'a str>,
+}
+
+impl OrderSpec<'_> {
+    fn check(&self, validation: &mut Validation) {
+        let order_type = self.order_type;
+        let positive = |value: Option<Decimal>| value.is_none_or(|v| v > Decimal::ZERO);
+
+        validation
+            .check(!self.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                positive(self.quantity),
+                "quantity",
+                "must be greater than 0",
+            )
+            .check(
+                positive(self.quote_order_qty),
+                "quote_order_qty",
+                "must be greater than 0",
+            )
+            .check(positive(self.price), "price", "must be greater than 0")
+            .check(
+                positive(self.stop_price),
+                "stop_price",
+                "must be greater than 0",
+            )
+            .check(
+                positive(self.iceberg_qty),
+                "iceberg_qty",
+                "must be greater than 0",
+            )
+            .check(
+                if order_type == OrderTypeEnum::Market {
+                    self.quantity.is_some() != self.quote_order_qty.is_some()
+                } else {
+                    self.quantity.is_some()
+                },
+                "quantity",
+                "required, MARKET orders take either quantity or quote_order_qty",
+            )
+            .check(
+                order_type == OrderTypeEnum::Market || self.quote_order_qty.is_none(),
+                "quote_order_qty",
+                "only supported by MARKET orders",
+            )
+            .check(
+                order_type.has_price() == self.price.is_some(),
+                "price",
+                "required for LIMIT, LIMIT_MAKER and *_LIMIT orders only",
+            )
+            .check(
+                order_type.has_trigger()
+                    == (self.stop_price.is_some() || self.trailing_delta.is_some()),
+                "stop_price",
+                "stop_price or trailing_delta required for STOP_LOSS and TAKE_PROFIT orders only",
+            )
+            .check(
+                !self.has_time_in_force || order_type.is_limit(),
+                "time_in_force",
+                "only supported by LIMIT and *_LIMIT orders",
+            )
+            .check(
+                self.iceberg_qty.is_none() || order_type.has_price(),
+                "iceberg_qty",
+                "only supported by LIMIT, LIMIT_MAKER and *_LIMIT orders",
+            )
+            .check(
+                self.new_client_order_id
+                    .is_none_or(|id| !id.is_empty() && id.len() <= 36),
+                "new_client_order_id",
+                "must be 1 to 36 characters",
+            );
+    }
+}
+
+#[derive(Deserialize)]
+struct NewOrderParamsWrapper {
+    symbol: String,
+    side: NewOrderSideEnum,
+    r#type: OrderTypeEnum,
+    time_in_force: Option<NewOrderTimeInForceEnum>,
+    quantity: Option<Decimal>,
+    quote_order_qty: Option<Decimal>,
+    price: Option<Decimal>,
+    new_client_order_id: Option<String>,
+    stop_price: Option<Decimal>,
+    trailing_delta: Option<i64>,
+    iceberg_qty: Option<Decimal>,
+    new_order_resp_type: Option<NewOrderNewOrderRespTypeEnum>,
+    self_trade_prevention_mode: Option<NewOrderSelfTradePreventionModeEnum>,
+}
+
+impl TryFrom<NewOrderParamsWrapper> for NewOrderParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: NewOrderParamsWrapper) -> Result<Self, Self::Error> {
+        let mut validation = Validation::default();
+        OrderSpec {
+            symbol: &wrapper.symbol,
+            order_type: wrapper.r#type,
+            has_time_in_force: wrapper.time_in_force.is_some(),
+            quantity: wrapper.quantity,
+            quote_order_qty: wrapper.quote_order_qty,
+            price: wrapper.price,
+            stop_price: wrapper.stop_price,
+            trailing_delta: wrapper.trailing_delta,
+            iceberg_qty: wrapper.iceberg_qty,
+            new_client_order_id: wrapper.new_client_order_id.as_deref(),
+        }
+        .check(&mut validation);
+        validation.finish()?;
+
+        // 币安要求限价类订单必须带 timeInForce, 未传时默认 GTC
+        let time_in_force = wrapper.r#type.is_limit().then(|| {
+            wrapper
+                .time_in_force
+                .unwrap_or(NewOrderTimeInForceEnum::Gtc)
+        });
+
+        Ok(
+            Self::builder(wrapper.symbol, wrapper.side, wrapper.r#type.into())
+                .time_in_force(time_in_force)
+                .quantity(wrapper.quantity)
+                .quote_order_qty(wrapper.quote_order_qty)
+                .price(wrapper.price)
+                .new_client_order_id(wrapper.new_client_order_id)
+                .stop_price(wrapper.stop_price)
+                .trailing_delta(wrapper.trailing_delta)
+                .iceberg_qty(wrapper.iceberg_qty)
+                .new_order_resp_type(wrapper.new_order_resp_type)
+                .self_trade_prevention_mode(wrapper.self_trade_prevention_mode)
+                .build()?,
+        )
+    }
+}
+
+// SDK 的 order_test 不会带上订单参数, 这里按 /api/v3/order 的参数名自行组装
+fn order_test_params(
+    params: NewOrderParams,
+    compute_commission_rates: Option<bool>,
+) -> BTreeMap<String, Value> {
+    let mut map = BTreeMap::new();
+    map.insert("symbol".to_string(), json!(params.symbol));
+    map.insert("side".to_string(), json!(params.side));
+    map.insert("type".to_string(), json!(params.r#type));
+
+    let optional = [
+        ("timeInForce", params.time_in_force.map(|v| json!(v))),
+        ("quantity", params.quantity.map(|v| json!(v))),
+        ("quoteOrderQty", params.quote_order_qty.map(|v| json!(v))),
+        ("price", params.price.map(|v| json!(v))),
+        (
+            "newClientOrderId",
+            params.new_client_order_id.map(|v| json!(v)),
+        ),
+        ("stopPrice", params.stop_price.map(|v| json!(v))),
+        ("trailingDelta", params.trailing_delta.map(|v| json!(v))),
+        ("icebergQty", params.iceberg_qty.map(|v| json!(v))),
+        (
+            "newOrderRespType",
+            params.new_order_resp_type.map(|v| json!(v)),
+        ),
+        (
+            "selfTradePreventionMode",
+            params.self_trade_prevention_mode.map(|v| json!(v)),
+        ),
+        (
+            "computeCommissionRates",
+            compute_commission_rates.map(|v| json!(v)),
+        ),
+    ];
+    for (name, value) in optional {
+        if let Some(value) = value {
+            map.insert(name.to_string(), value);
+        }
+    }
+    map
+}
+
+// 按交易规则校验订单, round=true 时先对齐价格和数量
+// 交易规则不可用时跳过, 由币安校验
+async fn check_symbol_filters(
+    data: &web::Data<AppState>,
+    key_name: &str,
+    params: &mut NewOrderParams,
+    round: bool,
+) -> Result<(), ApiError> {
+    let Some(filters) = data
+        .exchange_info
+        .spot_filters(key_name, &params.symbol)
+        .await?
+    else {
+        return Ok(());
+    };
+
+    let is_buy = matches!(params.side, NewOrderSideEnum::Buy);
+    if round {
+        filters.round(
+            is_buy,
+            &mut params.price,
+            &mut params.stop_price,
+            &mut params.quantity,
+        );
+    }
+
+    let mut order = OrderCheck {
+        is_buy,
+        price: params.price,
+        stop_price: params.stop_price,
+        quantity: params.quantity,
+        reference_price: None,
+        check_notional: true,
+    };
+    if filters.needs_reference_price(&order) {
+        order.reference_price = data.exchange_info.spot_avg_price(&params.symbol).await;
+    }
+
+    let mut validation = Validation::default();
+    filters.check(&order, &mut validation);
+    validation.finish()
+}
+
+// 按 client order id 查询订单, 订单不存在时返回 None
+async fn find_order(
+    client: &rest_api::RestApi,
+    symbol: &str,
+    client_order_id: String,
+) -> anyhow::Result<Option<GetOrderResponse>> {
+    let params = GetOrderParams::builder(symbol.to_string())
+        .orig_client_order_id(client_order_id)
+        .build()?;
+    match client.get_order(params).await {
+        Ok(response) => Ok(Some(response.data().await?)),
+        Err(e) if is_order_not_found(&e) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[derive(Deserialize)]
+struct TestOrderQuery {
+    compute_commission_rates: Option<bool>,
+}
+
+/// 创建新订单
+/// POST /new_order
+/// 参数:
+/// - symbol: 交易对 (必填)
+/// - side: BUY / SELL (必填)
+/// - type: MARKET / LIMIT / STOP_LOSS / STOP_LOSS_LIMIT / TAKE_PROFIT / TAKE_PROFIT_LIMIT / LIMIT_MAKER (必填)
+/// - quantity: 数量 (MARKET 订单可改用 quote_order_qty)
+/// - price: 价格 (LIMIT / LIMIT_MAKER / *_LIMIT 必填)
+/// - time_in_force: 有效时间 (可选, 限价类订单默认 GTC)
+/// - stop_price / trailing_delta: 触发条件 (STOP_LOSS / TAKE_PROFIT 类订单必填其一)
+/// - iceberg_qty, new_client_order_id, new_order_resp_type, self_trade_prevention_mode (可选)
+/// - round: true 时把 price / stop_price 对齐到 tickSize, quantity 向下对齐到 stepSize (可选, 放在 query 中)
+/// - resubmit: true 表示重新提交之前结果未知的订单, 网关没有记录时先向币安查询 (可选, 放在 query 中)
+///
+/// 下单前按交易规则校验 PRICE_FILTER, LOT_SIZE, MIN_NOTIONAL / NOTIONAL 和 PERCENT_PRICE
+/// 没有 new_client_order_id 时自动生成, 使用的 id 在每个响应 (包括错误响应) 的 X-Client-Order-Id 中返回
+/// 同一个 id 重复提交时返回第一次下单的结果, 不会重复下单; 跨越网关重启等端到端的幂等需要调用方自带 id
+#[post("/new_order", wrap = "from_fn(require_trade)")]
+async fn new_order(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    rounding: web::Query<OrderRounding>,
+    resubmit: web::Query<OrderResubmit>,
+    param: web::Form<NewOrderParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut params = NewOrderParams::try_from(param.into_inner())?;
+
+    // 没有 new_client_order_id 时自动生成, 下单结果未知时可以按 id 查询后安全重试
+    let generated = params.new_client_order_id.is_none();
+    let client_order_id = params
+        .new_client_order_id
+        .get_or_insert_with(new_client_order_id)
+        .clone();
+
+    let result = submit_order(
+        &data,
+        &query.key,
+        params,
+        &client_order_id,
+        rounding.round,
+        resubmit.resubmit && !generated,
+    )
+    .await;
+    Ok(with_client_order_id(result, &client_order_id))
+}
+
+async fn submit_order(
+    data: &web::Data<AppState>,
+    key_name: &str,
+    mut params: NewOrderParams,
+    client_order_id: &str,
+    round: bool,
+    resubmit: bool,
+) -> Result<HttpResponse, ApiError> {
+    // 同一个 id 已经下过单时返回第一次的响应
+    let submission = match data
+        .order_submissions
+        .begin(Product::Spot, key_name, client_order_id)
+    {
+        Begin::New(submission) => submission,
+        Begin::Done(response) => return Ok(HttpResponse::Ok().json(response)),
+        Begin::Pending => {
+            return Err(ApiError::conflict(format!(
+                "Order {} is already being submitted",
+                client_order_id
+            )));
+        }
+    };
+
+    check_symbol_filters(data, key_name, &mut params, round).await?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(data, key_name)?;
+
+    // 重新提交的 id 不在最近的记录中 (如网关重启后), 先向币安查询该订单是否已存在
+    let existing = if resubmit {
+        find_order(&client, &params.symbol, client_order_id.to_string())
+            .await
+            .map_err(|e| {
+                error!("new_order: {}", e);
+                ApiError::from(e)
+            })?
+    } else {
+        None
+    };
+
+    // 调用 API 方法, 结果未知的下单先按 id 查询, 确认没有下单后才重试
+    let placed = match existing {
+        Some(order) => Placed::Existing(order),
+        None => data
+            .retry
+            .place_order(
+                "spot/new_order",
+                Some(client_order_id),
+                // 市价单和限价单可能立即成交, LIMIT_MAKER 和止损止盈单不会
+                matches!(params.r#type, NewOrderTypeEnum::Market | NewOrderTypeEnum::Limit),
+                || async {
+                    let response = client.new_order(params.clone()).await?;
+                    data.rate_limiter
+                        .record(Product::Spot, Some(key_name), &response.rate_limits);
+                    Ok(response.data().await?)
+                },
+                |client_order_id| find_order(&client, &params.symbol, client_order_id),
+            )
+            .await
+            .map_err(|e| {
+                error!("new_order: {}", e);
+                ApiError::from(e)
+            })?,
+    };
+    let response =
+        serde_json::to_value(&placed).map_err(|e| ApiError::from(anyhow::Error::from(e)))?;
+    submission.finish(&response);
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// 测试下单, 币安只校验参数不会真正下单
+/// POST /test_order?compute_commission_rates=true
+/// 参数与 /new_order 相同
+/// - compute_commission_rates: 是否返回手续费率 (可选, 放在 query 中)
+#[post("/test_order", wrap = "from_fn(require_trade)")]
+async fn test_order(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    test_query: web::Query<TestOrderQuery>,
+    rounding: web::Query<OrderRounding>,
+    param: web::Form<NewOrderParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut params = NewOrderParams::try_from(param.into_inner())?;
+    check_symbol_filters(&data, &query.key, &mut params, rounding.round).await?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    // 调用 API 方法, 测试下单不会真正下单, 可以放心重试
+    let params = order_test_params(params, test_query.compute_commission_rates);
+    let response = data
+        .retry
+        .call("spot/test_order", Idempotency::Idempotent, || {
+            client.send_signed_request::<Value>("/api/v3/order/test", Method::POST, params.clone())
+        })
+        .await
+        .map_err(|e| {
+            error!("test_order: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::Spot, Some(&query.key), &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+#[derive(Deserialize)]
+struct CancelOrderParamsWrapper {
+    symbol: String,
+    order_id: Option<i64>,
+    orig_client_order_id: Option<String>,
+    new_client_order_id: Option<String>,
+    cancel_restrictions: Option<DeleteOrderCancelRestrictionsEnum>,
+}
+
+impl TryFrom<CancelOrderParamsWrapper> for DeleteOrderParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: CancelOrderParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                wrapper.order_id.is_some() || wrapper.orig_client_order_id.is_some(),
+                "order_id",
+                "order_id or orig_client_order_id is required",
+            )
+            .finish()?;
+
+        Ok(Self::builder(wrapper.symbol)
+            .order_id(wrapper.order_id)
+            .orig_client_order_id(wrapper.orig_client_order_id)
+            .new_client_order_id(wrapper.new_client_order_id)
+            .cancel_restrictions(wrapper.cancel_restrictions)
+            .build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct CancelAllOpenOrdersParamsWrapper {
+    symbol: String,
+}
+
+impl TryFrom<CancelAllOpenOrdersParamsWrapper> for DeleteOpenOrdersParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: CancelAllOpenOrdersParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .finish()?;
+
+        Ok(Self::builder(wrapper.symbol).build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct CancelReplaceParamsWrapper {
+    symbol: String,
+    side: OrderCancelReplaceSideEnum,
+    r#type: OrderTypeEnum,
+    cancel_replace_mode: OrderCancelReplaceCancelReplaceModeEnum,
+    time_in_force: Option<OrderCancelReplaceTimeInForceEnum>,
+    quantity: Option<Decimal>,
+    quote_order_qty: Option<Decimal>,
+    price: Option<Decimal>,
+    cancel_order_id: Option<i64>,
+    cancel_orig_client_order_id: Option<String>,
+    cancel_new_client_order_id: Option<String>,
+    new_client_order_id: Option<String>,
+    stop_price: Option<Decimal>,
+    trailing_delta: Option<i64>,
+    iceberg_qty: Option<Decimal>,
+    new_order_resp_type: Option<OrderCancelReplaceNewOrderRespTypeEnum>,
+    self_trade_prevention_mode: Option<OrderCancelReplaceSelfTradePreventionModeEnum>,
+    cancel_restrictions: Option<OrderCancelReplaceCancelRestrictionsEnum>,
+    order_rate_limit_exceeded_mode: Option<OrderCancelReplaceOrderRateLimitExceededModeEnum>,
+}
+
+impl TryFrom<CancelReplaceParamsWrapper> for OrderCancelReplaceParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: CancelReplaceParamsWrapper) -> Result<Self, Self::Error> {
+        let mut validation = Validation::default();
+        OrderSpec {
+            symbol: &wrapper.symbol,
+            order_type: wrapper.r#type,
+            has_time_in_force: wrapper.time_in_force.is_some(),
+            quantity: wrapper.quantity,
+            quote_order_qty: wrapper.quote_order_qty,
+            price: wrapper.price,
+            stop_price: wrapper.stop_price,
+            trailing_delta: wrapper.trailing_delta,
+            iceberg_qty: wrapper.iceberg_qty,
+            new_client_order_id: wrapper.new_client_order_id.as_deref(),
+        }
+        .check(&mut validation);
+        validation
+            .check(
+                wrapper.cancel_order_id.is_some() || wrapper.cancel_orig_client_order_id.is_some(),
+                "cancel_order_id",
+                "cancel_order_id or cancel_orig_client_order_id is required",
+            )
+            .finish()?;
+
+        let time_in_force = wrapper.r#type.is_limit().then(|| {
+            wrapper
+                .time_in_force
+                .unwrap_or(OrderCancelReplaceTimeInForceEnum::Gtc)
+        });
+
+        Ok(Self::builder(
+            wrapper.symbol,
+            wrapper.side,
+            wrapper.r#type.into(),
+            wrapper.cancel_replace_mode,
+        )
+        .time_in_force(time_in_force)
+        .quantity(wrapper.quantity)
+        .quote_order_qty(wrapper.quote_order_qty)
+        .price(wrapper.price)
+        .cancel_order_id(wrapper.cancel_order_id)
+        .cancel_orig_client_order_id(wrapper.cancel_orig_client_order_id)
+        .cancel_new_client_order_id(wrapper.cancel_new_client_order_id)
+        .new_client_order_id(wrapper.new_client_order_id)
+        .stop_price(wrapper.stop_price)
+        .trailing_delta(wrapper.trailing_delta)
+        .iceberg_qty(wrapper.iceberg_qty)
+        .new_order_resp_type(wrapper.new_order_resp_type)
+        .self_trade_prevention_mode(wrapper.self_trade_prevention_mode)
+        .cancel_restrictions(wrapper.cancel_restrictions)
+        .order_rate_limit_exceeded_mode(wrapper.order_rate_limit_exceeded_mode)
+        .build()?)
+    }
+}
+
+/// 撤销订单
+/// POST /cancel_order
+/// 参数:
+/// - symbol: 交易对 (必填)
+/// - order_id / orig_client_order_id: 二选一
+/// - new_client_order_id, cancel_restrictions (可选)
+#[post("/cancel_order", wrap = "from_fn(require_trade)")]
+async fn cancel_order(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Form<CancelOrderParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = DeleteOrderParams::try_from(param.into_inner())?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    let response = data
+        .retry
+        .call("spot/cancel_order", Idempotency::NonIdempotent, || {
+            client.delete_order(params.clone())
+        })
+        .await
+        .map_err(|e| {
+            error!("cancel_order: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::Spot, Some(&query.key), &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// 撤销交易对的全部挂单
+/// POST /cancel_all_open_orders
+/// 参数:
+/// - symbol: 交易对 (必填)
+#[post("/cancel_all_open_orders", wrap = "from_fn(require_trade)")]
+async fn cancel_all_open_orders(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Form<CancelAllOpenOrdersParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = DeleteOpenOrdersParams::try_from(param.into_inner())?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    let response = data
+        .retry
+        .call(
+            "spot/cancel_all_open_orders",
+            Idempotency::NonIdempotent,
+            || client.delete_open_orders(params.clone()),
+        )
+        .await
+        .map_err(|e| {
+            error!("cancel_all_open_orders: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::Spot, Some(&query.key), &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// 撤销订单并下新单
+/// POST /cancel_replace
+/// 参数:
+/// - cancel_replace_mode: STOP_ON_FAILURE / ALLOW_FAILURE (必填)
+/// - cancel_order_id / cancel_orig_client_order_id: 要撤销的订单, 二选一
+/// - cancel_new_client_order_id, cancel_restrictions, order_rate_limit_exceeded_mode (可选)
+/// - 其余新订单参数与 /new_order 相同
+#[post("/cancel_replace", wrap = "from_fn(require_trade)")]
+async fn cancel_replace(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Form<CancelReplaceParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = OrderCancelReplaceParams::try_from(param.into_inner())?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    let response = data
+        .retry
+        .call("spot/cancel_replace", Idempotency::NonIdempotent, || {
+            client.order_cancel_replace(params.clone())
+        })
+        .await
+        .map_err(|e| {
+            error!("cancel_replace: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::Spot, Some(&query.key), &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test};
+
+    use crate::handler::common::test_util::{invalid_fields, post_form, test_state};
+    use crate::handler::spot::routes;
+
+    #[actix_web::test]
+    async fn test_new_order_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = post_form(
+            "/spot/new_order?key=binance1",
+            "symbol=BTCUSDT&side=BUY&type=MARKET&quantity=1&quote_order_qty=100",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["quantity"]);
+
+        let req = post_form(
+            "/spot/test_order?key=binance1",
+            "symbol=BTCUSDT&side=SELL&type=STOP_LOSS_LIMIT&quantity=1&time_in_force=GTC",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["price", "stop_price"]);
+    }
+
+    #[actix_web::test]
+    async fn test_order_lifecycle_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = post_form("/spot/cancel_order?key=binance1", "symbol=BTCUSDT").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["order_id"]);
+
+        let req = post_form(
+            "/spot/cancel_replace?key=binance1",
+            "symbol=BTCUSDT&side=BUY&type=LIMIT&cancel_replace_mode=STOP_ON_FAILURE&quantity=1&price=100",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["cancel_order_id"]);
+    }
+}
@@ -0,0 +1,46 @@
+pub mod get;
+pub mod post;
+
+use actix_web::middleware::from_fn;
+use actix_web::web;
+use binance_sdk::sub_account::rest_api::RestApi;
+
+use crate::app::AppState;
+use crate::handler::common::{
+    ApiError, form_config, get_client_from_state, json_config, query_config,
+};
+use crate::middleware::auth::bearer_auth;
+
+// 子账户客户端只为母账户 key 创建, 已配置的非母账户 key 返回 403
+fn get_master_client(data: &web::Data<AppState>, key_name: &str) -> Result<RestApi, ApiError> {
+    get_client_from_state::<RestApi>(data, key_name).map_err(|e| {
+        if data
+            .rest_spot_clients
+            .lock()
+            .unwrap()
+            .contains_key(key_name)
+        {
+            ApiError::forbidden(format!("Key {} is not a master account", key_name))
+        } else {
+            e
+        }
+    })
+}
+
+pub fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/sub_account")
+            .wrap(from_fn(bearer_auth))
+            .app_data(form_config())
+            .app_data(query_config())
+            .app_data(json_config())
+            // GET method
+            .service(get::list)
+            .service(get::spot_assets)
+            .service(get::futures_account)
+            .service(get::futures_summary)
+            .service(get::universal_transfer_history)
+            // POST method
+            .service(post::universal_transfer),
+    );
+}
@@ -0,0 +1,372 @@
+use actix_web::middleware::from_fn;
+use actix_web::{HttpResponse, get, web};
+use binance_sdk::sub_account::rest_api::{
+    GetDetailOnSubAccountsFuturesAccountV2Params, GetSummaryOfSubAccountsFuturesAccountV2Params,
+    QuerySubAccountAssetsParams, QuerySubAccountListParams, QueryUniversalTransferHistoryParams,
+};
+use serde::Deserialize;
+use tracing::error;
+
+use super::get_master_client;
+use crate::app::AppState;
+use crate::common::params::KeyName;
+use crate::handler::common::{ApiError, Validation};
+use crate::middleware::auth::require_account;
+
+// futures_type: 1 为 U 本位合约, 2 为币本位合约
+const FUTURES_TYPES: [i64; 2] = [1, 2];
+const DEFAULT_FUTURES_TYPE: i64 = 1;
+
+#[derive(Deserialize)]
+struct ListParamsWrapper {
+    email: Option<String>,
+    is_freeze: Option<bool>,
+    page: Option<i64>,
+    limit: Option<i64>,
+}
+
+impl TryFrom<ListParamsWrapper> for QuerySubAccountListParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: ListParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(
+                wrapper.page.is_none_or(|page| page >= 1),
+                "page",
+                "must be greater than 0",
+            )
+            .check(
+                wrapper.limit.is_none_or(|limit| (1..=200).contains(&limit)),
+                "limit",
+                "must be between 1 and 200",
+            )
+            .finish()?;
+
+        Ok(Self::builder()
+            .email(wrapper.email.filter(|email| !email.is_empty()))
+            .is_freeze(wrapper.is_freeze.map(|v| v.to_string()))
+            .page(wrapper.page)
+            .limit(wrapper.limit)
+            .build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct SpotAssetsParamsWrapper {
+    email: String,
+}
+
+impl TryFrom<SpotAssetsParamsWrapper> for QuerySubAccountAssetsParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: SpotAssetsParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(!wrapper.email.is_empty(), "email", "must not be empty")
+            .finish()?;
+
+        Ok(Self::builder(wrapper.email).build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct FuturesAccountParamsWrapper {
+    email: String,
+    futures_type: Option<i64>,
+}
+
+impl TryFrom<FuturesAccountParamsWrapper> for GetDetailOnSubAccountsFuturesAccountV2Params {
+    type Error = ApiError;
+
+    fn try_from(wrapper: FuturesAccountParamsWrapper) -> Result<Self, Self::Error> {
+        let futures_type = wrapper.futures_type.unwrap_or(DEFAULT_FUTURES_TYPE);
+        Validation::default()
+            .check(!wrapper.email.is_empty(), "email", "must not be empty")
+            .check(
+                FUTURES_TYPES.contains(&futures_type),
+                "futures_type",
+                "must be 1 (USDS-M) or 2 (COIN-M)",
+            )
+            .finish()?;
+
+        Ok(Self::builder(wrapper.email, futures_type).build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct FuturesSummaryParamsWrapper {
+    futures_type: Option<i64>,
+    page: Option<i64>,
+    limit: Option<i64>,
+}
+
+impl TryFrom<FuturesSummaryParamsWrapper> for GetSummaryOfSubAccountsFuturesAccountV2Params {
+    type Error = ApiError;
+
+    fn try_from(wrapper: FuturesSummaryParamsWrapper) -> Result<Self, Self::Error> {
+        let futures_type = wrapper.futures_type.unwrap_or(DEFAULT_FUTURES_TYPE);
+        Validation::default()
+            .check(
+                FUTURES_TYPES.contains(&futures_type),
+                "futures_type",
+                "must be 1 (USDS-M) or 2 (COIN-M)",
+            )
+            .check(
+                wrapper.page.is_none_or(|page| page >= 1),
+                "page",
+                "must be greater than 0",
+            )
+            .check(
+                wrapper.limit.is_none_or(|limit| (1..=20).contains(&limit)),
+                "limit",
+                "must be between 1 and 20",
+            )
+            .finish()?;
+
+        Ok(Self::builder(futures_type)
+            .page(wrapper.page)
+            .limit(wrapper.limit)
+            .build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct TransferHistoryParamsWrapper {
+    from_email: Option<String>,
+    to_email: Option<String>,
+    client_tran_id: Option<String>,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    page: Option<i64>,
+    limit: Option<i64>,
+}
+
+impl TryFrom<TransferHistoryParamsWrapper> for QueryUniversalTransferHistoryParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: TransferHistoryParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(
+                wrapper.from_email.is_none() || wrapper.to_email.is_none(),
+                "to_email",
+                "cannot be sent together with from_email",
+            )
+            .check(
+                match (wrapper.start_time, wrapper.end_time) {
+                    (Some(start), Some(end)) => start <= end,
+                    _ => true,
+                },
+                "start_time",
+                "must not be after end_time",
+            )
+            .check(
+                wrapper.page.is_none_or(|page| page >= 1),
+                "page",
+                "must be greater than 0",
+            )
+            .check(
+                wrapper.limit.is_none_or(|limit| (1..=500).contains(&limit)),
+                "limit",
+                "must be between 1 and 500",
+            )
+            .finish()?;
+
+        Ok(Self::builder()
+            .from_email(wrapper.from_email.filter(|email| !email.is_empty()))
+            .to_email(wrapper.to_email.filter(|email| !email.is_empty()))
+            .client_tran_id(wrapper.client_tran_id)
+            .start_time(wrapper.start_time)
+            .end_time(wrapper.end_time)
+            .page(wrapper.page)
+            .limit(wrapper.limit)
+            .build()?)
+    }
+}
+
+/// 查询子账户列表
+/// GET /list
+/// 参数:
+/// - email: 子账户邮箱 (可选)
+/// - is_freeze: true / false (可选)
+/// - page: 默认 1 (可选)
+/// - limit: 默认 1, 最大 200 (可选)
+#[get("/list", wrap = "from_fn(require_account)")]
+pub async fn list(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Query<ListParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = QuerySubAccountListParams::try_from(param.into_inner())?;
+
+    // 只有母账户 key 可以调用
+    let client = get_master_client(&data, &query.key)?;
+
+    let response = client.query_sub_account_list(params).await.map_err(|e| {
+        error!("list: {}", e);
+        ApiError::from(e)
+    })?;
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// 查询子账户现货资产
+/// GET /spot_assets
+/// 参数:
+/// - email: 子账户邮箱 (必填)
+#[get("/spot_assets", wrap = "from_fn(require_account)")]
+pub async fn spot_assets(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Query<SpotAssetsParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = QuerySubAccountAssetsParams::try_from(param.into_inner())?;
+
+    // 只有母账户 key 可以调用
+    let client = get_master_client(&data, &query.key)?;
+
+    let response = client.query_sub_account_assets(params).await.map_err(|e| {
+        error!("spot_assets: {}", e);
+        ApiError::from(e)
+    })?;
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// 查询子账户合约账户详情
+/// GET /futures_account
+/// 参数:
+/// - email: 子账户邮箱 (必填)
+/// - futures_type: 1 U 本位 / 2 币本位, 默认 1 (可选)
+#[get("/futures_account", wrap = "from_fn(require_account)")]
+pub async fn futures_account(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Query<FuturesAccountParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = GetDetailOnSubAccountsFuturesAccountV2Params::try_from(param.into_inner())?;
+
+    // 只有母账户 key 可以调用
+    let client = get_master_client(&data, &query.key)?;
+
+    let response = client
+        .get_detail_on_sub_accounts_futures_account_v2(params)
+        .await
+        .map_err(|e| {
+            error!("futures_account: {}", e);
+            ApiError::from(e)
+        })?;
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// 查询所有子账户的合约账户汇总
+/// GET /futures_summary
+/// 参数:
+/// - futures_type: 1 U 本位 / 2 币本位, 默认 1 (可选)
+/// - page: 默认 1 (可选)
+/// - limit: 默认 10, 最大 20 (可选)
+#[get("/futures_summary", wrap = "from_fn(require_account)")]
+pub async fn futures_summary(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Query<FuturesSummaryParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = GetSummaryOfSubAccountsFuturesAccountV2Params::try_from(param.into_inner())?;
+
+    // 只有母账户 key 可以调用
+    let client = get_master_client(&data, &query.key)?;
+
+    let response = client
+        .get_summary_of_sub_accounts_futures_account_v2(params)
+        .await
+        .map_err(|e| {
+            error!("futures_summary: {}", e);
+            ApiError::from(e)
+        })?;
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// 查询万能划转历史
+/// GET /universal_transfer_history
+/// 参数:
+/// - from_email / to_email: 转出或转入的子账户邮箱, 不能同时传 (可选)
+/// - client_tran_id (可选)
+/// - start_time / end_time: 不传时返回最近 30 天 (可选)
+/// - page: 默认 1 (可选)
+/// - limit: 默认 500, 最大 500 (可选)
+#[get("/universal_transfer_history", wrap = "from_fn(require_account)")]
+pub async fn universal_transfer_history(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Query<TransferHistoryParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = QueryUniversalTransferHistoryParams::try_from(param.into_inner())?;
+
+    // 只有母账户 key 可以调用
+    let client = get_master_client(&data, &query.key)?;
+
+    let response = client
+        .query_universal_transfer_history(params)
+        .await
+        .map_err(|e| {
+            error!("universal_transfer_history: {}", e);
+            ApiError::from(e)
+        })?;
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test};
+
+    use crate::handler::common::test_util::{get, invalid_fields, test_state};
+    use crate::handler::sub_account::routes;
+
+    #[actix_web::test]
+    async fn test_sub_account_queries_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req =
+            get("/sub_account/futures_account?key=binance1&email=&futures_type=3").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["email", "futures_type"]);
+
+        let req = get(
+            "/sub_account/universal_transfer_history?key=binance1&from_email=a@x.com&to_email=b@x.com&limit=501",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["to_email", "limit"]);
+    }
+}
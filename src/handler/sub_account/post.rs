@@ -0,0 +1,205 @@
+use actix_web::middleware::from_fn;
+use actix_web::{HttpResponse, post, web};
+use binance_sdk::sub_account::rest_api::UniversalTransferParams;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use tracing::{error, info};
+
+use super::get_master_client;
+use crate::app::AppState;
+use crate::common::params::KeyName;
+use crate::handler::common::{ApiError, Validation};
+use crate::middleware::auth::require_transfer;
+
+// SDK 中万能划转的账户类型是 String, 这里定义币安支持的账户类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum AccountTypeEnum {
+    Spot,
+    UsdtFuture,
+    CoinFuture,
+    Margin,
+    IsolatedMargin,
+}
+
+impl AccountTypeEnum {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Spot => "SPOT",
+            Self::UsdtFuture => "USDT_FUTURE",
+            Self::CoinFuture => "COIN_FUTURE",
+            Self::Margin => "MARGIN",
+            Self::IsolatedMargin => "ISOLATED_MARGIN",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct UniversalTransferParamsWrapper {
+    from_account_type: AccountTypeEnum,
+    to_account_type: AccountTypeEnum,
+    asset: String,
+    amount: Decimal,
+    from_email: Option<String>,
+    to_email: Option<String>,
+    client_tran_id: Option<String>,
+    symbol: Option<String>,
+}
+
+impl TryFrom<UniversalTransferParamsWrapper> for UniversalTransferParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: UniversalTransferParamsWrapper) -> Result<Self, Self::Error> {
+        let from_email = wrapper.from_email.filter(|email| !email.is_empty());
+        let to_email = wrapper.to_email.filter(|email| !email.is_empty());
+        let isolated = wrapper.from_account_type == AccountTypeEnum::IsolatedMargin
+            || wrapper.to_account_type == AccountTypeEnum::IsolatedMargin;
+
+        Validation::default()
+            .check(!wrapper.asset.is_empty(), "asset", "must not be empty")
+            .check(
+                wrapper.amount > Decimal::ZERO,
+                "amount",
+                "must be greater than 0",
+            )
+            .check(
+                from_email.is_some() || to_email.is_some(),
+                "from_email",
+                "from_email or to_email is required",
+            )
+            .check(
+                !isolated || wrapper.symbol.as_ref().is_some_and(|s| !s.is_empty()),
+                "symbol",
+                "required for ISOLATED_MARGIN transfers",
+            )
+            .finish()?;
+
+        Ok(Self::builder(
+            wrapper.from_account_type.as_str().to_string(),
+            wrapper.to_account_type.as_str().to_string(),
+            wrapper.asset,
+            wrapper.amount,
+        )
+        .from_email(from_email)
+        .to_email(to_email)
+        .client_tran_id(wrapper.client_tran_id)
+        .symbol(wrapper.symbol.filter(|symbol| !symbol.is_empty()))
+        .build()?)
+    }
+}
+
+/// 母子账户之间的万能划转
+/// POST /universal_transfer
+/// 参数:
+/// - from_account_type / to_account_type: SPOT / USDT_FUTURE / COIN_FUTURE / MARGIN / ISOLATED_MARGIN (必填)
+/// - asset: 资产 (必填)
+/// - amount: 数量 (必填)
+/// - from_email / to_email: 子账户邮箱, 不传表示母账户, 至少传一个
+/// - client_tran_id: 自定义划转 id (可选)
+/// - symbol: 逐仓杠杆交易对 (ISOLATED_MARGIN 必填)
+///
+/// 资金会转出当前账户, 需要 transfer 权限, trade 权限的 token 不能调用
+#[post("/universal_transfer", wrap = "from_fn(require_transfer)")]
+pub async fn universal_transfer(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Form<UniversalTransferParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = UniversalTransferParams::try_from(param.into_inner())?;
+
+    // 只有母账户 key 可以调用
+    let client = get_master_client(&data, &query.key)?;
+
+    // 资金划转记录到日志, 便于对账
+    info!(
+        "universal_transfer: key={} {} {} {:?} -> {:?}",
+        query.key, params.amount, params.asset, params.from_email, params.to_email
+    );
+
+    let response = client.universal_transfer(params).await.map_err(|e| {
+        error!("universal_transfer: {}", e);
+        ApiError::from(e)
+    })?;
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{App, test};
+    use binance_sdk::config::ConfigurationRestApi;
+    use binance_sdk::spot::SpotRestApi;
+
+    use crate::config::{AuthConfig, AuthToken, Permission};
+    use crate::handler::common::test_util::{
+        TEST_TOKEN, invalid_fields, post_form, test_state, test_state_with,
+    };
+    use crate::handler::sub_account::routes;
+
+    #[actix_web::test]
+    async fn test_universal_transfer_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = post_form(
+            "/sub_account/universal_transfer?key=binance1",
+            "from_account_type=SPOT&to_account_type=ISOLATED_MARGIN&asset=USDT&amount=0",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            invalid_fields(resp).await,
+            vec!["amount", "from_email", "symbol"]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_non_master_key_forbidden() {
+        let state = test_state();
+        // sub1 只有现货客户端, 不是母账户 key
+        state.rest_spot_clients.lock().unwrap().insert(
+            "sub1".to_string(),
+            SpotRestApi::production(ConfigurationRestApi::builder().build().unwrap()),
+        );
+        let app = test::init_service(App::new().app_data(state).configure(routes)).await;
+
+        let body =
+            "from_account_type=SPOT&to_account_type=SPOT&asset=USDT&amount=1&to_email=a@x.com";
+        let req = post_form("/sub_account/universal_transfer?key=sub1", body).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 403);
+
+        let req = post_form("/sub_account/universal_transfer?key=unknown", body).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn test_transfer_permission() {
+        // trade 权限的 token 不能划转
+        let state = test_state_with(|state| {
+            state.auth = Arc::new(AuthConfig {
+                tokens: vec![AuthToken {
+                    name: "bot1".to_string(),
+                    token: TEST_TOKEN.to_string(),
+                    keys: vec!["*".to_string()],
+                    permission: Permission::Trade,
+                }],
+            });
+        });
+        let app = test::init_service(App::new().app_data(state).configure(routes)).await;
+
+        let body =
+            "from_account_type=SPOT&to_account_type=SPOT&asset=USDT&amount=1&to_email=a@x.com";
+        let req = post_form("/sub_account/universal_transfer?key=binance1", body).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 403);
+    }
+}
@@ -1,21 +1,54 @@
 pub mod get;
 pub mod post;
 
+use actix_web::middleware::from_fn;
 use actix_web::web;
 
+use crate::handler::common::{form_config, json_config, query_config};
+use crate::middleware::auth::bearer_auth;
+
 pub fn routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/usds_future")
+            .wrap(from_fn(bearer_auth))
+            .app_data(form_config())
+            .app_data(query_config())
+            .app_data(json_config())
             // GET method
             .service(get::account::account_information)
             .service(get::account::account_balance)
             .service(get::exchange::exchange_information)
+            .service(get::kline::kline)
             .service(get::position::position_information)
+            .service(get::order::query_order)
+            .service(get::order::current_open_orders)
+            .service(get::order::all_orders)
+            .service(get::trade::user_trades)
+            .service(get::trade::income_history)
+            .service(get::trade::commission_rate)
+            .service(get::market::depth)
+            .service(get::market::trades)
+            .service(get::market::agg_trades)
+            .service(get::market::ticker_24hr)
+            .service(get::market::book_ticker)
+            .service(get::market::mark_price)
+            .service(get::market::funding_rate)
+            .service(get::market::open_interest)
+            .service(get::market::open_interest_hist)
+            .service(get::market::long_short_ratio)
+            .service(get::market::mark_price_kline)
+            .service(get::market::index_price_kline)
+            .service(get::market::premium_index_kline)
             // POST method
             .service(post::position::change_position_mode)
             .service(post::leverage::change_initial_leverage)
             .service(post::margin::change_margin_type)
             .service(post::kline::kline)
-            .service(post::order::new_order),
+            .service(post::order::new_order)
+            .service(post::order::cancel_order)
+            .service(post::order::cancel_all_open_orders)
+            .service(post::order::modify_order)
+            .service(post::batch_order::batch_orders)
+            .service(post::batch_order::cancel_batch_orders),
     );
 }
\ No newline at end of file
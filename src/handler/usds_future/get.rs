@@ -1,3 +1,7 @@
 pub mod account;
 pub mod position;
-pub mod exchange;
\ No newline at end of file
+pub mod exchange;
+pub mod kline;
+pub mod market;
+pub mod order;
+pub mod trade;
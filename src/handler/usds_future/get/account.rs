@@ -1,13 +1,17 @@
+use actix_web::middleware::from_fn;
 use actix_web::{HttpResponse, get, web};
 use binance_sdk::derivatives_trading_usds_futures::rest_api;
 use tracing::error;
 
 use crate::app::AppState;
-use crate::common::params::KeyName;
+use crate::common::params::{CacheControl, KeyName};
+use crate::common::retry::Idempotency;
 
-use crate::handler::common::get_client_from_state;
+use crate::handler::common::{ApiError, get_client_from_state};
+use crate::middleware::auth::require_account;
+use crate::stream::market::Product;
 
-#[get("/account_information")]
+#[get("/account_information", wrap = "from_fn(require_account)")]
 pub async fn account_information(
     data: web::Data<AppState>,
     query: web::Query<KeyName>,
@@ -19,43 +23,77 @@ pub async fn account_information(
     let params = rest_api::AccountInformationV3Params::default();
 
     // 调用 API 方法，替换为实际存在的 get_account_info 方法
-    let response = client.account_information_v3(params).await.map_err(|e| {
-        error!("account_information: {}", e);
-        actix_web::error::ErrorInternalServerError(e)
-    })?;
+    let response = data
+        .retry
+        .call(
+            "usds_future/account_information",
+            Idempotency::Idempotent,
+            || client.account_information_v3(params.clone()),
+        )
+        .await
+        .map_err(|e| {
+            error!("account_information: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, Some(&query.key), &response.rate_limits);
 
     let data = response.data().await.map_err(|e| {
         error!("Failed to get data from response: {}", e);
-        actix_web::error::ErrorInternalServerError(e)
+        ApiError::from(e)
     })?;
 
     // 返回响应
     Ok(HttpResponse::Ok().json(data))
 }
 
-#[get("/account_balance")]
+/// 查询账户余额
+/// GET /account_balance
+/// 参数:
+/// - fresh: true 时绕过本地缓存直接请求币安 (可选)
+///
+/// 缓存只返回用户数据流推送的钱包余额, 可用余额, 最大可转出金额和未实现盈亏需要 fresh=true
+#[get("/account_balance", wrap = "from_fn(require_account)")]
 pub async fn account_balance(
     data: web::Data<AppState>,
     query: web::Query<KeyName>,
+    cache: web::Query<CacheControl>,
 ) -> Result<HttpResponse, actix_web::Error> {
     // 调用辅助函数获取客户端
     let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
 
+    // 优先使用用户数据流维护的缓存
+    if !cache.fresh
+        && let Some(balances) = data
+            .usds_future_account_cache
+            .balances(&query.key, &client)
+            .await
+    {
+        return Ok(HttpResponse::Ok().json(balances));
+    }
+
     // 设置 API 参数
     let params = rest_api::FuturesAccountBalanceV3Params::default();
 
     // 调用 API 方法，替换为实际存在的 get_account_info 方法
-    let response = client
-        .futures_account_balance_v3(params)
+    let response = data
+        .retry
+        .call(
+            "usds_future/account_balance",
+            Idempotency::Idempotent,
+            || client.futures_account_balance_v3(params.clone()),
+        )
         .await
         .map_err(|e| {
             error!("account_balance: {}", e);
-            actix_web::error::ErrorInternalServerError(e)
+            ApiError::from(e)
         })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, Some(&query.key), &response.rate_limits);
 
     let data = response.data().await.map_err(|e| {
         error!("Failed to get data from response: {}", e);
-        actix_web::error::ErrorInternalServerError(e)
+        ApiError::from(e)
     })?;
 
     // 返回响应
@@ -1,29 +1,55 @@
+use actix_web::middleware::from_fn;
 use actix_web::{HttpResponse, get, web};
 use binance_sdk::derivatives_trading_usds_futures::rest_api;
 use tracing::error;
 
 use crate::app::AppState;
-use crate::common::params::KeyName;
+use crate::common::params::{CacheControl, OptionalKeyName};
+use crate::common::retry::Idempotency;
 
-use crate::handler::common::get_client_from_state;
+use crate::handler::common::{ApiError, get_market_client};
+use crate::middleware::auth::require_market_data;
+use crate::stream::market::Product;
 
-#[get("/exchange_information")]
+/// 交易规则
+/// GET /exchange_information
+/// 参数:
+/// - fresh: true 时绕过本地缓存直接请求币安 (可选)
+///
+/// 正式环境的交易规则由网关缓存并定期刷新, 测试网和自定义 base_url 的 key 直接请求币安
+#[get("/exchange_information", wrap = "from_fn(require_market_data)")]
 pub async fn exchange_information(
     data: web::Data<AppState>,
-    query: web::Query<KeyName>,
+    query: web::Query<OptionalKeyName>,
+    cache: web::Query<CacheControl>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    if !cache.fresh && data.exchange_info.covers(query.key()) {
+        let info = data.exchange_info.usds_future_info().await?;
+        return Ok(HttpResponse::Ok().json(info.as_ref()));
+    }
+
     // 调用辅助函数获取客户端
-    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+    let client = get_market_client::<rest_api::RestApi>(&data, query.key())?;
 
     // 调用 API 方法，替换为实际存在的 get_account_info 方法
-    let response = client.exchange_information().await.map_err(|e| {
-        error!("exchange_information: {}", e);
-        actix_web::error::ErrorInternalServerError(e)
-    })?;
+    let response = data
+        .retry
+        .call(
+            "usds_future/exchange_information",
+            Idempotency::Idempotent,
+            || client.exchange_information(),
+        )
+        .await
+        .map_err(|e| {
+            error!("exchange_information: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, query.key(), &response.rate_limits);
 
     let data = response.data().await.map_err(|e| {
         error!("Failed to get data from response: {}", e);
-        actix_web::error::ErrorInternalServerError(e)
+        ApiError::from(e)
     })?;
 
     // 返回响应
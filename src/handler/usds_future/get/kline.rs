@@ -0,0 +1,175 @@
+use actix_web::middleware::from_fn;
+use actix_web::{HttpResponse, get, web};
+use binance_sdk::derivatives_trading_usds_futures::rest_api::{self, KlineCandlestickDataParams};
+use tracing::error;
+
+use crate::app::AppState;
+use crate::common::kline_store::KlineQuery;
+use crate::common::params::OptionalKeyName;
+use crate::common::retry::Idempotency;
+use crate::handler::common::{ApiError, get_market_client, stored_klines};
+use crate::handler::usds_future::post::kline::KlineCandlestickDataParamsWrapper;
+use crate::middleware::auth::require_market_data;
+use crate::stream::market::Product;
+
+/// K 线数据, 参数与 POST /kline 相同, 通过 query 传递
+/// GET /kline?symbol=BTCUSDT&interval=1m
+///
+/// 配置了 [kline_store] 且请求范围已同步到本地时, 从本地 K 线存储返回
+#[get("/kline", wrap = "from_fn(require_market_data)")]
+pub async fn kline(
+    data: web::Data<AppState>,
+    query: web::Query<OptionalKeyName>,
+    param: web::Query<KlineCandlestickDataParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let param = KlineCandlestickDataParams::try_from(param.into_inner())?;
+
+    if let Some(rows) = stored_klines(&data, query.key(), KlineQuery::from(&param)).await {
+        return Ok(HttpResponse::Ok().json(rows));
+    }
+
+    // 调用辅助函数获取客户端
+    let client = get_market_client::<rest_api::RestApi>(&data, query.key())?;
+
+    let response = data
+        .retry
+        .call("usds_future/kline", Idempotency::Idempotent, || {
+            client.kline_candlestick_data(param.clone())
+        })
+        .await
+        .map_err(|e| {
+            error!("kline - {} {:?}", e, param);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, query.key(), &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use actix_web::{App, test, web};
+    use serde_json::{Value, json};
+
+    use crate::common::kline_store::{KlineQuery, KlineStore};
+    use crate::common::klines::KlineClient;
+    use crate::common::paginate::now_ms;
+    use crate::config::{KlineSeries, KlineStoreConfig};
+    use crate::handler::common::test_util::{
+        MINUTE, get, invalid_fields, kline_rows, state_with_usds_future_stub, stub_klines,
+        test_state,
+    };
+    use crate::handler::usds_future::routes;
+    use crate::stream::market::Product;
+
+    #[actix_web::test]
+    async fn test_get_kline_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = get("/usds_future/kline?symbol=BTCUSDT&interval=1m&limit=0").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["limit"]);
+
+        // 传了未配置的 key 时不会回退到无 key 客户端
+        let req = get("/usds_future/kline?key=unknown&symbol=BTCUSDT&interval=1m").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn test_get_kline_from_store() {
+        let starts = web::Data::new(Mutex::new(Vec::<i64>::new()));
+        let series = KlineSeries {
+            product: Product::UsdsFuture,
+            symbol: "BTCUSDT".to_string(),
+            interval: "1m".to_string(),
+            start_time: (now_ms() / MINUTE - 30) * MINUTE,
+        };
+        let config = KlineStoreConfig {
+            path: ":memory:".to_string(),
+            sync_interval_secs: 60,
+            series: vec![series.clone()],
+        };
+        let store = Arc::new(KlineStore::open(&config, &HashMap::new()).unwrap());
+        let state = state_with_usds_future_stub(
+            {
+                let starts = starts.clone();
+                move |cfg| {
+                    cfg.app_data(starts.clone())
+                        .route("/fapi/v1/klines", web::get().to(stub_klines));
+                }
+            },
+            |state| {
+                let usds_future =
+                    KlineClient::UsdsFuture(Box::new(state.public_usds_future_client.clone()));
+                let spot = KlineClient::Spot(Box::new(state.public_spot_client.clone()));
+                actix_web::rt::spawn(store.clone().sync_periodically(
+                    state.rate_limiter.clone(),
+                    usds_future,
+                    spot,
+                ));
+                state.kline_store = Some(store.clone());
+            },
+        )
+        .await;
+
+        // 等待后台的首次同步保存已收盘的 30 根 K 线
+        let query = KlineQuery {
+            product: Product::UsdsFuture,
+            symbol: "BTCUSDT",
+            interval: "1m",
+            start_time: Some(series.start_time),
+            end_time: Some(series.start_time + 30 * MINUTE - 1),
+            limit: 500,
+        };
+        let mut synced = false;
+        for _ in 0..100 {
+            if store.klines(&query).await.is_some() {
+                synced = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(synced);
+
+        let app = test::init_service(App::new().app_data(state).configure(routes)).await;
+
+        // 已同步的范围从本地返回, 格式与币安相同
+        let requests_before = starts.lock().unwrap().len();
+        let (start_time, end_time) = (series.start_time, series.start_time + 10 * MINUTE - 1);
+        let req = get(&format!(
+            "/usds_future/kline?symbol=BTCUSDT&interval=1m&start_time={}&end_time={}",
+            start_time, end_time
+        ))
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body, json!(kline_rows(start_time, end_time, 500)));
+        assert_eq!(starts.lock().unwrap().len(), requests_before);
+
+        // 超出本地范围时请求币安
+        let req = get(&format!(
+            "/usds_future/kline?symbol=BTCUSDT&interval=1m&start_time={}&end_time={}",
+            start_time - MINUTE,
+            end_time
+        ))
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body, json!(kline_rows(start_time - MINUTE, end_time, 500)));
+        assert_eq!(starts.lock().unwrap().len(), requests_before + 1);
+    }
+}
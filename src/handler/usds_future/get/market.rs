@@ -0,0 +1,732 @@
+use actix_web::middleware::from_fn;
+use actix_web::{HttpResponse, get, web};
+use binance_sdk::derivatives_trading_usds_futures::rest_api::{
+    self, CompressedAggregateTradesListParams, GetFundingRateHistoryParams,
+    IndexPriceKlineCandlestickDataIntervalEnum, IndexPriceKlineCandlestickDataParams,
+    LongShortRatioParams, LongShortRatioPeriodEnum, MarkPriceKlineCandlestickDataIntervalEnum,
+    MarkPriceKlineCandlestickDataParams, MarkPriceParams, OpenInterestParams,
+    OpenInterestStatisticsParams, OpenInterestStatisticsPeriodEnum, OrderBookParams,
+    PremiumIndexKlineDataIntervalEnum, PremiumIndexKlineDataParams, RecentTradesListParams,
+    SymbolOrderBookTickerParams, Ticker24hrPriceChangeStatisticsParams,
+};
+use binance_sdk::models::RestApiResponse;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::app::AppState;
+use crate::common::params::OptionalKeyName;
+use crate::common::retry::Idempotency;
+use crate::handler::common::{ApiError, Validation, get_market_client};
+use crate::middleware::auth::require_market_data;
+use crate::stream::market::Product;
+
+// 行情接口都是公开数据, ?key= 可选, 不传时使用共享的无 key 客户端
+
+// 币安 depth 接口只接受以下 limit
+const DEPTH_LIMITS: [i64; 7] = [5, 10, 20, 50, 100, 500, 1000];
+// aggTrades 同时传 start_time 和 end_time 时跨度不能超过 1 小时
+const AGG_TRADES_WINDOW_MS: i64 = 60 * 60 * 1000;
+
+fn valid_range(start_time: Option<i64>, end_time: Option<i64>) -> bool {
+    match (start_time, end_time) {
+        (Some(start), Some(end)) => start <= end,
+        _ => true,
+    }
+}
+
+#[derive(Deserialize)]
+struct DepthParamsWrapper {
+    symbol: String,
+    limit: Option<i64>,
+}
+
+impl TryFrom<DepthParamsWrapper> for OrderBookParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: DepthParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                wrapper
+                    .limit
+                    .is_none_or(|limit| DEPTH_LIMITS.contains(&limit)),
+                "limit",
+                "must be one of 5, 10, 20, 50, 100, 500, 1000",
+            )
+            .finish()?;
+
+        Ok(Self::builder(wrapper.symbol).limit(wrapper.limit).build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct TradesParamsWrapper {
+    symbol: String,
+    limit: Option<i64>,
+}
+
+impl TryFrom<TradesParamsWrapper> for RecentTradesListParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: TradesParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                wrapper
+                    .limit
+                    .is_none_or(|limit| (1..=1000).contains(&limit)),
+                "limit",
+                "must be between 1 and 1000",
+            )
+            .finish()?;
+
+        Ok(Self::builder(wrapper.symbol).limit(wrapper.limit).build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct AggTradesParamsWrapper {
+    symbol: String,
+    from_id: Option<i64>,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    limit: Option<i64>,
+}
+
+impl TryFrom<AggTradesParamsWrapper> for CompressedAggregateTradesListParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: AggTradesParamsWrapper) -> Result<Self, Self::Error> {
+        let window = match (wrapper.start_time, wrapper.end_time) {
+            (Some(start), Some(end)) => Some(end - start),
+            _ => None,
+        };
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                wrapper
+                    .limit
+                    .is_none_or(|limit| (1..=1000).contains(&limit)),
+                "limit",
+                "must be between 1 and 1000",
+            )
+            .check(
+                window.is_none_or(|window| (0..=AGG_TRADES_WINDOW_MS).contains(&window)),
+                "end_time",
+                "must be within 1 hour after start_time",
+            )
+            .finish()?;
+
+        Ok(Self::builder(wrapper.symbol)
+            .from_id(wrapper.from_id)
+            .start_time(wrapper.start_time)
+            .end_time(wrapper.end_time)
+            .limit(wrapper.limit)
+            .build()?)
+    }
+}
+
+// 24h ticker, book ticker 和 mark price 的 symbol 都是可选的, 不传时返回全部交易对
+#[derive(Deserialize)]
+struct SymbolParamsWrapper {
+    symbol: Option<String>,
+}
+
+impl SymbolParamsWrapper {
+    fn symbol(self) -> Option<String> {
+        self.symbol.filter(|symbol| !symbol.is_empty())
+    }
+}
+
+impl TryFrom<SymbolParamsWrapper> for Ticker24hrPriceChangeStatisticsParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: SymbolParamsWrapper) -> Result<Self, Self::Error> {
+        Ok(Self::builder().symbol(wrapper.symbol()).build()?)
+    }
+}
+
+impl TryFrom<SymbolParamsWrapper> for SymbolOrderBookTickerParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: SymbolParamsWrapper) -> Result<Self, Self::Error> {
+        Ok(Self::builder().symbol(wrapper.symbol()).build()?)
+    }
+}
+
+impl TryFrom<SymbolParamsWrapper> for MarkPriceParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: SymbolParamsWrapper) -> Result<Self, Self::Error> {
+        Ok(Self::builder().symbol(wrapper.symbol()).build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct FundingRateParamsWrapper {
+    symbol: Option<String>,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    limit: Option<i64>,
+}
+
+impl TryFrom<FundingRateParamsWrapper> for GetFundingRateHistoryParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: FundingRateParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(
+                wrapper
+                    .limit
+                    .is_none_or(|limit| (1..=1000).contains(&limit)),
+                "limit",
+                "must be between 1 and 1000",
+            )
+            .check(
+                valid_range(wrapper.start_time, wrapper.end_time),
+                "start_time",
+                "must not be after end_time",
+            )
+            .finish()?;
+
+        Ok(Self::builder()
+            .symbol(wrapper.symbol.filter(|symbol| !symbol.is_empty()))
+            .start_time(wrapper.start_time)
+            .end_time(wrapper.end_time)
+            .limit(wrapper.limit)
+            .build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenInterestParamsWrapper {
+    symbol: String,
+}
+
+impl TryFrom<OpenInterestParamsWrapper> for OpenInterestParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: OpenInterestParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .finish()?;
+
+        Ok(Self::builder(wrapper.symbol).build()?)
+    }
+}
+
+// 持仓量统计和多空比的参数相同, period 的枚举类型不同
+#[derive(Deserialize)]
+struct StatisticsParamsWrapper<P> {
+    symbol: String,
+    period: P,
+    limit: Option<i64>,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+}
+
+impl<P> StatisticsParamsWrapper<P> {
+    fn validate(&self) -> Result<(), ApiError> {
+        Validation::default()
+            .check(!self.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                self.limit.is_none_or(|limit| (1..=500).contains(&limit)),
+                "limit",
+                "must be between 1 and 500",
+            )
+            .check(
+                valid_range(self.start_time, self.end_time),
+                "start_time",
+                "must not be after end_time",
+            )
+            .finish()
+    }
+}
+
+impl TryFrom<StatisticsParamsWrapper<OpenInterestStatisticsPeriodEnum>>
+    for OpenInterestStatisticsParams
+{
+    type Error = ApiError;
+
+    fn try_from(
+        wrapper: StatisticsParamsWrapper<OpenInterestStatisticsPeriodEnum>,
+    ) -> Result<Self, Self::Error> {
+        wrapper.validate()?;
+
+        Ok(Self::builder(wrapper.symbol, wrapper.period)
+            .limit(wrapper.limit)
+            .start_time(wrapper.start_time)
+            .end_time(wrapper.end_time)
+            .build()?)
+    }
+}
+
+impl TryFrom<StatisticsParamsWrapper<LongShortRatioPeriodEnum>> for LongShortRatioParams {
+    type Error = ApiError;
+
+    fn try_from(
+        wrapper: StatisticsParamsWrapper<LongShortRatioPeriodEnum>,
+    ) -> Result<Self, Self::Error> {
+        wrapper.validate()?;
+
+        Ok(Self::builder(wrapper.symbol, wrapper.period)
+            .limit(wrapper.limit)
+            .start_time(wrapper.start_time)
+            .end_time(wrapper.end_time)
+            .build()?)
+    }
+}
+
+// 标记价格, 指数价格和溢价指数 K 线的参数相同, interval 的枚举类型不同
+// 指数价格 K 线的交易对参数名为 pair, 这里同时接受 symbol 和 pair
+#[derive(Deserialize)]
+struct PriceKlineParamsWrapper<I> {
+    #[serde(alias = "pair")]
+    symbol: String,
+    interval: I,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    limit: Option<i64>,
+}
+
+impl<I> PriceKlineParamsWrapper<I> {
+    fn validate(&self) -> Result<(), ApiError> {
+        Validation::default()
+            .check(!self.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                self.limit.is_none_or(|limit| (1..=1500).contains(&limit)),
+                "limit",
+                "must be between 1 and 1500",
+            )
+            .check(
+                valid_range(self.start_time, self.end_time),
+                "start_time",
+                "must not be after end_time",
+            )
+            .finish()
+    }
+}
+
+impl TryFrom<PriceKlineParamsWrapper<MarkPriceKlineCandlestickDataIntervalEnum>>
+    for MarkPriceKlineCandlestickDataParams
+{
+    type Error = ApiError;
+
+    fn try_from(
+        wrapper: PriceKlineParamsWrapper<MarkPriceKlineCandlestickDataIntervalEnum>,
+    ) -> Result<Self, Self::Error> {
+        wrapper.validate()?;
+
+        Ok(Self::builder(wrapper.symbol, wrapper.interval)
+            .start_time(wrapper.start_time)
+            .end_time(wrapper.end_time)
+            .limit(wrapper.limit)
+            .build()?)
+    }
+}
+
+impl TryFrom<PriceKlineParamsWrapper<IndexPriceKlineCandlestickDataIntervalEnum>>
+    for IndexPriceKlineCandlestickDataParams
+{
+    type Error = ApiError;
+
+    fn try_from(
+        wrapper: PriceKlineParamsWrapper<IndexPriceKlineCandlestickDataIntervalEnum>,
+    ) -> Result<Self, Self::Error> {
+        wrapper.validate()?;
+
+        Ok(Self::builder(wrapper.symbol, wrapper.interval)
+            .start_time(wrapper.start_time)
+            .end_time(wrapper.end_time)
+            .limit(wrapper.limit)
+            .build()?)
+    }
+}
+
+impl TryFrom<PriceKlineParamsWrapper<PremiumIndexKlineDataIntervalEnum>>
+    for PremiumIndexKlineDataParams
+{
+    type Error = ApiError;
+
+    fn try_from(
+        wrapper: PriceKlineParamsWrapper<PremiumIndexKlineDataIntervalEnum>,
+    ) -> Result<Self, Self::Error> {
+        wrapper.validate()?;
+
+        Ok(Self::builder(wrapper.symbol, wrapper.interval)
+            .start_time(wrapper.start_time)
+            .end_time(wrapper.end_time)
+            .limit(wrapper.limit)
+            .build()?)
+    }
+}
+
+/// 行情接口的公共流程: 获取客户端, 调用 SDK (可重试), 记录权重用量, 返回 JSON
+async fn market_data<T, F>(
+    data: &web::Data<AppState>,
+    key_name: Option<&str>,
+    operation: &'static str,
+    call: F,
+) -> Result<HttpResponse, actix_web::Error>
+where
+    T: Serialize + Send + 'static,
+    F: AsyncFn(&rest_api::RestApi) -> anyhow::Result<RestApiResponse<T>>,
+{
+    // 调用辅助函数获取客户端
+    let client = get_market_client::<rest_api::RestApi>(data, key_name)?;
+
+    let response = data
+        .retry
+        .call(operation, Idempotency::Idempotent, || call(&client))
+        .await
+        .map_err(|e| {
+            error!("{}: {}", operation, e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, key_name, &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// 订单簿深度
+/// GET /depth
+/// 参数:
+/// - symbol: 交易对 (必填)
+/// - limit: 5 / 10 / 20 / 50 / 100 / 500 / 1000, 默认 500 (可选)
+#[get("/depth", wrap = "from_fn(require_market_data)")]
+pub async fn depth(
+    data: web::Data<AppState>,
+    query: web::Query<OptionalKeyName>,
+    param: web::Query<DepthParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = OrderBookParams::try_from(param.into_inner())?;
+
+    market_data(&data, query.key(), "usds_future/depth", async |client| {
+        client.order_book(params.clone()).await
+    })
+    .await
+}
+
+/// 近期成交
+/// GET /trades
+/// 参数:
+/// - symbol: 交易对 (必填)
+/// - limit: 默认 500, 最大 1000 (可选)
+#[get("/trades", wrap = "from_fn(require_market_data)")]
+pub async fn trades(
+    data: web::Data<AppState>,
+    query: web::Query<OptionalKeyName>,
+    param: web::Query<TradesParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = RecentTradesListParams::try_from(param.into_inner())?;
+
+    market_data(&data, query.key(), "usds_future/trades", async |client| {
+        client.recent_trades_list(params.clone()).await
+    })
+    .await
+}
+
+/// 归集成交
+/// GET /agg_trades
+/// 参数:
+/// - symbol: 交易对 (必填)
+/// - from_id: 从该归集成交 id 开始返回 (可选)
+/// - start_time / end_time: 时间跨度不超过 1 小时 (可选)
+/// - limit: 默认 500, 最大 1000 (可选)
+#[get("/agg_trades", wrap = "from_fn(require_market_data)")]
+pub async fn agg_trades(
+    data: web::Data<AppState>,
+    query: web::Query<OptionalKeyName>,
+    param: web::Query<AggTradesParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = CompressedAggregateTradesListParams::try_from(param.into_inner())?;
+
+    market_data(
+        &data,
+        query.key(),
+        "usds_future/agg_trades",
+        async |client| {
+            client
+                .compressed_aggregate_trades_list(params.clone())
+                .await
+        },
+    )
+    .await
+}
+
+/// 24 小时价格变动统计
+/// GET /ticker_24hr
+/// 参数:
+/// - symbol: 交易对 (可选, 不传时返回全部交易对)
+#[get("/ticker_24hr", wrap = "from_fn(require_market_data)")]
+pub async fn ticker_24hr(
+    data: web::Data<AppState>,
+    query: web::Query<OptionalKeyName>,
+    param: web::Query<SymbolParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = Ticker24hrPriceChangeStatisticsParams::try_from(param.into_inner())?;
+
+    market_data(
+        &data,
+        query.key(),
+        "usds_future/ticker_24hr",
+        async |client| {
+            client
+                .ticker24hr_price_change_statistics(params.clone())
+                .await
+        },
+    )
+    .await
+}
+
+/// 最优挂单
+/// GET /book_ticker
+/// 参数:
+/// - symbol: 交易对 (可选, 不传时返回全部交易对)
+#[get("/book_ticker", wrap = "from_fn(require_market_data)")]
+pub async fn book_ticker(
+    data: web::Data<AppState>,
+    query: web::Query<OptionalKeyName>,
+    param: web::Query<SymbolParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = SymbolOrderBookTickerParams::try_from(param.into_inner())?;
+
+    market_data(
+        &data,
+        query.key(),
+        "usds_future/book_ticker",
+        async |client| client.symbol_order_book_ticker(params.clone()).await,
+    )
+    .await
+}
+
+/// 标记价格和资金费率 (premium index)
+/// GET /mark_price
+/// 参数:
+/// - symbol: 交易对 (可选, 不传时返回全部交易对)
+#[get("/mark_price", wrap = "from_fn(require_market_data)")]
+pub async fn mark_price(
+    data: web::Data<AppState>,
+    query: web::Query<OptionalKeyName>,
+    param: web::Query<SymbolParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = MarkPriceParams::try_from(param.into_inner())?;
+
+    market_data(
+        &data,
+        query.key(),
+        "usds_future/mark_price",
+        async |client| client.mark_price(params.clone()).await,
+    )
+    .await
+}
+
+/// 资金费率历史
+/// GET /funding_rate
+/// 参数:
+/// - symbol: 交易对 (可选)
+/// - start_time / end_time (可选)
+/// - limit: 默认 100, 最大 1000 (可选)
+#[get("/funding_rate", wrap = "from_fn(require_market_data)")]
+pub async fn funding_rate(
+    data: web::Data<AppState>,
+    query: web::Query<OptionalKeyName>,
+    param: web::Query<FundingRateParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = GetFundingRateHistoryParams::try_from(param.into_inner())?;
+
+    market_data(
+        &data,
+        query.key(),
+        "usds_future/funding_rate",
+        async |client| client.get_funding_rate_history(params.clone()).await,
+    )
+    .await
+}
+
+/// 当前持仓量
+/// GET /open_interest
+/// 参数:
+/// - symbol: 交易对 (必填)
+#[get("/open_interest", wrap = "from_fn(require_market_data)")]
+pub async fn open_interest(
+    data: web::Data<AppState>,
+    query: web::Query<OptionalKeyName>,
+    param: web::Query<OpenInterestParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = OpenInterestParams::try_from(param.into_inner())?;
+
+    market_data(
+        &data,
+        query.key(),
+        "usds_future/open_interest",
+        async |client| client.open_interest(params.clone()).await,
+    )
+    .await
+}
+
+/// 持仓量统计
+/// GET /open_interest_hist
+/// 参数:
+/// - symbol: 交易对 (必填)
+/// - period: 5m / 15m / 30m / 1h / 2h / 4h / 6h / 12h / 1d (必填)
+/// - limit: 默认 30, 最大 500 (可选)
+/// - start_time / end_time: 只提供最近 30 天的数据 (可选)
+#[get("/open_interest_hist", wrap = "from_fn(require_market_data)")]
+pub async fn open_interest_hist(
+    data: web::Data<AppState>,
+    query: web::Query<OptionalKeyName>,
+    param: web::Query<StatisticsParamsWrapper<OpenInterestStatisticsPeriodEnum>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = OpenInterestStatisticsParams::try_from(param.into_inner())?;
+
+    market_data(
+        &data,
+        query.key(),
+        "usds_future/open_interest_hist",
+        async |client| client.open_interest_statistics(params.clone()).await,
+    )
+    .await
+}
+
+/// 多空账户数比
+/// GET /long_short_ratio
+/// 参数:
+/// - symbol: 交易对 (必填)
+/// - period: 5m / 15m / 30m / 1h / 2h / 4h / 6h / 12h / 1d (必填)
+/// - limit: 默认 30, 最大 500 (可选)
+/// - start_time / end_time: 只提供最近 30 天的数据 (可选)
+#[get("/long_short_ratio", wrap = "from_fn(require_market_data)")]
+pub async fn long_short_ratio(
+    data: web::Data<AppState>,
+    query: web::Query<OptionalKeyName>,
+    param: web::Query<StatisticsParamsWrapper<LongShortRatioPeriodEnum>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = LongShortRatioParams::try_from(param.into_inner())?;
+
+    market_data(
+        &data,
+        query.key(),
+        "usds_future/long_short_ratio",
+        async |client| client.long_short_ratio(params.clone()).await,
+    )
+    .await
+}
+
+/// 标记价格 K 线
+/// GET /mark_price_kline
+/// 参数:
+/// - symbol: 交易对 (必填)
+/// - interval: K 线间隔 (必填)
+/// - start_time / end_time (可选)
+/// - limit: 默认 500, 最大 1500 (可选)
+#[get("/mark_price_kline", wrap = "from_fn(require_market_data)")]
+pub async fn mark_price_kline(
+    data: web::Data<AppState>,
+    query: web::Query<OptionalKeyName>,
+    param: web::Query<PriceKlineParamsWrapper<MarkPriceKlineCandlestickDataIntervalEnum>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = MarkPriceKlineCandlestickDataParams::try_from(param.into_inner())?;
+
+    market_data(
+        &data,
+        query.key(),
+        "usds_future/mark_price_kline",
+        async |client| {
+            client
+                .mark_price_kline_candlestick_data(params.clone())
+                .await
+        },
+    )
+    .await
+}
+
+/// 指数价格 K 线
+/// GET /index_price_kline
+/// 参数:
+/// - pair (或 symbol): 标的交易对 (必填)
+/// - interval: K 线间隔 (必填)
+/// - start_time / end_time (可选)
+/// - limit: 默认 500, 最大 1500 (可选)
+#[get("/index_price_kline", wrap = "from_fn(require_market_data)")]
+pub async fn index_price_kline(
+    data: web::Data<AppState>,
+    query: web::Query<OptionalKeyName>,
+    param: web::Query<PriceKlineParamsWrapper<IndexPriceKlineCandlestickDataIntervalEnum>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = IndexPriceKlineCandlestickDataParams::try_from(param.into_inner())?;
+
+    market_data(
+        &data,
+        query.key(),
+        "usds_future/index_price_kline",
+        async |client| {
+            client
+                .index_price_kline_candlestick_data(params.clone())
+                .await
+        },
+    )
+    .await
+}
+
+/// 溢价指数 K 线
+/// GET /premium_index_kline
+/// 参数:
+/// - symbol: 交易对 (必填)
+/// - interval: K 线间隔 (必填)
+/// - start_time / end_time (可选)
+/// - limit: 默认 500, 最大 1500 (可选)
+#[get("/premium_index_kline", wrap = "from_fn(require_market_data)")]
+pub async fn premium_index_kline(
+    data: web::Data<AppState>,
+    query: web::Query<OptionalKeyName>,
+    param: web::Query<PriceKlineParamsWrapper<PremiumIndexKlineDataIntervalEnum>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = PremiumIndexKlineDataParams::try_from(param.into_inner())?;
+
+    market_data(
+        &data,
+        query.key(),
+        "usds_future/premium_index_kline",
+        async |client| client.premium_index_kline_data(params.clone()).await,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test};
+
+    use crate::handler::common::test_util::{get, invalid_fields, test_state};
+    use crate::handler::usds_future::routes;
+
+    #[actix_web::test]
+    async fn test_market_data_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        // 行情接口不需要 key
+        let req = get("/usds_future/depth?symbol=BTCUSDT&limit=7").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["limit"]);
+
+        let req = get("/usds_future/agg_trades?symbol=BTCUSDT&start_time=0&end_time=3600001")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["end_time"]);
+
+        let req = get("/usds_future/open_interest_hist?symbol=BTCUSDT").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["period"]);
+
+        let req = get("/usds_future/index_price_kline?pair=&interval=1h&limit=1501").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["symbol", "limit"]);
+    }
+}
@@ -0,0 +1,264 @@
+use actix_web::middleware::from_fn;
+use actix_web::{HttpResponse, get, web};
+use binance_sdk::derivatives_trading_usds_futures::rest_api::{
+    self, AllOrdersParams, CurrentAllOpenOrdersParams, QueryOrderParams,
+};
+use serde::Deserialize;
+use tracing::error;
+
+use crate::app::AppState;
+use crate::common::params::{CacheControl, KeyName};
+use crate::common::retry::Idempotency;
+
+use crate::handler::common::{ApiError, Validation, get_client_from_state};
+use crate::middleware::auth::require_account;
+use crate::stream::market::Product;
+
+// all_orders 查询的时间跨度不能超过 7 天
+const MAX_ORDER_QUERY_WINDOW_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+
+#[derive(Deserialize)]
+struct QueryOrderParamsWrapper {
+    symbol: String,
+    order_id: Option<i64>,
+    orig_client_order_id: Option<String>,
+}
+
+impl TryFrom<QueryOrderParamsWrapper> for QueryOrderParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: QueryOrderParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                wrapper.order_id.is_some() || wrapper.orig_client_order_id.is_some(),
+                "order_id",
+                "order_id or orig_client_order_id is required",
+            )
+            .finish()?;
+
+        Ok(Self::builder(wrapper.symbol)
+            .order_id(wrapper.order_id)
+            .orig_client_order_id(wrapper.orig_client_order_id)
+            .build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct CurrentOpenOrdersParamsWrapper {
+    symbol: Option<String>,
+}
+
+impl TryFrom<CurrentOpenOrdersParamsWrapper> for CurrentAllOpenOrdersParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: CurrentOpenOrdersParamsWrapper) -> Result<Self, Self::Error> {
+        Ok(Self::builder()
+            .symbol(wrapper.symbol.filter(|symbol| !symbol.is_empty()))
+            .build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct AllOrdersParamsWrapper {
+    symbol: String,
+    order_id: Option<i64>,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    limit: Option<i64>,
+}
+
+impl TryFrom<AllOrdersParamsWrapper> for AllOrdersParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: AllOrdersParamsWrapper) -> Result<Self, Self::Error> {
+        let window = match (wrapper.start_time, wrapper.end_time) {
+            (Some(start), Some(end)) => Some(end - start),
+            _ => None,
+        };
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                wrapper
+                    .limit
+                    .is_none_or(|limit| (1..=1000).contains(&limit)),
+                "limit",
+                "must be between 1 and 1000",
+            )
+            .check(
+                window.is_none_or(|window| (0..=MAX_ORDER_QUERY_WINDOW_MS).contains(&window)),
+                "end_time",
+                "must be within 7 days after start_time",
+            )
+            .finish()?;
+
+        Ok(Self::builder(wrapper.symbol)
+            .order_id(wrapper.order_id)
+            .start_time(wrapper.start_time)
+            .end_time(wrapper.end_time)
+            .limit(wrapper.limit)
+            .build()?)
+    }
+}
+
+/// 查询订单
+/// GET /query_order
+/// 参数:
+/// - symbol: 交易对 (必填)
+/// - order_id / orig_client_order_id: 二选一
+#[get("/query_order", wrap = "from_fn(require_account)")]
+pub async fn query_order(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Query<QueryOrderParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = QueryOrderParams::try_from(param.into_inner())?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    let response = data
+        .retry
+        .call("usds_future/query_order", Idempotency::Idempotent, || {
+            client.query_order(params.clone())
+        })
+        .await
+        .map_err(|e| {
+            error!("query_order: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, Some(&query.key), &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// 查询当前挂单
+/// GET /current_open_orders
+/// 参数:
+/// - symbol: 交易对 (可选, 不传时返回全部交易对的挂单)
+/// - fresh: true 时绕过本地缓存直接请求币安 (可选)
+#[get("/current_open_orders", wrap = "from_fn(require_account)")]
+pub async fn current_open_orders(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Query<CurrentOpenOrdersParamsWrapper>,
+    cache: web::Query<CacheControl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = CurrentAllOpenOrdersParams::try_from(param.into_inner())?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    // 优先使用用户数据流维护的缓存
+    if !cache.fresh
+        && let Some(orders) = data
+            .usds_future_account_cache
+            .open_orders(&query.key, &client, params.symbol.as_deref())
+            .await
+    {
+        return Ok(HttpResponse::Ok().json(orders));
+    }
+
+    let response = data
+        .retry
+        .call(
+            "usds_future/current_open_orders",
+            Idempotency::Idempotent,
+            || client.current_all_open_orders(params.clone()),
+        )
+        .await
+        .map_err(|e| {
+            error!("current_open_orders: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, Some(&query.key), &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// 查询所有订单 (包括历史订单)
+/// GET /all_orders
+/// 参数:
+/// - symbol: 交易对 (必填)
+/// - order_id: 返回大于等于该 id 的订单 (可选)
+/// - start_time / end_time: 时间跨度不超过 7 天 (可选)
+/// - limit: 默认 500, 最大 1000 (可选)
+#[get("/all_orders", wrap = "from_fn(require_account)")]
+pub async fn all_orders(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Query<AllOrdersParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = AllOrdersParams::try_from(param.into_inner())?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    let response = data
+        .retry
+        .call("usds_future/all_orders", Idempotency::Idempotent, || {
+            client.all_orders(params.clone())
+        })
+        .await
+        .map_err(|e| {
+            error!("all_orders: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, Some(&query.key), &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test};
+
+    use crate::handler::common::test_util::{get, invalid_fields, test_state};
+    use crate::handler::usds_future::routes;
+
+    #[actix_web::test]
+    async fn test_order_queries_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = get("/usds_future/query_order?key=binance1&symbol=BTCUSDT").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["order_id"]);
+
+        let req = get(
+            "/usds_future/all_orders?key=binance1&symbol=BTCUSDT&limit=0&start_time=0&end_time=604800001",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["limit", "end_time"]);
+    }
+
+    #[actix_web::test]
+    async fn test_current_open_orders_invalid_fresh() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = get("/usds_future/current_open_orders?key=binance1&fresh=maybe").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+    }
+}
@@ -0,0 +1,372 @@
+use actix_web::middleware::from_fn;
+use actix_web::{HttpResponse, get, web};
+use binance_sdk::derivatives_trading_usds_futures::rest_api::{
+    self, AccountTradeListParams, AccountTradeListResponseInner, GetIncomeHistoryParams,
+    GetIncomeHistoryResponseInner, UserCommissionRateParams,
+};
+use serde::Deserialize;
+use tracing::{debug, error};
+
+use crate::app::AppState;
+use crate::common::paginate::{TimePager, now_ms};
+use crate::common::params::KeyName;
+use crate::common::retry::Idempotency;
+
+use crate::handler::common::{ApiError, Validation, get_client_from_state};
+use crate::middleware::auth::require_account;
+use crate::stream::market::Product;
+
+// 成交和资金流水单次查询的时间跨度不能超过 7 天
+const HISTORY_WINDOW_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+// 网关分页查询允许的最大时间跨度, 币安也只保留最近 3 个月的资金流水
+const MAX_HISTORY_RANGE_MS: i64 = 90 * 24 * 60 * 60 * 1000;
+const MAX_USER_TRADES_LIMIT: i64 = 1000;
+const MAX_INCOME_LIMIT: i64 = 1000;
+
+// 只传 start_time 时结束时间默认为当前时间, 返回 (end_time, 时间跨度)
+fn history_range(start_time: Option<i64>, end_time: Option<i64>) -> (Option<i64>, Option<i64>) {
+    let end_time = match (start_time, end_time) {
+        (Some(_), None) => Some(now_ms()),
+        (_, end_time) => end_time,
+    };
+    let range = start_time.zip(end_time).map(|(start, end)| end - start);
+    (end_time, range)
+}
+
+#[derive(Deserialize)]
+struct UserTradesParamsWrapper {
+    symbol: String,
+    order_id: Option<i64>,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    from_id: Option<i64>,
+    limit: Option<i64>,
+}
+
+impl TryFrom<UserTradesParamsWrapper> for AccountTradeListParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: UserTradesParamsWrapper) -> Result<Self, Self::Error> {
+        let (end_time, range) = history_range(wrapper.start_time, wrapper.end_time);
+
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                wrapper
+                    .limit
+                    .is_none_or(|limit| (1..=MAX_USER_TRADES_LIMIT).contains(&limit)),
+                "limit",
+                "must be between 1 and 1000",
+            )
+            .check(
+                range.is_none_or(|range| (0..=MAX_HISTORY_RANGE_MS).contains(&range)),
+                "end_time",
+                "must be within 90 days after start_time",
+            )
+            .check(
+                wrapper.from_id.is_none()
+                    || (wrapper.start_time.is_none() && wrapper.end_time.is_none()),
+                "from_id",
+                "cannot be sent together with start_time or end_time",
+            )
+            .finish()?;
+
+        Ok(Self::builder(wrapper.symbol)
+            .order_id(wrapper.order_id)
+            .start_time(wrapper.start_time)
+            .end_time(end_time)
+            .from_id(wrapper.from_id)
+            .limit(wrapper.limit)
+            .build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct IncomeHistoryParamsWrapper {
+    symbol: Option<String>,
+    income_type: Option<String>,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    limit: Option<i64>,
+}
+
+impl TryFrom<IncomeHistoryParamsWrapper> for GetIncomeHistoryParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: IncomeHistoryParamsWrapper) -> Result<Self, Self::Error> {
+        let (end_time, range) = history_range(wrapper.start_time, wrapper.end_time);
+
+        Validation::default()
+            .check(
+                wrapper
+                    .limit
+                    .is_none_or(|limit| (1..=MAX_INCOME_LIMIT).contains(&limit)),
+                "limit",
+                "must be between 1 and 1000",
+            )
+            .check(
+                range.is_none_or(|range| (0..=MAX_HISTORY_RANGE_MS).contains(&range)),
+                "end_time",
+                "must be within 90 days after start_time",
+            )
+            .finish()?;
+
+        Ok(Self::builder()
+            .symbol(wrapper.symbol.filter(|symbol| !symbol.is_empty()))
+            .income_type(wrapper.income_type.filter(|income| !income.is_empty()))
+            .start_time(wrapper.start_time)
+            .end_time(end_time)
+            .limit(wrapper.limit)
+            .build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct CommissionRateParamsWrapper {
+    symbol: String,
+}
+
+impl TryFrom<CommissionRateParamsWrapper> for UserCommissionRateParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: CommissionRateParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .finish()?;
+
+        Ok(Self::builder(wrapper.symbol).build()?)
+    }
+}
+
+async fn user_trades_page(
+    client: &rest_api::RestApi,
+    data: &AppState,
+    key_name: &str,
+    params: AccountTradeListParams,
+) -> Result<Vec<AccountTradeListResponseInner>, ApiError> {
+    let response = data
+        .retry
+        .call("usds_future/user_trades", Idempotency::Idempotent, || {
+            client.account_trade_list(params.clone())
+        })
+        .await
+        .map_err(|e| {
+            error!("user_trades: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, Some(key_name), &response.rate_limits);
+
+    response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })
+}
+
+async fn income_history_page(
+    client: &rest_api::RestApi,
+    data: &AppState,
+    key_name: &str,
+    params: GetIncomeHistoryParams,
+) -> Result<Vec<GetIncomeHistoryResponseInner>, ApiError> {
+    let response = data
+        .retry
+        .call(
+            "usds_future/income_history",
+            Idempotency::Idempotent,
+            || client.get_income_history(params.clone()),
+        )
+        .await
+        .map_err(|e| {
+            error!("income_history: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, Some(key_name), &response.rate_limits);
+
+    response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })
+}
+
+/// 查询账户成交历史
+/// GET /user_trades
+/// 参数:
+/// - symbol: 交易对 (必填)
+/// - order_id / from_id: 按订单或成交 id 查询 (可选, 此时只查询一页)
+/// - start_time / end_time: 时间跨度不超过 90 天, 网关按 7 天窗口自动分页 (可选, end_time 默认当前时间)
+/// - limit: 每页数量, 默认 500, 分页时默认 1000, 最大 1000 (可选)
+#[get("/user_trades", wrap = "from_fn(require_account)")]
+pub async fn user_trades(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Query<UserTradesParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = AccountTradeListParams::try_from(param.into_inner())?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    let trades = match (params.start_time, params.end_time, params.order_id) {
+        (Some(start_time), Some(end_time), None) => {
+            let limit = params.limit.unwrap_or(MAX_USER_TRADES_LIMIT);
+            let pager = TimePager {
+                start_time,
+                end_time,
+                window_ms: HISTORY_WINDOW_MS,
+                limit: limit as usize,
+            };
+            let trades = pager
+                .collect(
+                    |start, end| {
+                        let mut page_params = params.clone();
+                        page_params.start_time = Some(start);
+                        page_params.end_time = Some(end);
+                        page_params.limit = Some(limit);
+                        user_trades_page(&client, &data, &query.key, page_params)
+                    },
+                    |trade| trade.time,
+                    |trade| trade.id,
+                )
+                .await?;
+            debug!(
+                "user_trades: {} trades between {} and {}",
+                trades.len(),
+                start_time,
+                end_time
+            );
+            trades
+        }
+        _ => user_trades_page(&client, &data, &query.key, params).await?,
+    };
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(trades))
+}
+
+/// 查询资金流水 (资金费, 已实现盈亏, 手续费, 划转等)
+/// GET /income_history
+/// 参数:
+/// - symbol: 交易对 (可选)
+/// - income_type: TRANSFER / REALIZED_PNL / FUNDING_FEE / COMMISSION 等 (可选, 不传时返回全部类型)
+/// - start_time / end_time: 时间跨度不超过 90 天, 网关按 7 天窗口自动分页 (可选, 都不传时币安返回最近 7 天)
+/// - limit: 每页数量, 默认 100, 分页时默认 1000, 最大 1000 (可选)
+#[get("/income_history", wrap = "from_fn(require_account)")]
+pub async fn income_history(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Query<IncomeHistoryParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = GetIncomeHistoryParams::try_from(param.into_inner())?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    let incomes = match (params.start_time, params.end_time) {
+        (Some(start_time), Some(end_time)) => {
+            let limit = params.limit.unwrap_or(MAX_INCOME_LIMIT);
+            let pager = TimePager {
+                start_time,
+                end_time,
+                window_ms: HISTORY_WINDOW_MS,
+                limit: limit as usize,
+            };
+            let incomes = pager
+                .collect(
+                    |start, end| {
+                        let mut page_params = params.clone();
+                        page_params.start_time = Some(start);
+                        page_params.end_time = Some(end);
+                        page_params.limit = Some(limit);
+                        income_history_page(&client, &data, &query.key, page_params)
+                    },
+                    |income| income.time,
+                    // tranId 只在同一种流水类型内唯一
+                    |income| (income.tran_id, income.income_type.clone()),
+                )
+                .await?;
+            debug!(
+                "income_history: {} records between {} and {}",
+                incomes.len(),
+                start_time,
+                end_time
+            );
+            incomes
+        }
+        _ => income_history_page(&client, &data, &query.key, params).await?,
+    };
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(incomes))
+}
+
+/// 查询交易对的手续费率
+/// GET /commission_rate
+/// 参数:
+/// - symbol: 交易对 (必填)
+#[get("/commission_rate", wrap = "from_fn(require_account)")]
+pub async fn commission_rate(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Query<CommissionRateParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = UserCommissionRateParams::try_from(param.into_inner())?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    let response = data
+        .retry
+        .call(
+            "usds_future/commission_rate",
+            Idempotency::Idempotent,
+            || client.user_commission_rate(params.clone()),
+        )
+        .await
+        .map_err(|e| {
+            error!("commission_rate: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, Some(&query.key), &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test};
+
+    use crate::handler::common::test_util::{get, invalid_fields, test_state};
+    use crate::handler::usds_future::routes;
+
+    #[actix_web::test]
+    async fn test_history_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = get(
+            "/usds_future/user_trades?key=binance1&symbol=BTCUSDT&from_id=1&start_time=0&end_time=1",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["from_id"]);
+
+        let req = get(
+            "/usds_future/income_history?key=binance1&limit=1001&start_time=0&end_time=7776000001",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["limit", "end_time"]);
+
+        let req = get("/usds_future/commission_rate?key=binance1&symbol=").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["symbol"]);
+    }
+}
@@ -2,4 +2,5 @@ pub mod order;
 pub mod position;
 pub mod kline;
 pub mod leverage;
-pub mod margin;
\ No newline at end of file
+pub mod margin;
+pub mod batch_order;
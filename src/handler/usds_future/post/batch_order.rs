@@ -0,0 +1,347 @@
+use actix_web::middleware::from_fn;
+use actix_web::{HttpResponse, post, web};
+use binance_sdk::derivatives_trading_usds_futures::rest_api::{
+    self, CancelMultipleOrdersParams, ModifyMultipleOrdersBatchOrdersParameterInner,
+    NewOrderParams, PlaceMultipleOrdersParams,
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tracing::error;
+
+use super::order::{NewOrderParamsWrapper, check_risk};
+use crate::{
+    app::AppState,
+    common::params::KeyName,
+    common::retry::Idempotency,
+    common::risk::{OrderRisk, Reservation},
+    handler::common::{ApiError, FieldError, Validation, get_client_from_state},
+    middleware::auth::require_trade,
+    stream::market::Product,
+};
+
+// 币安批量下单最多 5 个订单, 批量撤单最多 10 个订单
+const MAX_BATCH_ORDERS: usize = 5;
+const MAX_BATCH_CANCEL: usize = 10;
+
+#[derive(Deserialize)]
+struct BatchOrdersParamsWrapper {
+    batch_orders: Vec<NewOrderParamsWrapper>,
+}
+
+// 单个下单和批量下单的枚举在 SDK 中是分开定义的, 但序列化后的取值相同
+fn convert<S: Serialize, D: DeserializeOwned>(value: Option<S>) -> Option<D> {
+    value
+        .and_then(|v| serde_json::to_value(v).ok())
+        .and_then(|v| serde_json::from_value(v).ok())
+}
+
+fn batch_order(params: NewOrderParams) -> ModifyMultipleOrdersBatchOrdersParameterInner {
+    ModifyMultipleOrdersBatchOrdersParameterInner {
+        symbol: Some(params.symbol),
+        side: convert(Some(params.side)),
+        position_side: convert(params.position_side),
+        r#type: Some(params.r#type),
+        time_in_force: convert(params.time_in_force),
+        quantity: params.quantity,
+        reduce_only: params.reduce_only,
+        price: params.price,
+        new_client_order_id: params.new_client_order_id,
+        stop_price: params.stop_price,
+        activation_price: params.activation_price,
+        callback_rate: params.callback_rate,
+        working_type: convert(params.working_type),
+        price_protect: params.price_protect,
+        new_order_resp_type: convert(params.new_order_resp_type),
+        price_match: convert(params.price_match),
+        self_trade_prevention_mode: convert(params.self_trade_prevention_mode),
+        good_till_date: params.good_till_date,
+    }
+}
+
+// 风控检查的批量订单, 批量下单不支持 close_position
+fn order_risk(order: &ModifyMultipleOrdersBatchOrdersParameterInner) -> OrderRisk {
+    let side: Option<String> = convert(order.side.as_ref());
+    OrderRisk {
+        symbol: order.symbol.clone().unwrap_or_default(),
+        is_buy: side.as_deref() == Some("BUY"),
+        reduce_only: order.reduce_only.as_deref() == Some("true"),
+        position_side: convert(order.position_side.as_ref()).unwrap_or_else(|| "BOTH".to_string()),
+        quantity: order.quantity,
+        price: order.price,
+        stop_price: order.stop_price,
+        ..Default::default()
+    }
+}
+
+impl TryFrom<BatchOrdersParamsWrapper> for PlaceMultipleOrdersParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: BatchOrdersParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(
+                (1..=MAX_BATCH_ORDERS).contains(&wrapper.batch_orders.len()),
+                "batch_orders",
+                "must contain 1 to 5 orders",
+            )
+            .finish()?;
+
+        // 每个订单复用 new_order 的校验, 出错的字段加上下标前缀, 如 batch_orders[1].price
+        let mut fields = Vec::new();
+        let mut orders = Vec::new();
+        for (i, order) in wrapper.batch_orders.into_iter().enumerate() {
+            if order.close_position.is_some() {
+                fields.push(FieldError {
+                    field: format!("batch_orders[{}].close_position", i),
+                    reason: "not supported in batch orders".to_string(),
+                });
+                continue;
+            }
+            match NewOrderParams::try_from(order) {
+                Ok(params) => orders.push(batch_order(params)),
+                Err(e) => fields.extend(e.fields.into_iter().map(|f| FieldError {
+                    field: format!("batch_orders[{}].{}", i, f.field),
+                    reason: f.reason,
+                })),
+            }
+        }
+        if !fields.is_empty() {
+            return Err(ApiError::invalid_params(fields));
+        }
+
+        Ok(Self::builder(orders).build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct CancelBatchOrdersParamsWrapper {
+    symbol: String,
+    order_id_list: Option<Vec<i64>>,
+    orig_client_order_id_list: Option<Vec<String>>,
+}
+
+impl TryFrom<CancelBatchOrdersParamsWrapper> for CancelMultipleOrdersParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: CancelBatchOrdersParamsWrapper) -> Result<Self, Self::Error> {
+        let count = wrapper.order_id_list.as_ref().map_or(0, Vec::len)
+            + wrapper
+                .orig_client_order_id_list
+                .as_ref()
+                .map_or(0, Vec::len);
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                (1..=MAX_BATCH_CANCEL).contains(&count),
+                "order_id_list",
+                "order_id_list or orig_client_order_id_list must contain 1 to 10 ids",
+            )
+            .finish()?;
+
+        Ok(Self::builder(wrapper.symbol)
+            .order_id_list(wrapper.order_id_list.filter(|ids| !ids.is_empty()))
+            .orig_client_order_id_list(
+                wrapper
+                    .orig_client_order_id_list
+                    .filter(|ids| !ids.is_empty()),
+            )
+            .build()?)
+    }
+}
+
+/// 批量下单
+/// POST /batch_orders
+/// JSON 参数:
+/// - batch_orders: 订单列表, 最多 5 个, 每个订单的参数与 /new_order 相同 (不支持 close_position)
+///
+/// 币安会并发处理批量订单, 返回结果与请求顺序一致, 单个订单失败时对应位置返回 {code, msg}
+/// 配置了 [risk] 时每个订单按 /new_order 的规则检查, 任意一个不满足时整批返回 422, 不会下单
+#[post("/batch_orders", wrap = "from_fn(require_trade)")]
+async fn batch_orders(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Json<BatchOrdersParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = PlaceMultipleOrdersParams::try_from(param.into_inner())?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    // 按顺序检查, 前面订单的预留计入后面订单的挂单数和挂单数量, 预留持有到下单结束
+    let mut reservations = Vec::new();
+    for (i, order) in params.batch_orders.iter().enumerate() {
+        let risk = order_risk(order);
+        match check_risk("batch_orders", &data, &query.key, &client, risk, None).await {
+            Ok(reservation) => reservations.extend(reservation),
+            Err(mut e) => {
+                // 整批都没有发送, 立即释放前面订单的预留
+                reservations.into_iter().for_each(Reservation::cancel);
+                e.msg = format!("batch_orders[{}]: {}", i, e.msg);
+                return Err(e.into());
+            }
+        }
+    }
+
+    let response = data
+        .retry
+        .call(
+            "usds_future/batch_orders",
+            Idempotency::NonIdempotent,
+            || client.place_multiple_orders(params.clone()),
+        )
+        .await
+        .map_err(|e| {
+            error!("batch_orders: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, Some(&query.key), &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// 批量撤单
+/// POST /cancel_batch_orders
+/// JSON 参数:
+/// - symbol: 交易对 (必填)
+/// - order_id_list / orig_client_order_id_list: 订单 id 列表, 最多 10 个
+#[post("/cancel_batch_orders", wrap = "from_fn(require_trade)")]
+async fn cancel_batch_orders(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Json<CancelBatchOrdersParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = CancelMultipleOrdersParams::try_from(param.into_inner())?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    let response = data
+        .retry
+        .call(
+            "usds_future/cancel_batch_orders",
+            Idempotency::NonIdempotent,
+            || client.cancel_multiple_orders(params.clone()),
+        )
+        .await
+        .map_err(|e| {
+            error!("cancel_batch_orders: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, Some(&query.key), &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use actix_web::{App, test};
+    use serde_json::{Value, json};
+
+    use crate::common::risk::RiskEngine;
+    use crate::config::RiskRules;
+    use crate::handler::common::test_util::{
+        invalid_fields, post_json, test_state, test_state_with, usds_future_client,
+    };
+    use crate::handler::usds_future::routes;
+
+    #[actix_web::test]
+    async fn test_batch_orders_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let order = json!({"symbol": "BTCUSDT", "side": "BUY", "type": "MARKET", "quantity": "1"});
+        let req = post_json(
+            "/usds_future/batch_orders?key=binance1",
+            json!({ "batch_orders": vec![order.clone(); 6] }),
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["batch_orders"]);
+
+        let req = post_json(
+            "/usds_future/batch_orders?key=binance1",
+            json!({ "batch_orders": [
+                order,
+                {"symbol": "BTCUSDT", "side": "BUY", "type": "LIMIT", "quantity": "1"},
+            ]}),
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["batch_orders[1].price"]);
+
+        let req = post_json(
+            "/usds_future/cancel_batch_orders?key=binance1",
+            json!({"symbol": "BTCUSDT", "order_id_list": []}),
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["order_id_list"]);
+    }
+
+    #[actix_web::test]
+    async fn test_batch_order_conversion() {
+        let wrapper: super::BatchOrdersParamsWrapper = serde_json::from_value(json!({
+            "batch_orders": [{
+                "symbol": "BTCUSDT",
+                "side": "SELL",
+                "type": "LIMIT",
+                "quantity": "0.01",
+                "price": "100000",
+                "position_side": "SHORT",
+                "reduce_only": true,
+            }]
+        }))
+        .unwrap();
+        let params = super::PlaceMultipleOrdersParams::try_from(wrapper).unwrap();
+        let order = serde_json::to_value(&params.batch_orders[0]).unwrap();
+        assert_eq!(order["side"], "SELL");
+        assert_eq!(order["positionSide"], "SHORT");
+        assert_eq!(order["timeInForce"], "GTC");
+        assert_eq!(order["reduceOnly"], "true");
+    }
+
+    #[actix_web::test]
+    async fn test_batch_orders_risk() {
+        let state = test_state_with(|state| {
+            // 不可达的地址, 风控拒绝时不会发送请求
+            state.rest_usds_future_clients.lock().unwrap().insert(
+                "binance1".to_string(),
+                usds_future_client("http://127.0.0.1:1"),
+            );
+            let rules = RiskRules {
+                denied_symbols: vec!["ETHUSDT".to_string()],
+                ..Default::default()
+            };
+            state.risk = Arc::new(RiskEngine::new(HashMap::from([("*".to_string(), rules)])));
+        });
+        let app = test::init_service(App::new().app_data(state).configure(routes)).await;
+
+        let req = post_json(
+            "/usds_future/batch_orders?key=binance1",
+            json!({ "batch_orders": [
+                {"symbol": "BTCUSDT", "side": "BUY", "type": "MARKET", "quantity": "1"},
+                {"symbol": "ETHUSDT", "side": "BUY", "type": "MARKET", "quantity": "1"},
+            ]}),
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["rule"], "denied_symbols");
+        assert!(body["msg"].as_str().unwrap().starts_with("batch_orders[1]"));
+    }
+}
@@ -1,4 +1,13 @@
-use crate::{app::AppState, common::params::KeyName, handler::common::get_client_from_state};
+use crate::{
+    app::AppState,
+    common::kline_store::KlineQuery,
+    common::params::OptionalKeyName,
+    common::retry::Idempotency,
+    handler::common::{ApiError, Validation, get_market_client, stored_klines},
+    middleware::auth::require_market_data,
+    stream::market::Product,
+};
+use actix_web::middleware::from_fn;
 use actix_web::{HttpResponse, post, web};
 use binance_sdk::derivatives_trading_usds_futures::rest_api;
 use binance_sdk::derivatives_trading_usds_futures::rest_api::{
@@ -8,7 +17,7 @@ use serde::Deserialize;
 use tracing::error;
 
 #[derive(Deserialize)]
-struct KlineCandlestickDataParamsWrapper {
+pub(crate) struct KlineCandlestickDataParamsWrapper {
     symbol: String,
     interval: KlineCandlestickDataIntervalEnum,
     start_time: Option<i64>,
@@ -16,21 +25,43 @@ struct KlineCandlestickDataParamsWrapper {
     limit: Option<i64>,
 }
 
-impl From<KlineCandlestickDataParamsWrapper> for KlineCandlestickDataParams {
-    fn from(wrapper: KlineCandlestickDataParamsWrapper) -> Self {
-        KlineCandlestickDataParams::builder(wrapper.symbol, wrapper.interval)
-            .start_time(wrapper.start_time)
-            .end_time(wrapper.end_time)
-            .limit(wrapper.limit)
-            .build()
-            .unwrap()
+impl TryFrom<KlineCandlestickDataParamsWrapper> for KlineCandlestickDataParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: KlineCandlestickDataParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                wrapper
+                    .limit
+                    .is_none_or(|limit| (1..=1500).contains(&limit)),
+                "limit",
+                "must be between 1 and 1500",
+            )
+            .check(
+                match (wrapper.start_time, wrapper.end_time) {
+                    (Some(start), Some(end)) => start <= end,
+                    _ => true,
+                },
+                "start_time",
+                "must not be after end_time",
+            )
+            .finish()?;
+
+        Ok(
+            KlineCandlestickDataParams::builder(wrapper.symbol, wrapper.interval)
+                .start_time(wrapper.start_time)
+                .end_time(wrapper.end_time)
+                .limit(wrapper.limit)
+                .build()?,
+        )
     }
 }
 
-#[post("/kline")]
+#[post("/kline", wrap = "from_fn(require_market_data)")]
 async fn kline(
     data: web::Data<AppState>,
-    query: web::Query<KeyName>,
+    query: web::Query<OptionalKeyName>,
     param: web::Form<KlineCandlestickDataParamsWrapper>,
 ) -> Result<HttpResponse, actix_web::Error> {
     /*
@@ -39,24 +70,59 @@ async fn kline(
     之所以有这样的区别, 论坛上给出的答复是为了给使用 stream 的人方便调用的, 我们这里使用 kline candlestick data
     */
 
+    let param = KlineCandlestickDataParams::try_from(param.into_inner())?;
+
+    if let Some(rows) = stored_klines(&data, query.key(), KlineQuery::from(&param)).await {
+        return Ok(HttpResponse::Ok().json(rows));
+    }
+
     // 调用辅助函数获取客户端
-    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+    let client = get_market_client::<rest_api::RestApi>(&data, query.key())?;
 
-    let param: KlineCandlestickDataParams = param.into_inner().into();
     // 调用 API 方法，替换为实际存在的 get_account_info 方法
-    let response = client
-        .kline_candlestick_data(param.clone())
+    let response = data
+        .retry
+        .call("usds_future/kline", Idempotency::Idempotent, || {
+            client.kline_candlestick_data(param.clone())
+        })
         .await
         .map_err(|e| {
             error!("kline - {} {:?}", e, param);
-            actix_web::error::ErrorInternalServerError(e)
+            ApiError::from(e)
         })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, query.key(), &response.rate_limits);
 
     let data = response.data().await.map_err(|e| {
         error!("Failed to get data from response: {}", e);
-        actix_web::error::ErrorInternalServerError(e)
+        ApiError::from(e)
     })?;
 
     // 返回响应
     Ok(HttpResponse::Ok().json(data))
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test};
+
+    use crate::handler::common::test_util::{invalid_fields, post_form, test_state};
+    use crate::handler::usds_future::routes;
+
+    #[actix_web::test]
+    async fn test_kline_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = post_form("/usds_future/kline?key=binance1", "interval=1m").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["symbol"]);
+
+        let req = post_form(
+            "/usds_future/kline?key=binance1",
+            "symbol=BTCUSDT&interval=1m&limit=5000&start_time=2&end_time=1",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["limit", "start_time"]);
+    }
+}
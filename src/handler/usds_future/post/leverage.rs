@@ -1,9 +1,17 @@
+use actix_web::middleware::from_fn;
 use actix_web::{HttpResponse, post, web};
 use binance_sdk::derivatives_trading_usds_futures::rest_api;
 use serde::Deserialize;
 use tracing::error;
 
-use crate::{app::AppState, common::params::KeyName, handler::common::get_client_from_state};
+use crate::{
+    app::AppState,
+    common::params::KeyName,
+    common::retry::Idempotency,
+    handler::common::{ApiError, Validation, get_client_from_state},
+    middleware::auth::require_trade,
+    stream::market::Product,
+};
 
 #[derive(Deserialize)]
 struct LeverageParam {
@@ -11,32 +19,120 @@ struct LeverageParam {
     leverage: i64,
 }
 
-#[post("/change_initial_leverage")]
+impl TryFrom<LeverageParam> for rest_api::ChangeInitialLeverageParams {
+    type Error = ApiError;
+
+    fn try_from(param: LeverageParam) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(!param.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                (1..=125).contains(&param.leverage),
+                "leverage",
+                "must be between 1 and 125",
+            )
+            .finish()?;
+
+        Ok(Self::builder(param.symbol, param.leverage).build()?)
+    }
+}
+
+#[post("/change_initial_leverage", wrap = "from_fn(require_trade)")]
 async fn change_initial_leverage(
     data: web::Data<AppState>,
     query: web::Query<KeyName>,
     param: web::Form<LeverageParam>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    // 设置 API 参数
+    let params = rest_api::ChangeInitialLeverageParams::try_from(param.into_inner())?;
+
+    // 按 key 的风控规则检查交易对和杠杆上限
+    if let Some(rules) = data.risk.rules(&query.key) {
+        rules.check_leverage(&params.symbol, params.leverage)?;
+    }
+
     // 调用辅助函数获取客户端
     let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
 
-    // 设置 API 参数
-    let params =
-        rest_api::ChangeInitialLeverageParams::builder(param.symbol.clone(), param.leverage)
-            .build()
-            .unwrap();
-
     // 调用 API 方法，替换为实际存在的 get_account_info 方法
-    let response = client.change_initial_leverage(params).await.map_err(|e| {
-        error!("change_initial_leverage: {}", e);
-        actix_web::error::ErrorInternalServerError(e)
-    })?;
+    let response = data
+        .retry
+        .call(
+            "usds_future/change_initial_leverage",
+            Idempotency::NonIdempotent,
+            || client.change_initial_leverage(params.clone()),
+        )
+        .await
+        .map_err(|e| {
+            error!("change_initial_leverage: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, Some(&query.key), &response.rate_limits);
 
     let data = response.data().await.map_err(|e| {
         error!("Failed to get data from response: {}", e);
-        actix_web::error::ErrorInternalServerError(e)
+        ApiError::from(e)
     })?;
 
     // 返回响应
     Ok(HttpResponse::Ok().json(data))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use actix_web::{App, test};
+    use serde_json::Value;
+
+    use crate::common::risk::RiskEngine;
+    use crate::config::RiskRules;
+    use crate::handler::common::test_util::{
+        invalid_fields, post_form, test_state, test_state_with,
+    };
+    use crate::handler::usds_future::routes;
+
+    #[actix_web::test]
+    async fn test_change_initial_leverage_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = post_form(
+            "/usds_future/change_initial_leverage?key=binance1",
+            "symbol=BTCUSDT&leverage=abc",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_client_error());
+
+        let req = post_form(
+            "/usds_future/change_initial_leverage?key=binance1",
+            "symbol=BTCUSDT&leverage=500",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["leverage"]);
+    }
+
+    #[actix_web::test]
+    async fn test_change_initial_leverage_risk() {
+        let state = test_state_with(|state| {
+            let rules = RiskRules {
+                max_leverage: Some(10),
+                ..Default::default()
+            };
+            state.risk = Arc::new(RiskEngine::new(HashMap::from([("*".to_string(), rules)])));
+        });
+        let app = test::init_service(App::new().app_data(state).configure(routes)).await;
+
+        let req = post_form(
+            "/usds_future/change_initial_leverage?key=binance1",
+            "symbol=BTCUSDT&leverage=20",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["rule"], "max_leverage");
+    }
+}
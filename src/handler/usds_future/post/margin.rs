@@ -1,3 +1,4 @@
+use actix_web::middleware::from_fn;
 use actix_web::{HttpResponse, post, web};
 use binance_sdk::derivatives_trading_usds_futures::rest_api::{
     self, ChangeMarginTypeMarginTypeEnum, ChangeMarginTypeParams,
@@ -5,7 +6,14 @@ use binance_sdk::derivatives_trading_usds_futures::rest_api::{
 use serde::Deserialize;
 use tracing::error;
 
-use crate::{app::AppState, common::params::KeyName, handler::common::get_client_from_state};
+use crate::{
+    app::AppState,
+    common::params::KeyName,
+    common::retry::Idempotency,
+    handler::common::{ApiError, Validation, get_client_from_state},
+    middleware::auth::require_trade,
+    stream::market::Product,
+};
 
 #[derive(Deserialize)]
 struct ChangeMarginTypeParamsWrapper {
@@ -13,39 +21,80 @@ struct ChangeMarginTypeParamsWrapper {
     margin_type: ChangeMarginTypeMarginTypeEnum,
 }
 
-impl From<ChangeMarginTypeParamsWrapper> for ChangeMarginTypeParams {
-    fn from(wrapper: ChangeMarginTypeParamsWrapper) -> Self {
-        let builder = Self::builder(wrapper.symbol, wrapper.margin_type);
-        builder.build().unwrap()
+impl TryFrom<ChangeMarginTypeParamsWrapper> for ChangeMarginTypeParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: ChangeMarginTypeParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .finish()?;
+
+        Ok(Self::builder(wrapper.symbol, wrapper.margin_type).build()?)
     }
 }
 
-#[post("/change_margin_type")]
+#[post("/change_margin_type", wrap = "from_fn(require_trade)")]
 async fn change_margin_type(
     data: web::Data<AppState>,
     query: web::Query<KeyName>,
     param: web::Form<ChangeMarginTypeParamsWrapper>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    // 设置 API 参数
+    let params = ChangeMarginTypeParams::try_from(param.into_inner())?;
+
     // 调用辅助函数获取客户端
     let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
 
-    // 设置 API 参数
-    let params =
-        rest_api::ChangeMarginTypeParams::builder(param.symbol.clone(), param.margin_type.clone())
-            .build()
-            .unwrap();
-
     // 调用 API 方法，替换为实际存在的 get_account_info 方法
-    let response = client.change_margin_type(params).await.map_err(|e| {
-        error!("change_margin_type: {}", e);
-        actix_web::error::ErrorInternalServerError(e)
-    })?;
+    let response = data
+        .retry
+        .call(
+            "usds_future/change_margin_type",
+            Idempotency::NonIdempotent,
+            || client.change_margin_type(params.clone()),
+        )
+        .await
+        .map_err(|e| {
+            error!("change_margin_type: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, Some(&query.key), &response.rate_limits);
 
     let data = response.data().await.map_err(|e| {
         error!("Failed to get data from response: {}", e);
-        actix_web::error::ErrorInternalServerError(e)
+        ApiError::from(e)
     })?;
 
     // 返回响应
     Ok(HttpResponse::Ok().json(data))
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test};
+
+    use crate::handler::common::test_util::{invalid_fields, post_form, test_state};
+    use crate::handler::usds_future::routes;
+
+    #[actix_web::test]
+    async fn test_change_margin_type_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = post_form(
+            "/usds_future/change_margin_type?key=binance1",
+            "symbol=BTCUSDT",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["margin_type"]);
+
+        let req = post_form(
+            "/usds_future/change_margin_type?key=binance1",
+            "symbol=&margin_type=ISOLATED",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["symbol"]);
+    }
+}
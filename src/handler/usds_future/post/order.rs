@@ -1,53 +1,447 @@
+use actix_web::middleware::from_fn;
 use actix_web::{HttpResponse, post, web};
-use binance_sdk::{
-    derivatives_trading_usds_futures::rest_api::{
-        self, NewOrderNewOrderRespTypeEnum, NewOrderParams, NewOrderSideEnum,
-    },
-    spot::rest_api::NewOrderTypeEnum,
+use binance_sdk::derivatives_trading_usds_futures::rest_api::{
+    self, AllOrdersResponseInner, CancelAllOpenOrdersParams, CancelOrderParams,
+    CurrentAllOpenOrdersParams, ModifyOrderParams, ModifyOrderPriceMatchEnum, ModifyOrderSideEnum,
+    NewOrderNewOrderRespTypeEnum, NewOrderParams, NewOrderPositionSideEnum, NewOrderPriceMatchEnum,
+    NewOrderSelfTradePreventionModeEnum, NewOrderSideEnum, NewOrderTimeInForceEnum,
+    NewOrderWorkingTypeEnum, PositionInformationV3Params, QueryOrderParams, QueryOrderResponse,
 };
 use rust_decimal::Decimal;
 use serde::Deserialize;
-use tracing::error;
+use tracing::{error, warn};
 
-use crate::{app::AppState, common::params::KeyName, handler::common::get_client_from_state};
+use crate::{
+    app::AppState,
+    common::exchange_info::OrderCheck,
+    common::params::{KeyName, OrderResubmit, OrderRounding},
+    common::rate_limit::Cost,
+    common::retry::{Idempotency, Placed, is_order_not_found},
+    common::risk::{OrderRisk, Reservation},
+    common::submissions::{Begin, new_client_order_id},
+    handler::common::{ApiError, Validation, get_client_from_state, with_client_order_id},
+    middleware::auth::require_trade,
+    stream::market::Product,
+};
 
 fn default_new_order_resp_type() -> Option<NewOrderNewOrderRespTypeEnum> {
     Some(NewOrderNewOrderRespTypeEnum::Result)
 }
 
+// SDK 中期货下单的 type 参数是 String, 这里定义期货支持的订单类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum OrderTypeEnum {
+    Limit,
+    Market,
+    Stop,
+    StopMarket,
+    TakeProfit,
+    TakeProfitMarket,
+    TrailingStopMarket,
+}
+
+impl OrderTypeEnum {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Limit => "LIMIT",
+            Self::Market => "MARKET",
+            Self::Stop => "STOP",
+            Self::StopMarket => "STOP_MARKET",
+            Self::TakeProfit => "TAKE_PROFIT",
+            Self::TakeProfitMarket => "TAKE_PROFIT_MARKET",
+            Self::TrailingStopMarket => "TRAILING_STOP_MARKET",
+        }
+    }
+}
+
 #[derive(Deserialize)]
-struct NewOrderParamsWrapper {
+pub(super) struct NewOrderParamsWrapper {
     symbol: String,
     side: NewOrderSideEnum,
-    r#type: NewOrderTypeEnum,
-    quantity: Decimal,
+    r#type: OrderTypeEnum,
+    position_side: Option<NewOrderPositionSideEnum>,
+    time_in_force: Option<NewOrderTimeInForceEnum>,
+    quantity: Option<Decimal>,
+    reduce_only: Option<bool>,
     price: Option<Decimal>,
-    // time_in_force: Option<String>,
+    new_client_order_id: Option<String>,
+    stop_price: Option<Decimal>,
+    pub(super) close_position: Option<bool>,
+    activation_price: Option<Decimal>,
+    callback_rate: Option<Decimal>,
+    working_type: Option<NewOrderWorkingTypeEnum>,
+    price_protect: Option<bool>,
     #[serde(default = "default_new_order_resp_type")]
     new_order_resp_type: Option<NewOrderNewOrderRespTypeEnum>,
+    price_match: Option<NewOrderPriceMatchEnum>,
+    self_trade_prevention_mode: Option<NewOrderSelfTradePreventionModeEnum>,
+    good_till_date: Option<i64>,
 }
 
-impl From<NewOrderParamsWrapper> for NewOrderParams {
-    fn from(wrapper: NewOrderParamsWrapper) -> Self {
-        let mut builder = Self::builder(
+impl NewOrderParamsWrapper {
+    fn validate(&self) -> Result<(), ApiError> {
+        use OrderTypeEnum::*;
+
+        let order_type = self.r#type;
+        let close_position = self.close_position == Some(true);
+        let positive = |value: Option<Decimal>| value.is_none_or(|v| v > Decimal::ZERO);
+
+        Validation::default()
+            .check(!self.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                positive(self.quantity),
+                "quantity",
+                "must be greater than 0",
+            )
+            .check(positive(self.price), "price", "must be greater than 0")
+            .check(
+                positive(self.stop_price),
+                "stop_price",
+                "must be greater than 0",
+            )
+            .check(
+                self.quantity.is_some() || close_position,
+                "quantity",
+                "required unless close_position is true",
+            )
+            .check(
+                !matches!(order_type, Limit | Stop | TakeProfit)
+                    || self.price.is_some()
+                    || self.price_match.is_some(),
+                "price",
+                "required for LIMIT, STOP and TAKE_PROFIT orders",
+            )
+            .check(
+                !(self.price.is_some() && self.price_match.is_some()),
+                "price_match",
+                "cannot be sent together with price",
+            )
+            .check(
+                !matches!(
+                    order_type,
+                    Stop | StopMarket | TakeProfit | TakeProfitMarket
+                ) || self.stop_price.is_some(),
+                "stop_price",
+                "required for STOP and TAKE_PROFIT orders",
+            )
+            .check(
+                order_type != TrailingStopMarket
+                    || self
+                        .callback_rate
+                        .is_some_and(|rate| (Decimal::new(1, 1)..=Decimal::TEN).contains(&rate)),
+                "callback_rate",
+                "required for TRAILING_STOP_MARKET orders, between 0.1 and 10",
+            )
+            .check(
+                !close_position || matches!(order_type, StopMarket | TakeProfitMarket),
+                "close_position",
+                "only supported by STOP_MARKET and TAKE_PROFIT_MARKET orders",
+            )
+            .check(
+                !close_position || (self.quantity.is_none() && self.reduce_only.is_none()),
+                "close_position",
+                "cannot be sent together with quantity or reduce_only",
+            )
+            .check(
+                self.time_in_force.is_none() || matches!(order_type, Limit | Stop | TakeProfit),
+                "time_in_force",
+                "only supported by LIMIT, STOP and TAKE_PROFIT orders",
+            )
+            .check(
+                !matches!(self.time_in_force, Some(NewOrderTimeInForceEnum::Gtd))
+                    || self.good_till_date.is_some(),
+                "good_till_date",
+                "required when time_in_force is GTD",
+            )
+            .check(
+                self.new_client_order_id
+                    .as_ref()
+                    .is_none_or(|id| !id.is_empty() && id.len() <= 36),
+                "new_client_order_id",
+                "must be 1 to 36 characters",
+            )
+            .finish()
+    }
+}
+
+impl TryFrom<NewOrderParamsWrapper> for NewOrderParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: NewOrderParamsWrapper) -> Result<Self, Self::Error> {
+        wrapper.validate()?;
+
+        // 币安要求限价类订单必须带 timeInForce, 未传时默认 GTC
+        let time_in_force = match wrapper.r#type {
+            OrderTypeEnum::Limit | OrderTypeEnum::Stop | OrderTypeEnum::TakeProfit => Some(
+                wrapper
+                    .time_in_force
+                    .unwrap_or(NewOrderTimeInForceEnum::Gtc),
+            ),
+            _ => None,
+        };
+        let bool_str = |value: Option<bool>| value.map(|v| v.to_string());
+
+        Ok(Self::builder(
             wrapper.symbol,
             wrapper.side,
             wrapper.r#type.as_str().to_string(),
         )
-        .quantity(wrapper.quantity);
+        .position_side(wrapper.position_side)
+        .time_in_force(time_in_force)
+        .quantity(wrapper.quantity)
+        .reduce_only(bool_str(wrapper.reduce_only))
+        .price(wrapper.price)
+        .new_client_order_id(wrapper.new_client_order_id)
+        .stop_price(wrapper.stop_price)
+        .close_position(bool_str(wrapper.close_position))
+        .activation_price(wrapper.activation_price)
+        .callback_rate(wrapper.callback_rate)
+        .working_type(wrapper.working_type)
+        .price_protect(bool_str(wrapper.price_protect))
+        .new_order_resp_type(wrapper.new_order_resp_type)
+        .price_match(wrapper.price_match)
+        .self_trade_prevention_mode(wrapper.self_trade_prevention_mode)
+        .good_till_date(wrapper.good_till_date)
+        .build()?)
+    }
+}
 
-        if let Some(price) = wrapper.price {
-            builder = builder.price(price);
-        }
+// 按交易规则校验订单, round=true 时先对齐价格和数量
+// 交易规则不可用时跳过, 由币安校验
+async fn check_symbol_filters(
+    data: &web::Data<AppState>,
+    key_name: &str,
+    params: &mut NewOrderParams,
+    round: bool,
+) -> Result<(), ApiError> {
+    let Some(filters) = data
+        .exchange_info
+        .usds_future_filters(key_name, &params.symbol)
+        .await?
+    else {
+        return Ok(());
+    };
+
+    let is_buy = matches!(params.side, NewOrderSideEnum::Buy);
+    if round {
+        filters.round(
+            is_buy,
+            &mut params.price,
+            &mut params.stop_price,
+            &mut params.quantity,
+        );
+    }
+
+    let reduce_only = |value: &Option<String>| value.as_deref() == Some("true");
+    let mut order = OrderCheck {
+        is_buy,
+        price: params.price,
+        stop_price: params.stop_price,
+        quantity: params.quantity,
+        reference_price: None,
+        check_notional: !reduce_only(&params.reduce_only) && !reduce_only(&params.close_position),
+    };
+    if filters.needs_reference_price(&order) {
+        order.reference_price = data
+            .exchange_info
+            .usds_future_mark_price(&params.symbol)
+            .await;
+    }
+
+    let mut validation = Validation::default();
+    filters.check(&order, &mut validation);
+    validation.finish()
+}
 
-        // if let Some(time_in_force) = wrapper.time_in_force {
-        //     builder = builder.time_in_force(time_in_force);
-        // }
+// 可能立即成交的订单: 市价单和非只做 maker (GTX) 的限价单
+// 条件单触发前一直是挂单, 结果未知时币安会拒绝重复的 client order id
+fn is_marketable(params: &NewOrderParams) -> bool {
+    params.r#type == OrderTypeEnum::Market.as_str()
+        || (params.r#type == OrderTypeEnum::Limit.as_str()
+            && !matches!(params.time_in_force, Some(NewOrderTimeInForceEnum::Gtx)))
+}
+
+// 风控检查的新订单, SDK 参数中的 reduce_only 和 close_position 是 "true" / "false"
+fn order_risk(params: &NewOrderParams) -> OrderRisk {
+    let is_true = |value: &Option<String>| value.as_deref() == Some("true");
+    OrderRisk {
+        symbol: params.symbol.clone(),
+        is_buy: matches!(params.side, NewOrderSideEnum::Buy),
+        reduce_only: is_true(&params.reduce_only) || is_true(&params.close_position),
+        position_side: params
+            .position_side
+            .as_ref()
+            .map_or("BOTH", NewOrderPositionSideEnum::as_str)
+            .to_string(),
+        quantity: params.quantity,
+        price: params.price,
+        stop_price: params.stop_price,
+        ..Default::default()
+    }
+}
 
-        if let Some(new_order_resp_type) = wrapper.new_order_resp_type {
-            builder = builder.new_order_resp_type(new_order_resp_type);
+// 按 key 的风控规则检查订单, 只查询规则用到的挂单, 持仓和标记价格
+// 通过时返回预留, 调用方持有到下单结束; modified 为被修改订单的 order_id, 不计入挂单
+pub(super) async fn check_risk<'a>(
+    operation: &str,
+    data: &'a web::Data<AppState>,
+    key_name: &str,
+    client: &rest_api::RestApi,
+    mut order: OrderRisk,
+    modified: Option<i64>,
+) -> Result<Option<Reservation<'a>>, ApiError> {
+    let Some(rules) = data.risk.rules(key_name) else {
+        return Ok(None);
+    };
+
+    if rules.needs_open_orders() || rules.needs_position(&order) {
+        // 只检查持仓名义价值时只需要该交易对的挂单
+        let symbol = (!rules.needs_open_orders()).then_some(order.symbol.as_str());
+        let orders = open_orders(data, key_name, client, symbol).await?;
+        let orders = orders
+            .iter()
+            .filter(|o| modified.is_none_or(|id| o.order_id != Some(id)));
+        order.open_orders = orders.clone().count();
+
+        let side = if order.is_buy { "BUY" } else { "SELL" };
+        order.open_order_qty = orders
+            .filter(|o| {
+                o.symbol.as_deref() == Some(order.symbol.as_str())
+                    && o.side.as_deref() == Some(side)
+                    && o.position_side.as_deref() == Some(order.position_side.as_str())
+                    && o.reduce_only != Some(true)
+                    && o.close_position != Some(true)
+            })
+            .filter_map(|o| {
+                let orig_qty = o.orig_qty.as_deref()?.parse::<Decimal>().ok()?;
+                let executed_qty = o
+                    .executed_qty
+                    .as_deref()
+                    .and_then(|qty| qty.parse::<Decimal>().ok())
+                    .unwrap_or_default();
+                Some(orig_qty - executed_qty)
+            })
+            .sum();
+    }
+    if rules.needs_position(&order) {
+        order.position_amt =
+            position_amt(data, key_name, client, &order.symbol, &order.position_side).await?;
+    }
+    if rules.needs_mark_price(&order) {
+        order.mark_price = data
+            .exchange_info
+            .usds_future_mark_price(&order.symbol)
+            .await;
+    }
+
+    data.risk
+        .reserve(key_name, rules, &order)
+        .map(Some)
+        .inspect_err(|e| {
+            warn!("{}: {} rejected by risk rules: {}", operation, key_name, e);
+        })
+}
+
+// 挂单, symbol 为 None 时返回全部交易对的挂单, 优先使用用户数据流维护的缓存
+async fn open_orders(
+    data: &web::Data<AppState>,
+    key_name: &str,
+    client: &rest_api::RestApi,
+    symbol: Option<&str>,
+) -> Result<Vec<AllOrdersResponseInner>, ApiError> {
+    if let Some(orders) = data
+        .usds_future_account_cache
+        .open_orders(key_name, client, symbol)
+        .await
+    {
+        return Ok(orders);
+    }
+
+    // 与路由请求共用该 key 的限流额度, 不带 symbol 时权重为 40
+    let weight = if symbol.is_some() { 1 } else { 40 };
+    let params = CurrentAllOpenOrdersParams::builder()
+        .symbol(symbol.map(str::to_string))
+        .build()?;
+    let response = data
+        .rate_limiter
+        .call(
+            Some(key_name),
+            Cost::weight(Product::UsdsFuture, weight),
+            async || {
+                data.retry
+                    .call(
+                        "usds_future/current_all_open_orders",
+                        Idempotency::Idempotent,
+                        || client.current_all_open_orders(params.clone()),
+                    )
+                    .await
+            },
+        )
+        .await?;
+    Ok(response.data().await?)
+}
+
+// 交易对在该持仓方向上的持仓数量, 空头为负数, 优先使用用户数据流维护的缓存
+async fn position_amt(
+    data: &web::Data<AppState>,
+    key_name: &str,
+    client: &rest_api::RestApi,
+    symbol: &str,
+    position_side: &str,
+) -> Result<Decimal, ApiError> {
+    let positions = match data
+        .usds_future_account_cache
+        .positions(key_name, client)
+        .await
+    {
+        Some(positions) => positions,
+        None => {
+            let params = PositionInformationV3Params::builder()
+                .symbol(symbol.to_string())
+                .build()?;
+            let response = data
+                .rate_limiter
+                .call(
+                    Some(key_name),
+                    Cost::weight(Product::UsdsFuture, 5),
+                    async || {
+                        data.retry
+                            .call(
+                                "usds_future/position_information",
+                                Idempotency::Idempotent,
+                                || client.position_information_v3(params.clone()),
+                            )
+                            .await
+                    },
+                )
+                .await?;
+            response.data().await?
         }
-        builder.build().unwrap()
+    };
+
+    Ok(positions
+        .iter()
+        .filter(|p| {
+            p.symbol.as_deref() == Some(symbol) && p.position_side.as_deref() == Some(position_side)
+        })
+        .filter_map(|p| p.position_amt.as_deref()?.parse::<Decimal>().ok())
+        .sum())
+}
+
+// 按 client order id 查询订单, 订单不存在时返回 None
+async fn find_order(
+    client: &rest_api::RestApi,
+    symbol: &str,
+    client_order_id: String,
+) -> anyhow::Result<Option<QueryOrderResponse>> {
+    let params = QueryOrderParams::builder(symbol.to_string())
+        .orig_client_order_id(client_order_id)
+        .build()?;
+    match client.query_order(params).await {
+        Ok(response) => Ok(Some(response.data().await?)),
+        Err(e) if is_order_not_found(&e) => Ok(None),
+        Err(e) => Err(e),
     }
 }
 
@@ -56,33 +450,788 @@ impl From<NewOrderParamsWrapper> for NewOrderParams {
 /// 参数:
 /// - symbol: 交易对 (必填)
 /// - side: 订单方向 (必填)
-/// - type: 订单类型 (必填)
-/// - quantity: 数量 (必填)
-/// - price: 价格 (限价单必填)
-/// - time_in_force: 有效时间 (可选)
-#[post("/new_order")]
+/// - type: LIMIT / MARKET / STOP / STOP_MARKET / TAKE_PROFIT / TAKE_PROFIT_MARKET / TRAILING_STOP_MARKET (必填)
+/// - quantity: 数量 (close_position=true 时不传)
+/// - price: 价格 (LIMIT / STOP / TAKE_PROFIT 必填, 或使用 price_match)
+/// - time_in_force: 有效时间 (可选, 限价类订单默认 GTC)
+/// - position_side: BOTH / LONG / SHORT, 双向持仓模式下必填
+/// - reduce_only, close_position, price_protect: true / false (可选)
+/// - stop_price: 触发价 (STOP / TAKE_PROFIT 类订单必填)
+/// - activation_price, callback_rate: 跟踪止损订单参数
+/// - working_type, price_match, self_trade_prevention_mode, new_client_order_id, good_till_date (可选)
+/// - round: true 时把 price / stop_price 对齐到 tickSize, quantity 向下对齐到 stepSize (可选, 放在 query 中)
+/// - resubmit: true 表示重新提交之前结果未知的订单, 网关没有记录时先向币安查询 (可选, 放在 query 中)
+///
+/// 下单前按交易规则校验 PRICE_FILTER, LOT_SIZE, MIN_NOTIONAL 和 PERCENT_PRICE
+/// 没有 new_client_order_id 时自动生成, 使用的 id 在每个响应 (包括错误响应) 的 X-Client-Order-Id 中返回
+/// 同一个 id 重复提交时返回第一次下单的结果, 不会重复下单; 跨越网关重启等端到端的幂等需要调用方自带 id
+/// 配置了 [risk] 时按 key 的风控规则检查, 不满足时返回 422 和触发的规则 rule
+#[post("/new_order", wrap = "from_fn(require_trade)")]
 async fn new_order(
     data: web::Data<AppState>,
     query: web::Query<KeyName>,
+    rounding: web::Query<OrderRounding>,
+    resubmit: web::Query<OrderResubmit>,
     param: web::Form<NewOrderParamsWrapper>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    let mut params = NewOrderParams::try_from(param.into_inner())?;
+
+    // 没有 new_client_order_id 时自动生成, 下单结果未知时可以按 id 查询后安全重试
+    let generated = params.new_client_order_id.is_none();
+    let client_order_id = params
+        .new_client_order_id
+        .get_or_insert_with(new_client_order_id)
+        .clone();
+
+    let result = submit_order(
+        &data,
+        &query.key,
+        params,
+        &client_order_id,
+        rounding.round,
+        resubmit.resubmit && !generated,
+    )
+    .await;
+    Ok(with_client_order_id(result, &client_order_id))
+}
+
+async fn submit_order(
+    data: &web::Data<AppState>,
+    key_name: &str,
+    mut params: NewOrderParams,
+    client_order_id: &str,
+    round: bool,
+    resubmit: bool,
+) -> Result<HttpResponse, ApiError> {
+    // 同一个 id 已经下过单时返回第一次的响应
+    let submission =
+        match data
+            .order_submissions
+            .begin(Product::UsdsFuture, key_name, client_order_id)
+        {
+            Begin::New(submission) => submission,
+            Begin::Done(response) => return Ok(HttpResponse::Ok().json(response)),
+            Begin::Pending => {
+                return Err(ApiError::conflict(format!(
+                    "Order {} is already being submitted",
+                    client_order_id
+                )));
+            }
+        };
+
+    check_symbol_filters(data, key_name, &mut params, round).await?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(data, key_name)?;
+
+    // 重新提交的 id 不在最近的记录中 (如网关重启后), 先向币安查询该订单是否已存在
+    let existing = if resubmit {
+        find_order(&client, &params.symbol, client_order_id.to_string())
+            .await
+            .map_err(|e| {
+                error!("new_order: {}", e);
+                ApiError::from(e)
+            })?
+    } else {
+        None
+    };
+
+    // 调用 API 方法, 结果未知的下单先按 id 查询, 确认没有下单后才重试
+    let placed = match existing {
+        Some(order) => Placed::Existing(order),
+        None => {
+            // 风控检查放在查询之后, 已经下过的订单直接返回, 不受之后的持仓和挂单影响
+            // 预留持有到下单结束
+            let _reservation = check_risk(
+                "new_order",
+                data,
+                key_name,
+                &client,
+                order_risk(&params),
+                None,
+            )
+            .await?;
+            data.retry
+                .place_order(
+                    "usds_future/new_order",
+                    Some(client_order_id),
+                    is_marketable(&params),
+                    || async {
+                        let response = client.new_order(params.clone()).await?;
+                        data.rate_limiter.record(
+                            Product::UsdsFuture,
+                            Some(key_name),
+                            &response.rate_limits,
+                        );
+                        Ok(response.data().await?)
+                    },
+                    |client_order_id| find_order(&client, &params.symbol, client_order_id),
+                )
+                .await
+                .map_err(|e| {
+                    error!("new_order: {}", e);
+                    ApiError::from(e)
+                })?
+        }
+    };
+    let response =
+        serde_json::to_value(&placed).map_err(|e| ApiError::from(anyhow::Error::from(e)))?;
+    submission.finish(&response);
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[derive(Deserialize)]
+struct CancelOrderParamsWrapper {
+    symbol: String,
+    order_id: Option<i64>,
+    orig_client_order_id: Option<String>,
+}
+
+impl TryFrom<CancelOrderParamsWrapper> for CancelOrderParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: CancelOrderParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                wrapper.order_id.is_some() || wrapper.orig_client_order_id.is_some(),
+                "order_id",
+                "order_id or orig_client_order_id is required",
+            )
+            .finish()?;
+
+        Ok(Self::builder(wrapper.symbol)
+            .order_id(wrapper.order_id)
+            .orig_client_order_id(wrapper.orig_client_order_id)
+            .build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct CancelAllOpenOrdersParamsWrapper {
+    symbol: String,
+}
+
+impl TryFrom<CancelAllOpenOrdersParamsWrapper> for CancelAllOpenOrdersParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: CancelAllOpenOrdersParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .finish()?;
+
+        Ok(Self::builder(wrapper.symbol).build()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct ModifyOrderParamsWrapper {
+    symbol: String,
+    side: ModifyOrderSideEnum,
+    quantity: Decimal,
+    price: Decimal,
+    order_id: Option<i64>,
+    orig_client_order_id: Option<String>,
+    price_match: Option<ModifyOrderPriceMatchEnum>,
+}
+
+impl TryFrom<ModifyOrderParamsWrapper> for ModifyOrderParams {
+    type Error = ApiError;
+
+    fn try_from(wrapper: ModifyOrderParamsWrapper) -> Result<Self, Self::Error> {
+        Validation::default()
+            .check(!wrapper.symbol.is_empty(), "symbol", "must not be empty")
+            .check(
+                wrapper.quantity > Decimal::ZERO,
+                "quantity",
+                "must be greater than 0",
+            )
+            .check(
+                wrapper.price > Decimal::ZERO,
+                "price",
+                "must be greater than 0",
+            )
+            .check(
+                wrapper.order_id.is_some() || wrapper.orig_client_order_id.is_some(),
+                "order_id",
+                "order_id or orig_client_order_id is required",
+            )
+            .finish()?;
+
+        Ok(Self::builder(
+            wrapper.symbol,
+            wrapper.side,
+            wrapper.quantity,
+            wrapper.price,
+        )
+        .order_id(wrapper.order_id)
+        .orig_client_order_id(wrapper.orig_client_order_id)
+        .price_match(wrapper.price_match)
+        .build()?)
+    }
+}
+
+/// 撤销订单
+/// POST /cancel_order
+/// 参数:
+/// - symbol: 交易对 (必填)
+/// - order_id / orig_client_order_id: 二选一
+#[post("/cancel_order", wrap = "from_fn(require_trade)")]
+async fn cancel_order(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Form<CancelOrderParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = CancelOrderParams::try_from(param.into_inner())?;
+
     // 调用辅助函数获取客户端
     let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
 
-    // 调用 API 方法
-    let response = client
-        .new_order(param.into_inner().into())
+    let response = data
+        .retry
+        .call(
+            "usds_future/cancel_order",
+            Idempotency::NonIdempotent,
+            || client.cancel_order(params.clone()),
+        )
         .await
         .map_err(|e| {
-            error!("new_order: {}", e);
-            actix_web::error::ErrorInternalServerError(e)
+            error!("cancel_order: {}", e);
+            ApiError::from(e)
         })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, Some(&query.key), &response.rate_limits);
 
     let data = response.data().await.map_err(|e| {
         error!("Failed to get data from response: {}", e);
-        actix_web::error::ErrorInternalServerError(e)
+        ApiError::from(e)
     })?;
 
     // 返回响应
     Ok(HttpResponse::Ok().json(data))
 }
+
+/// 撤销交易对的全部挂单
+/// POST /cancel_all_open_orders
+/// 参数:
+/// - symbol: 交易对 (必填)
+#[post("/cancel_all_open_orders", wrap = "from_fn(require_trade)")]
+async fn cancel_all_open_orders(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Form<CancelAllOpenOrdersParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = CancelAllOpenOrdersParams::try_from(param.into_inner())?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    let response = data
+        .retry
+        .call(
+            "usds_future/cancel_all_open_orders",
+            Idempotency::NonIdempotent,
+            || client.cancel_all_open_orders(params.clone()),
+        )
+        .await
+        .map_err(|e| {
+            error!("cancel_all_open_orders: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, Some(&query.key), &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+/// 修改限价单的价格和数量
+/// POST /modify_order
+/// 参数:
+/// - symbol: 交易对 (必填)
+/// - side: 订单方向 (必填)
+/// - quantity: 数量 (必填)
+/// - price: 价格 (必填)
+/// - order_id / orig_client_order_id: 二选一
+/// - price_match: 价格匹配模式 (可选)
+///
+/// 配置了 [risk] 时按修改后的价格和数量检查风控, 被修改的挂单不计入挂单数和挂单数量
+#[post("/modify_order", wrap = "from_fn(require_trade)")]
+async fn modify_order(
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+    param: web::Form<ModifyOrderParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = ModifyOrderParams::try_from(param.into_inner())?;
+
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    // 持仓方向和是否只减仓取自被修改的挂单, 预留持有到修改结束
+    let _reservation = if data.risk.rules(&query.key).is_some() {
+        let original = open_orders(&data, &query.key, &client, Some(&params.symbol))
+            .await?
+            .into_iter()
+            .find(|o| {
+                (params.order_id.is_some() && o.order_id == params.order_id)
+                    || (params.orig_client_order_id.is_some()
+                        && o.client_order_id == params.orig_client_order_id)
+            });
+        let order = OrderRisk {
+            symbol: params.symbol.clone(),
+            is_buy: matches!(params.side, ModifyOrderSideEnum::Buy),
+            reduce_only: original
+                .as_ref()
+                .is_some_and(|o| o.reduce_only == Some(true) || o.close_position == Some(true)),
+            position_side: original
+                .as_ref()
+                .and_then(|o| o.position_side.clone())
+                .unwrap_or_else(|| "BOTH".to_string()),
+            quantity: Some(params.quantity),
+            price: Some(params.price),
+            ..Default::default()
+        };
+        let modified = original.and_then(|o| o.order_id);
+        check_risk("modify_order", &data, &query.key, &client, order, modified).await?
+    } else {
+        None
+    };
+
+    let response = data
+        .retry
+        .call(
+            "usds_future/modify_order",
+            Idempotency::NonIdempotent,
+            || client.modify_order(params.clone()),
+        )
+        .await
+        .map_err(|e| {
+            error!("modify_order: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, Some(&query.key), &response.rate_limits);
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    // 返回响应
+    Ok(HttpResponse::Ok().json(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use actix_web::{App, HttpRequest, HttpResponse, test, web};
+    use serde_json::{Value, json};
+
+    use crate::app::AppState;
+    use crate::common::rate_limit::LimitKind;
+    use crate::common::retry::RetryPolicy;
+    use crate::common::risk::RiskEngine;
+    use crate::config::{RetryConfig, RiskRules};
+    use crate::handler::common::CLIENT_ORDER_ID_HEADER;
+    use crate::handler::common::test_util::{
+        invalid_fields, post_form, state_with_usds_future_stub, test_state,
+    };
+    use crate::handler::usds_future::routes;
+
+    // 模拟币安的交易规则, 下单和查询订单接口, 记录交易规则的请求次数和每笔订单的参数
+    #[derive(Clone, Default)]
+    struct Upstream {
+        exchange_info: Arc<AtomicUsize>,
+        orders: Arc<Mutex<Vec<HashMap<String, String>>>>,
+        // 之后这么多笔订单下单成功但返回 503, 模拟响应丢失
+        lost_responses: Arc<AtomicUsize>,
+        // 查询挂单接口返回的挂单, 没有持仓, 标记价格固定为 60000
+        open_orders: Arc<Mutex<Vec<Value>>>,
+    }
+
+    async fn stub_exchange_info(upstream: web::Data<Upstream>) -> HttpResponse {
+        upstream.exchange_info.fetch_add(1, Ordering::SeqCst);
+        HttpResponse::Ok().json(json!({"symbols": [{
+            "symbol": "BTCUSDT",
+            "baseAsset": "BTC",
+            "filters": [
+                {"filterType": "PRICE_FILTER", "minPrice": "556.80", "maxPrice": "4529764", "tickSize": "0.10"},
+                {"filterType": "LOT_SIZE", "minQty": "0.001", "maxQty": "1000", "stepSize": "0.001"},
+                {"filterType": "MARKET_LOT_SIZE", "minQty": "0.001", "maxQty": "120", "stepSize": "0.001"},
+                {"filterType": "MIN_NOTIONAL", "notional": "100"}
+            ]
+        }]}))
+    }
+
+    // 签名参数可能在 query 或 body 中, 合并后返回
+    fn request_params(req: &HttpRequest, body: &str) -> HashMap<String, String> {
+        let mut params = HashMap::new();
+        for raw in [req.query_string(), body] {
+            if let Ok(query) = web::Query::<HashMap<String, String>>::from_query(raw) {
+                params.extend(query.into_inner());
+            }
+        }
+        params
+    }
+
+    fn order_response(order_id: usize, params: &HashMap<String, String>) -> Value {
+        json!({
+            "orderId": order_id,
+            "symbol": params.get("symbol"),
+            "clientOrderId": params.get("newClientOrderId"),
+            "price": params.get("price"),
+            "origQty": params.get("quantity"),
+            "status": "NEW",
+        })
+    }
+
+    async fn stub_new_order(
+        upstream: web::Data<Upstream>,
+        req: HttpRequest,
+        body: String,
+    ) -> HttpResponse {
+        let params = request_params(&req, &body);
+        let mut orders = upstream.orders.lock().unwrap();
+        let response = order_response(orders.len() + 1, &params);
+        orders.push(params);
+
+        let lost = upstream
+            .lost_responses
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if lost {
+            return HttpResponse::ServiceUnavailable().finish();
+        }
+        HttpResponse::Ok().json(response)
+    }
+
+    async fn stub_query_order(
+        upstream: web::Data<Upstream>,
+        req: HttpRequest,
+        body: String,
+    ) -> HttpResponse {
+        let params = request_params(&req, &body);
+        let orders = upstream.orders.lock().unwrap();
+        match orders
+            .iter()
+            .position(|order| order.get("newClientOrderId") == params.get("origClientOrderId"))
+        {
+            Some(i) => HttpResponse::Ok().json(order_response(i + 1, &orders[i])),
+            None => HttpResponse::BadRequest()
+                .json(json!({"code": -2013, "msg": "Order does not exist."})),
+        }
+    }
+
+    async fn stub_open_orders(upstream: web::Data<Upstream>) -> HttpResponse {
+        HttpResponse::Ok().json(&*upstream.open_orders.lock().unwrap())
+    }
+
+    async fn stub_mark_price() -> HttpResponse {
+        HttpResponse::Ok().json(json!({"symbol": "BTCUSDT", "markPrice": "60000"}))
+    }
+
+    impl Upstream {
+        // binance1 的客户端和交易规则缓存都指向模拟服务
+        async fn state(&self, f: impl FnOnce(&mut AppState)) -> web::Data<AppState> {
+            let upstream = self.clone();
+            state_with_usds_future_stub(
+                move |cfg| {
+                    cfg.app_data(web::Data::new(upstream.clone()))
+                        .route("/fapi/v1/exchangeInfo", web::get().to(stub_exchange_info))
+                        .route("/fapi/v1/order", web::post().to(stub_new_order))
+                        .route("/fapi/v1/order", web::get().to(stub_query_order))
+                        .route("/fapi/v1/openOrders", web::get().to(stub_open_orders))
+                        .route(
+                            "/fapi/v3/positionRisk",
+                            web::get().to(|| async { HttpResponse::Ok().json(json!([])) }),
+                        )
+                        .route("/fapi/v1/premiumIndex", web::get().to(stub_mark_price));
+                },
+                f,
+            )
+            .await
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_new_order_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = post_form(
+            "/usds_future/new_order?key=binance1",
+            "symbol=BTCUSDT&side=BUY&type=MARKET",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["quantity"]);
+
+        let req = post_form(
+            "/usds_future/new_order?key=binance1",
+            "symbol=BTCUSDT&side=BUY&type=LIMIT&quantity=0",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["quantity", "price"]);
+
+        let req = post_form(
+            "/usds_future/new_order?key=binance1&round=maybe",
+            "symbol=BTCUSDT&side=BUY&type=MARKET&quantity=1",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn test_new_order_client_order_id_header() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        // 错误响应同样返回生成的 client order id
+        let req = post_form(
+            "/usds_future/new_order?key=binance1",
+            "symbol=BTCUSDT&side=BUY&type=MARKET&quantity=1",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_client_error());
+        let id = resp.headers().get(CLIENT_ORDER_ID_HEADER).unwrap();
+        let id = id.to_str().unwrap();
+        assert!(id.starts_with("qe-"));
+
+        let req = post_form(
+            "/usds_future/new_order?key=binance1",
+            "symbol=BTCUSDT&side=BUY&type=MARKET&quantity=1&new_client_order_id=bot1-1",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get(CLIENT_ORDER_ID_HEADER).unwrap(),
+            "bot1-1"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_order_lifecycle_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req =
+            post_form("/usds_future/cancel_order?key=binance1", "symbol=BTCUSDT").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["order_id"]);
+
+        let req = post_form(
+            "/usds_future/cancel_all_open_orders?key=binance1",
+            "symbol=",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["symbol"]);
+
+        let req = post_form(
+            "/usds_future/modify_order?key=binance1",
+            "symbol=BTCUSDT&side=BUY&quantity=-1&price=100&order_id=1",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["quantity"]);
+    }
+
+    #[actix_web::test]
+    async fn test_new_order_conditional_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = post_form(
+            "/usds_future/new_order?key=binance1",
+            "symbol=BTCUSDT&side=SELL&type=STOP_MARKET&close_position=true&quantity=1",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            invalid_fields(resp).await,
+            vec!["stop_price", "close_position"]
+        );
+
+        let req = post_form(
+            "/usds_future/new_order?key=binance1",
+            "symbol=BTCUSDT&side=SELL&type=TRAILING_STOP_MARKET&quantity=1&callback_rate=20",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["callback_rate"]);
+    }
+
+    #[actix_web::test]
+    async fn test_new_order_round() {
+        let upstream = Upstream::default();
+        let app = test::init_service(
+            App::new()
+                .app_data(upstream.state(|_| {}).await)
+                .configure(routes),
+        )
+        .await;
+
+        let form = "symbol=BTCUSDT&side=BUY&type=LIMIT&quantity=0.0123&price=60000.17";
+        let req = post_form("/usds_future/new_order?key=binance1", form).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["price", "quantity"]);
+
+        // 买单价格向下对齐到 tickSize, 数量向下对齐到 stepSize
+        let req = post_form("/usds_future/new_order?key=binance1&round=true", form).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        // 交易规则只请求一次, 之后的订单使用缓存
+        assert_eq!(upstream.exchange_info.load(Ordering::SeqCst), 1);
+        let orders = upstream.orders.lock().unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0]["price"], "60000.1");
+        assert_eq!(orders[0]["quantity"], "0.012");
+    }
+
+    #[actix_web::test]
+    async fn test_new_order_lost_response() {
+        let upstream = Upstream::default();
+        upstream.lost_responses.store(1, Ordering::SeqCst);
+        let state = upstream
+            .state(|state| {
+                let config = RetryConfig {
+                    initial_backoff_ms: 10,
+                    ..Default::default()
+                };
+                state.retry = Arc::new(RetryPolicy::new(&config));
+            })
+            .await;
+        let app = test::init_service(App::new().app_data(state).configure(routes)).await;
+
+        // 下单结果未知时按 client order id 查到已经下过的订单, 不会重复下单
+        let req = post_form(
+            "/usds_future/new_order?key=binance1",
+            "symbol=BTCUSDT&side=BUY&type=LIMIT&quantity=0.01&price=60000&time_in_force=GTC",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let id = resp.headers().get(CLIENT_ORDER_ID_HEADER).unwrap().clone();
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["orderId"], 1);
+        assert_eq!(body["clientOrderId"], id.to_str().unwrap());
+        assert_eq!(upstream.orders.lock().unwrap().len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_new_order_duplicate_submission() {
+        let upstream = Upstream::default();
+        let app = test::init_service(
+            App::new()
+                .app_data(upstream.state(|_| {}).await)
+                .configure(routes),
+        )
+        .await;
+        let form = "symbol=BTCUSDT&side=BUY&type=LIMIT&quantity=0.01&price=60000\
+                    &time_in_force=GTC&new_client_order_id=bot1-1";
+
+        // 同一个 id 再次提交时返回第一次的结果, 不会再次下单
+        let mut bodies = Vec::new();
+        for _ in 0..2 {
+            let req = post_form("/usds_future/new_order?key=binance1", form).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 200);
+            bodies.push(test::read_body_json::<Value, _>(resp).await);
+        }
+        assert_eq!(bodies[0], bodies[1]);
+        assert_eq!(upstream.orders.lock().unwrap().len(), 1);
+
+        // 网关重启后没有记录, resubmit=true 时先向币安查询, 订单已存在时直接返回
+        let app = test::init_service(
+            App::new()
+                .app_data(upstream.state(|_| {}).await)
+                .configure(routes),
+        )
+        .await;
+        let req = post_form("/usds_future/new_order?key=binance1&resubmit=true", form).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["orderId"], bodies[0]["orderId"]);
+        assert_eq!(upstream.orders.lock().unwrap().len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_new_order_risk() {
+        let upstream = Upstream::default();
+        upstream.open_orders.lock().unwrap().push(json!({
+            "orderId": 7,
+            "symbol": "BTCUSDT",
+            "side": "BUY",
+            "positionSide": "BOTH",
+            "origQty": "0.5",
+            "executedQty": "0",
+            "reduceOnly": false,
+            "closePosition": false,
+            "status": "NEW",
+        }));
+        let state = upstream
+            .state(|state| {
+                let rules = RiskRules {
+                    max_order_notional: Some(30_000.into()),
+                    max_position_notional: Some(50_000.into()),
+                    ..Default::default()
+                };
+                state.risk = Arc::new(RiskEngine::new(HashMap::from([("*".to_string(), rules)])));
+            })
+            .await;
+        let rate_limiter = state.rate_limiter.clone();
+        let app = test::init_service(App::new().app_data(state).configure(routes)).await;
+
+        // 0.5 的买入挂单加上这笔 0.4, 成交后持仓名义价值 54000
+        for (form, rule) in [
+            ("side=BUY&quantity=0.6", "max_order_notional"),
+            ("side=BUY&quantity=0.4", "max_position_notional"),
+        ] {
+            let req = post_form(
+                "/usds_future/new_order?key=binance1",
+                &format!(
+                    "symbol=BTCUSDT&type=LIMIT&price=60000&time_in_force=GTC&{}",
+                    form
+                ),
+            )
+            .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 422);
+            assert!(resp.headers().contains_key(CLIENT_ORDER_ID_HEADER));
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(body["rule"], rule);
+            assert_eq!(body["source"], "gateway");
+        }
+        assert!(upstream.orders.lock().unwrap().is_empty());
+
+        // 卖单只计入同方向的挂单, 成交后持仓名义价值 24000
+        let req = post_form(
+            "/usds_future/new_order?key=binance1",
+            "symbol=BTCUSDT&side=SELL&type=LIMIT&price=60000&time_in_force=GTC&quantity=0.4",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(upstream.orders.lock().unwrap().len(), 1);
+
+        // 缓存不可用时, 每笔订单的挂单 (权重 1) 和持仓 (权重 5) 查询都占用限流额度
+        let weight = rate_limiter
+            .usage()
+            .iter()
+            .filter(|u| u.kind == LimitKind::RequestWeight)
+            .map(|u| u.used)
+            .sum::<u32>();
+        assert!(weight >= 3 * (1 + 5));
+    }
+}
@@ -1,40 +1,98 @@
+use actix_web::middleware::from_fn;
 use actix_web::{HttpResponse, post, web};
 use binance_sdk::derivatives_trading_usds_futures::rest_api;
 use serde::Deserialize;
 use tracing::error;
 
-use crate::{app::AppState, common::params::KeyName, handler::common::get_client_from_state};
+use crate::{
+    app::AppState,
+    common::params::KeyName,
+    common::retry::Idempotency,
+    handler::common::{ApiError, Validation, get_client_from_state},
+    middleware::auth::require_trade,
+    stream::market::Product,
+};
 
 #[derive(Deserialize)]
 struct PositionModeParam {
     mode: String,
 }
 
-#[post("/change_position_mode")]
+impl TryFrom<PositionModeParam> for rest_api::ChangePositionModeParams {
+    type Error = ApiError;
+
+    fn try_from(param: PositionModeParam) -> Result<Self, Self::Error> {
+        // "true": 双向持仓模式, "false": 单向持仓模式
+        Validation::default()
+            .check(
+                param.mode == "true" || param.mode == "false",
+                "mode",
+                "must be true or false",
+            )
+            .finish()?;
+
+        Ok(Self::builder(param.mode).build()?)
+    }
+}
+
+#[post("/change_position_mode", wrap = "from_fn(require_trade)")]
 async fn change_position_mode(
     data: web::Data<AppState>,
     query: web::Query<KeyName>,
     param: web::Form<PositionModeParam>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    // 设置 API 参数
+    let params = rest_api::ChangePositionModeParams::try_from(param.into_inner())?;
+
     // 调用辅助函数获取客户端
     let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
 
-    // 设置 API 参数
-    let params = rest_api::ChangePositionModeParams::builder(param.mode.clone())
-        .build()
-        .unwrap();
-
     // 调用 API 方法，替换为实际存在的 get_account_info 方法
-    let response = client.change_position_mode(params).await.map_err(|e| {
-        error!("change_position_mode: {}", e);
-        actix_web::error::ErrorInternalServerError(e)
-    })?;
+    let response = data
+        .retry
+        .call(
+            "usds_future/change_position_mode",
+            Idempotency::NonIdempotent,
+            || client.change_position_mode(params.clone()),
+        )
+        .await
+        .map_err(|e| {
+            error!("change_position_mode: {}", e);
+            ApiError::from(e)
+        })?;
+    data.rate_limiter
+        .record(Product::UsdsFuture, Some(&query.key), &response.rate_limits);
 
     let data = response.data().await.map_err(|e| {
         error!("Failed to get data from response: {}", e);
-        actix_web::error::ErrorInternalServerError(e)
+        ApiError::from(e)
     })?;
 
     // 返回响应
     Ok(HttpResponse::Ok().json(data))
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test};
+
+    use crate::handler::common::test_util::{invalid_fields, post_form, test_state};
+    use crate::handler::usds_future::routes;
+
+    #[actix_web::test]
+    async fn test_change_position_mode_invalid_params() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = post_form("/usds_future/change_position_mode?key=binance1", "").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["mode"]);
+
+        let req = post_form(
+            "/usds_future/change_position_mode?key=binance1",
+            "mode=hedge",
+        )
+        .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["mode"]);
+    }
+}
@@ -0,0 +1,246 @@
+use actix_web::middleware::from_fn;
+use actix_web::{HttpRequest, HttpResponse, get, web};
+use actix_ws::{Message, MessageStream, Session};
+use binance_sdk::derivatives_trading_usds_futures::rest_api;
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+
+use crate::app::AppState;
+use crate::common::params::KeyName;
+use crate::handler::common::{ApiError, Validation, get_client_from_state, query_config};
+use crate::middleware::auth::{bearer_auth, require_account, require_market_data};
+use crate::stream::market::{Product, normalize_stream};
+
+// 单个本地连接最多订阅的行情流数量
+const MAX_MARKET_STREAMS: usize = 50;
+
+pub fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/ws")
+            .wrap(from_fn(bearer_auth))
+            .app_data(query_config())
+            .service(usds_future_user)
+            .service(market),
+    );
+}
+
+// 把广播接收端转为 Stream, 上游关闭时结束, 客户端消费太慢时跳过积压的消息
+fn receiver_stream(receiver: broadcast::Receiver<String>) -> impl Stream<Item = String> {
+    futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(msg) => return Some((msg, receiver)),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("relay: client lagged, {} messages skipped", skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+// 把上游消息转发给本地客户端, 直到任意一端关闭
+async fn relay(
+    mut session: Session,
+    mut messages: MessageStream,
+    mut events: impl Stream<Item = String> + Unpin,
+) {
+    loop {
+        tokio::select! {
+            event = events.next() => match event {
+                Some(text) => {
+                    if session.text(text).await.is_err() {
+                        return;
+                    }
+                }
+                None => break,
+            },
+            msg = messages.recv() => match msg {
+                Some(Ok(Message::Ping(bytes))) => {
+                    if session.pong(&bytes).await.is_err() {
+                        return;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    let _ = session.close(None).await;
+}
+
+/// U 本位合约用户数据流 (WebSocket)
+/// GET /usds_future/user
+/// 参数:
+/// - key: keys.toml 中的 key 名称 (必填)
+///
+/// 转发 ORDER_TRADE_UPDATE, ACCOUNT_UPDATE 和 MARGIN_CALL 事件, 消息为币安推送的原始 JSON
+/// 网关负责创建 listenKey, 每 30 分钟续期, 过期后自动重建
+#[get("/usds_future/user", wrap = "from_fn(require_account)")]
+pub async fn usds_future_user(
+    req: HttpRequest,
+    body: web::Payload,
+    data: web::Data<AppState>,
+    query: web::Query<KeyName>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // 调用辅助函数获取客户端
+    let client = get_client_from_state::<rest_api::RestApi>(&data, &query.key)?;
+
+    // 先完成握手校验, 再创建上游连接
+    let (response, session, messages) = actix_ws::handle(&req, body)?;
+    let events = data
+        .usds_future_user_streams
+        .subscribe(&query.key, client)
+        .await?;
+
+    info!("usds_future_user: client connected for key {}", query.key);
+    actix_web::rt::spawn(relay(session, messages, Box::pin(receiver_stream(events))));
+
+    Ok(response)
+}
+
+#[derive(Deserialize)]
+struct MarketParamsWrapper {
+    streams: String,
+    product: Option<Product>,
+}
+
+struct MarketSubscription {
+    product: Product,
+    streams: Vec<String>,
+}
+
+impl TryFrom<MarketParamsWrapper> for MarketSubscription {
+    type Error = ApiError;
+
+    fn try_from(wrapper: MarketParamsWrapper) -> Result<Self, Self::Error> {
+        let product = wrapper.product.unwrap_or_default();
+        let names: Vec<&str> = wrapper
+            .streams
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .collect();
+        let unsupported: Vec<&str> = names
+            .iter()
+            .filter(|name| normalize_stream(product, name).is_none())
+            .copied()
+            .collect();
+
+        Validation::default()
+            .check(!names.is_empty(), "streams", "must not be empty")
+            .check(
+                names.len() <= MAX_MARKET_STREAMS,
+                "streams",
+                "must not contain more than 50 streams",
+            )
+            .check(
+                unsupported.is_empty(),
+                "streams",
+                &format!("unsupported streams: {}", unsupported.join(",")),
+            )
+            .finish()?;
+
+        let mut streams: Vec<String> = names
+            .iter()
+            .filter_map(|name| normalize_stream(product, name))
+            .collect();
+        streams.sort();
+        streams.dedup();
+
+        Ok(MarketSubscription { product, streams })
+    }
+}
+
+/// 公开行情流 (WebSocket)
+/// GET /market
+/// 参数:
+/// - streams: 逗号分隔的行情流, 最多 50 个 (必填), 支持:
+///   `<symbol>@aggTrade`, `<symbol>@bookTicker`, `<symbol>@kline_<interval>`,
+///   `<symbol>@depth[5|10|20][@<speed>]`, `<symbol>@markPrice[@1s]` (只有 U 本位合约)
+/// - product: usds_future / spot, 默认 usds_future (可选)
+///
+/// 消息为币安组合流的原始 JSON `{"stream": "...", "data": {...}}`
+/// 每条行情流只有一条上游连接, 由所有本地订阅者共享
+#[get("/market", wrap = "from_fn(require_market_data)")]
+pub async fn market(
+    req: HttpRequest,
+    body: web::Payload,
+    data: web::Data<AppState>,
+    param: web::Query<MarketParamsWrapper>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscription = MarketSubscription::try_from(param.into_inner())?;
+
+    // 先完成握手校验, 再创建上游连接
+    let (response, session, messages) = actix_ws::handle(&req, body)?;
+    let mut receivers = Vec::with_capacity(subscription.streams.len());
+    for stream in &subscription.streams {
+        let receiver = data
+            .market_streams
+            .subscribe(subscription.product, stream)
+            .await?;
+        receivers.push(Box::pin(receiver_stream(receiver)));
+    }
+
+    info!(
+        "market: client subscribed to {} {}",
+        subscription.product.as_str(),
+        subscription.streams.join(",")
+    );
+    actix_web::rt::spawn(relay(
+        session,
+        messages,
+        futures::stream::select_all(receivers),
+    ));
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::header::{CONNECTION, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_VERSION, UPGRADE};
+    use actix_web::{App, test};
+
+    use crate::handler::common::test_util::{get, invalid_fields, test_state};
+    use crate::handler::ws::routes;
+
+    fn ws_get(uri: &str) -> test::TestRequest {
+        get(uri)
+            .insert_header((UPGRADE, "websocket"))
+            .insert_header((CONNECTION, "upgrade"))
+            .insert_header((SEC_WEBSOCKET_VERSION, "13"))
+            .insert_header((SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ=="))
+    }
+
+    #[actix_web::test]
+    async fn test_usds_future_user_unknown_key() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req = ws_get("/ws/usds_future/user?key=unknown").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        // 缺少 key 参数
+        let req = ws_get("/ws/usds_future/user").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn test_market_invalid_streams() {
+        let app = test::init_service(App::new().app_data(test_state()).configure(routes)).await;
+
+        let req =
+            ws_get("/ws/market?streams=btcusdt@markPrice,btcusdt@trade&product=spot").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["streams"]);
+
+        let req = ws_get("/ws/market?streams=").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(invalid_fields(resp).await, vec!["streams"]);
+    }
+}
@@ -2,3 +2,5 @@ pub mod app;
 pub mod handler;
 pub mod common;
 pub mod config;
+pub mod middleware;
+pub mod stream;
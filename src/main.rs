@@ -2,6 +2,8 @@ mod app;
 mod handler;
 mod common;
 mod config;
+mod middleware;
+mod stream;
 
 use crate::app::run;
 
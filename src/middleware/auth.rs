@@ -0,0 +1,293 @@
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{AUTHORIZATION, HeaderValue, WWW_AUTHENTICATE};
+use actix_web::middleware::Next;
+use actix_web::{HttpMessage, ResponseError, web};
+use tracing::{debug, warn};
+
+use crate::app::AppState;
+use crate::common::params::KeyName;
+use crate::config::{AuthConfig, AuthToken, Permission};
+use crate::handler::common::ApiError;
+use crate::middleware::rate_limit::rate_limit;
+
+// 逐字节比较, 避免通过响应时间猜测 token
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn find_token<'a>(auth: &'a AuthConfig, token: &str) -> Option<&'a AuthToken> {
+    auth.tokens
+        .iter()
+        .find(|t| constant_time_eq(t.token.as_bytes(), token.as_bytes()))
+}
+
+fn bearer_token(req: &ServiceRequest) -> Option<&str> {
+    req.headers()
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+/// 网关鉴权中间件
+/// 校验 `Authorization: Bearer <token>`, token 在 config.toml 的 `[[auth.tokens]]` 中配置
+/// 校验通过后把对应的 `AuthToken` 放入 request extensions, 供后续中间件和 handler 使用
+pub async fn bearer_auth(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let caller = req
+        .app_data::<web::Data<AppState>>()
+        .and_then(|data| find_token(&data.auth, bearer_token(&req)?).cloned());
+
+    match caller {
+        Some(caller) => {
+            debug!("{} {} by {}", req.method(), req.path(), caller.name);
+            req.extensions_mut().insert(caller);
+            next.call(req)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        }
+        None => Ok(unauthorized(req)),
+    }
+}
+
+fn unauthorized<B>(req: ServiceRequest) -> ServiceResponse<EitherBody<B>> {
+    warn!(
+        "Unauthorized request: {} {} from {:?}",
+        req.method(),
+        req.path(),
+        req.peer_addr()
+    );
+    let mut response = ApiError::unauthorized("Missing or invalid bearer token").error_response();
+    response
+        .headers_mut()
+        .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    req.into_response(response).map_into_right_body()
+}
+
+fn forbidden<B>(req: ServiceRequest, msg: String) -> ServiceResponse<EitherBody<B>> {
+    warn!(
+        "Forbidden request: {} {} - {}",
+        req.method(),
+        req.path(),
+        msg
+    );
+    req.into_response(ApiError::forbidden(msg).error_response())
+        .map_into_right_body()
+}
+
+/// 授权检查, 需要在 `bearer_auth` 之后执行, 通过后再占用限流额度, 被拒绝的请求不占用额度
+/// - token 的权限等级不低于路由要求的等级
+/// - 请求中的 `?key=` 在 token 允许的 key 列表中
+async fn authorize<B: MessageBody>(
+    required: Permission,
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
+    let Some(caller) = req.extensions().get::<AuthToken>().cloned() else {
+        return Ok(unauthorized(req));
+    };
+
+    if caller.permission < required {
+        let msg = format!(
+            "Token {} has {} permission, {} is required",
+            caller.name,
+            caller.permission.as_str(),
+            required.as_str()
+        );
+        return Ok(forbidden(req, msg));
+    }
+
+    // 没有 key 参数的请求交给 handler 处理
+    if let Ok(query) = web::Query::<KeyName>::from_query(req.query_string())
+        && !caller.allows_key(&query.key)
+    {
+        let msg = format!(
+            "Token {} is not allowed to use key {}",
+            caller.name, query.key
+        );
+        return Ok(forbidden(req, msg));
+    }
+
+    rate_limit(req, next).await
+}
+
+/// 路由级中间件, 用法: `#[get("/path", wrap = "from_fn(require_market_data)")]`
+pub async fn require_market_data(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    authorize(Permission::MarketData, req, next).await
+}
+
+pub async fn require_account(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    authorize(Permission::Account, req, next).await
+}
+
+pub async fn require_trade(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    authorize(Permission::Trade, req, next).await
+}
+
+pub async fn require_transfer(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    authorize(Permission::Transfer, req, next).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::middleware::from_fn;
+    use actix_web::{App, HttpResponse, http, test};
+
+    async fn ok() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    fn test_auth() -> AuthConfig {
+        AuthConfig {
+            tokens: vec![
+                AuthToken {
+                    name: "bot1".to_string(),
+                    token: "secret-token".to_string(),
+                    keys: vec!["*".to_string()],
+                    permission: Permission::Trade,
+                },
+                AuthToken {
+                    name: "research".to_string(),
+                    token: "research-token".to_string(),
+                    keys: vec!["sub1".to_string()],
+                    permission: Permission::Account,
+                },
+            ],
+        }
+    }
+
+    async fn call(uri: &str, token: Option<&str>) -> http::StatusCode {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState::for_test(test_auth())))
+                .service(
+                    web::scope("/usds_future")
+                        .wrap(from_fn(bearer_auth))
+                        .service(
+                            web::resource("/account")
+                                .wrap(from_fn(require_account))
+                                .route(web::get().to(ok)),
+                        )
+                        .service(
+                            web::resource("/trade")
+                                .wrap(from_fn(require_trade))
+                                .route(web::get().to(ok)),
+                        ),
+                ),
+        )
+        .await;
+        let mut req = test::TestRequest::get().uri(uri);
+        if let Some(token) = token {
+            req = req.insert_header((AUTHORIZATION, format!("Bearer {}", token)));
+        }
+        test::call_service(&app, req.to_request()).await.status()
+    }
+
+    #[actix_web::test]
+    async fn test_bearer_auth() {
+        let uri = "/usds_future/account?key=sub1";
+        assert_eq!(call(uri, None).await, http::StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            call(uri, Some("wrong-token")).await,
+            http::StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(call(uri, Some("secret-token")).await, http::StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_permission_class() {
+        assert_eq!(
+            call("/usds_future/trade?key=sub1", Some("research-token")).await,
+            http::StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            call("/usds_future/account?key=sub1", Some("research-token")).await,
+            http::StatusCode::OK
+        );
+        assert_eq!(
+            call("/usds_future/trade?key=sub1", Some("secret-token")).await,
+            http::StatusCode::OK
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_rate_limit_after_authorize() {
+        let data = web::Data::new(AppState::for_test(test_auth()));
+        let app = test::init_service(
+            App::new().app_data(data.clone()).service(
+                web::scope("/usds_future")
+                    .wrap(from_fn(bearer_auth))
+                    .service(
+                        web::resource("/trade")
+                            .wrap(from_fn(require_trade))
+                            .route(web::get().to(ok)),
+                    )
+                    .service(
+                        web::resource("/invalid")
+                            .wrap(from_fn(require_trade))
+                            .route(web::get().to(|| async {
+                                Err::<HttpResponse, _>(ApiError::bad_request("invalid"))
+                            })),
+                    ),
+            ),
+        )
+        .await;
+        let call = async |uri: &str, token: &str| {
+            let req = test::TestRequest::get()
+                .uri(uri)
+                .insert_header((AUTHORIZATION, format!("Bearer {}", token)))
+                .to_request();
+            test::call_service(&app, req).await.status()
+        };
+
+        // 鉴权失败和网关自身拒绝的请求不占用额度
+        assert_eq!(
+            call("/usds_future/trade?key=sub1", "research-token").await,
+            http::StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            call("/usds_future/invalid?key=sub1", "secret-token").await,
+            http::StatusCode::BAD_REQUEST
+        );
+        assert!(data.rate_limiter.usage().iter().all(|u| u.used == 0));
+
+        assert_eq!(
+            call("/usds_future/trade?key=sub1", "secret-token").await,
+            http::StatusCode::OK
+        );
+        assert!(data.rate_limiter.usage().iter().any(|u| u.used == 1));
+    }
+
+    #[actix_web::test]
+    async fn test_key_scope() {
+        assert_eq!(
+            call("/usds_future/account?key=binance1", Some("research-token")).await,
+            http::StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            call("/usds_future/account?key=binance1", Some("secret-token")).await,
+            http::StatusCode::OK
+        );
+        assert_eq!(
+            call("/usds_future/account", Some("research-token")).await,
+            http::StatusCode::OK
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::http::header::{HeaderValue, RETRY_AFTER};
+use actix_web::middleware::Next;
+use actix_web::{ResponseError, web};
+use tracing::warn;
+
+use crate::app::AppState;
+use crate::common::params::OptionalKeyName;
+use crate::common::rate_limit::{endpoint_cost, limited, retry_after};
+use crate::handler::common::{ApiError, ErrorSource};
+
+/// 本地限流, 由 `authorize` 在鉴权通过后调用, 鉴权失败的请求不占用额度
+/// 按路由的币安权重和下单数占用额度, 额度不足时排队等待窗口重置, 等待过久时返回 429 和 Retry-After
+/// 在调用币安之前被网关拒绝的请求 (参数校验, 风控等) 归还占用的额度
+/// 币安返回 429 / 418 时暂停该上游的全部请求, 网关自身返回的 429 (如缓存刷新被本地限流) 只归还额度
+pub(crate) async fn rate_limit<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
+    let Some(data) = req.app_data::<web::Data<AppState>>().cloned() else {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    };
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .map(web::Query::into_inner)
+        .unwrap_or_default();
+    let Some(cost) = endpoint_cost(req.path(), &query) else {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    };
+    let key = web::Query::<OptionalKeyName>::from_query(req.query_string())
+        .ok()
+        .and_then(|query| query.key().map(str::to_string));
+
+    if let Err(wait) = data.rate_limiter.acquire(key.as_deref(), cost).await {
+        warn!(
+            "Rate limited request: {} {}, retry after {}s",
+            req.method(),
+            req.path(),
+            retry_after(wait)
+        );
+        let mut response = limited(cost.product, wait).error_response();
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(retry_after(wait)));
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    let response = next.call(req).await?;
+    match (response.status(), error_source(&response)) {
+        (
+            status @ (StatusCode::TOO_MANY_REQUESTS | StatusCode::IM_A_TEAPOT),
+            Some(ErrorSource::Binance),
+        ) => {
+            data.rate_limiter.back_off(
+                cost.product,
+                key.as_deref(),
+                status == StatusCode::IM_A_TEAPOT,
+            );
+        }
+        (status, Some(ErrorSource::Gateway)) if status.is_client_error() => {
+            data.rate_limiter.release(key.as_deref(), cost);
+        }
+        _ => {}
+    }
+    Ok(response.map_into_left_body())
+}
+
+// 网关返回的 4xx 错误都在调用币安之前产生, 只有币安返回的 429 / 418 需要暂停上游
+fn error_source<B>(response: &ServiceResponse<B>) -> Option<ErrorSource> {
+    response
+        .response()
+        .error()
+        .and_then(|e| e.as_error::<ApiError>())
+        .map(|e| e.source)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use actix_web::http::header::RETRY_AFTER;
+    use actix_web::{App, HttpResponse, test, web};
+    use serde_json::{Value, json};
+
+    use crate::app::AppState;
+    use crate::common::rate_limit::RateLimiter;
+    use crate::config::RateLimitConfig;
+    use crate::handler::common::test_util::{get, post_form, state_with_usds_future_stub};
+    use crate::handler::usds_future::routes;
+
+    // 模拟币安超过权重上限, 记录请求次数
+    async fn stub_too_many_requests(requests: web::Data<AtomicUsize>) -> HttpResponse {
+        requests.fetch_add(1, Ordering::SeqCst);
+        HttpResponse::TooManyRequests().json(json!({
+            "code": -1003,
+            "msg": "Too many requests; current limit is 2400 requests per minute."
+        }))
+    }
+
+    // 下单返回 429 的模拟服务, 限流器不排队等待, 额度不足时直接返回 429
+    async fn state(
+        requests: &web::Data<AtomicUsize>,
+        config: RateLimitConfig,
+    ) -> web::Data<AppState> {
+        let requests = requests.clone();
+        state_with_usds_future_stub(
+            move |cfg| {
+                cfg.app_data(requests.clone())
+                    .route("/fapi/v1/order", web::post().to(stub_too_many_requests));
+            },
+            |state| {
+                let config = RateLimitConfig {
+                    max_wait_ms: 0,
+                    ..config
+                };
+                state.rate_limiter = Arc::new(RateLimiter::new(&HashMap::new(), &config));
+            },
+        )
+        .await
+    }
+
+    #[actix_web::test]
+    async fn test_retry_after() {
+        let requests = web::Data::new(AtomicUsize::new(0));
+        let state = state(&requests, RateLimitConfig::default()).await;
+        let app = test::init_service(App::new().app_data(state).configure(routes)).await;
+        let form = "symbol=BTCUSDT&side=BUY&type=MARKET&quantity=1";
+
+        // 币安返回 429 后暂停该上游, 之后的请求在本地拒绝并返回 Retry-After
+        let req = post_form("/usds_future/new_order?key=binance1", form).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 429);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["source"], "binance");
+
+        let req = post_form("/usds_future/new_order?key=binance1", form).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 429);
+        let retry_after: u64 = resp
+            .headers()
+            .get(RETRY_AFTER)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((1..=60).contains(&retry_after));
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["source"], "gateway");
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_local_too_many_requests() {
+        let requests = web::Data::new(AtomicUsize::new(0));
+        // 每个窗口只放行一次请求
+        let config = RateLimitConfig {
+            usage_ratio: 0.0,
+            ..Default::default()
+        };
+        let state = state(&requests, config).await;
+        let rate_limiter = state.rate_limiter.clone();
+        let app = test::init_service(App::new().app_data(state).configure(routes)).await;
+
+        // 路由占用额度后, 交易规则缓存的刷新被本地限流, 网关返回 429
+        let req = get("/usds_future/exchange_information?key=binance1").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 429);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["source"], "gateway");
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+
+        // 不暂停上游, 并归还路由占用的额度
+        let usage = rate_limiter.usage();
+        assert!(!usage.is_empty());
+        assert!(
+            usage
+                .iter()
+                .all(|u| u.used == 0 && u.blocked_until.is_none())
+        );
+    }
+}
@@ -0,0 +1,121 @@
+pub mod account;
+pub mod market;
+pub mod user_data;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{Mutex, OnceCell, broadcast};
+use tokio::time::{Instant, Interval};
+
+use crate::handler::common::ApiError;
+
+// 上游连接名称到本地广播通道的映射, 同一条上游连接的所有本地订阅者共享一个通道
+// 通道在 OnceCell 中创建, 建立连接时不持有 map 的锁, 同名的并发订阅等待同一次连接
+type Senders = Arc<Mutex<HashMap<String, Arc<OnceCell<broadcast::Sender<String>>>>>>;
+
+/// 订阅名为 name 的上游连接, 没有连接时调用 connect 建立
+/// connect 负责启动转发任务并返回广播通道, 失败时下一个订阅者重新尝试
+async fn subscribe<F, Fut>(
+    senders: &Senders,
+    name: &str,
+    connect: F,
+) -> Result<broadcast::Receiver<String>, ApiError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<broadcast::Sender<String>, ApiError>>,
+{
+    loop {
+        let cell = {
+            let mut senders = senders.lock().await;
+            let cell = senders.entry(name.to_string()).or_default().clone();
+            if let Some(sender) = cell.get() {
+                return Ok(sender.subscribe());
+            }
+            cell
+        };
+
+        let result = cell.get_or_try_init(&connect).await.cloned();
+
+        // 持有锁时订阅, 避免与 remove_if_idle 竞争
+        let mut senders = senders.lock().await;
+        let current = senders.get(name).is_some_and(|c| Arc::ptr_eq(c, &cell));
+        match result {
+            Ok(sender) if current => return Ok(sender.subscribe()),
+            // 连接已经因为空闲被关闭, 重新建立
+            Ok(_) => continue,
+            Err(e) => {
+                // 没有其他订阅者在等待这次连接时移除, 避免失败的名称留在 map 中
+                if current && !cell.initialized() && Arc::strong_count(&cell) == 2 {
+                    senders.remove(name);
+                }
+                return Err(e);
+            }
+        }
+    }
+}
+
+// 没有本地订阅者时移除通道, 持有锁时检查, 避免与新的订阅竞争
+async fn remove_if_idle(senders: &Senders, name: &str, sender: &broadcast::Sender<String>) -> bool {
+    let mut senders = senders.lock().await;
+    if sender.receiver_count() > 0 {
+        return false;
+    }
+    if senders
+        .get(name)
+        .and_then(|cell| cell.get())
+        .is_some_and(|s| s.same_channel(sender))
+    {
+        senders.remove(name);
+    }
+    true
+}
+
+// 空闲检查的定时器, 跳过立即触发的第一次, 给第一个订阅者留出订阅的时间
+fn idle_check(period: Duration) -> Interval {
+    tokio::time::interval_at(Instant::now() + period, period)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use actix_web::http::StatusCode;
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_subscribe() {
+        let senders: Senders = Arc::new(Mutex::new(HashMap::new()));
+        let connects = AtomicUsize::new(0);
+        let connect = || async {
+            connects.fetch_add(1, Ordering::SeqCst);
+            // 模拟网络延迟, 期间其他订阅者不会被锁阻塞
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok(broadcast::channel(16).0)
+        };
+
+        // 同名的并发订阅只建立一次连接
+        let (a, b) = futures::join!(
+            subscribe(&senders, "a", connect),
+            subscribe(&senders, "a", connect)
+        );
+        let (a, b) = (a.unwrap(), b.unwrap());
+        assert_eq!(connects.load(Ordering::SeqCst), 1);
+
+        let sender = senders.lock().await["a"].get().unwrap().clone();
+        assert!(!remove_if_idle(&senders, "a", &sender).await);
+        drop((a, b));
+        assert!(remove_if_idle(&senders, "a", &sender).await);
+        assert!(senders.lock().await.is_empty());
+
+        // 连接失败时不保留记录, 下次订阅重新连接
+        let failed = subscribe(&senders, "b", || async {
+            Err(ApiError::gateway(StatusCode::BAD_GATEWAY, "boom"))
+        })
+        .await;
+        assert!(failed.is_err());
+        assert!(senders.lock().await.is_empty());
+    }
+}
@@ -0,0 +1,665 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use actix_web::http::StatusCode;
+use binance_sdk::derivatives_trading_usds_futures::rest_api::{
+    AllOrdersResponseInner, CurrentAllOpenOrdersParams, FuturesAccountBalanceV2ResponseInner,
+    FuturesAccountBalanceV3Params, PositionInformationV3Params, PositionInformationV3ResponseInner,
+    RestApi,
+};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{Mutex, OnceCell, broadcast};
+use tokio::time::Instant;
+use tracing::{debug, error, info, warn};
+
+use super::market::Product;
+use super::user_data::{UserDataStreams, is_reconnected};
+use crate::common::rate_limit::{Cost, RateLimiter};
+use crate::handler::common::ApiError;
+
+// 定期用 REST 快照校正, 弥补可能丢失的事件
+const RESYNC_INTERVAL: Duration = Duration::from_secs(10 * 60);
+// 初始化失败后 (listenKey 或快照请求失败) 等待的时间, 期间读取直接回退到 REST
+const FAILURE_BACKOFF: Duration = Duration::from_secs(30);
+
+type Book = Arc<RwLock<AccountBook>>;
+
+#[derive(Deserialize)]
+#[serde(tag = "e")]
+enum UserDataEvent {
+    #[serde(rename = "ACCOUNT_UPDATE")]
+    AccountUpdate(AccountUpdateEvent),
+    #[serde(rename = "ORDER_TRADE_UPDATE")]
+    OrderTradeUpdate(Box<OrderTradeUpdateEvent>),
+}
+
+#[derive(Deserialize)]
+struct AccountUpdateEvent {
+    #[serde(rename = "T")]
+    time: i64,
+    #[serde(rename = "a")]
+    account: AccountUpdate,
+}
+
+#[derive(Deserialize)]
+struct AccountUpdate {
+    #[serde(rename = "B", default)]
+    balances: Vec<BalanceUpdate>,
+    #[serde(rename = "P", default)]
+    positions: Vec<PositionUpdate>,
+}
+
+#[derive(Deserialize)]
+struct BalanceUpdate {
+    #[serde(rename = "a")]
+    asset: String,
+    #[serde(rename = "wb")]
+    wallet_balance: String,
+    #[serde(rename = "cw")]
+    cross_wallet_balance: String,
+}
+
+#[derive(Deserialize)]
+struct PositionUpdate {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "pa")]
+    position_amt: String,
+    #[serde(rename = "ep")]
+    entry_price: String,
+    #[serde(rename = "bep")]
+    break_even_price: Option<String>,
+    #[serde(rename = "iw")]
+    isolated_wallet: String,
+    #[serde(rename = "ps")]
+    position_side: String,
+}
+
+#[derive(Deserialize)]
+struct OrderTradeUpdateEvent {
+    #[serde(rename = "o")]
+    order: OrderUpdate,
+}
+
+#[derive(Deserialize)]
+struct OrderUpdate {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "c")]
+    client_order_id: String,
+    #[serde(rename = "S")]
+    side: String,
+    #[serde(rename = "o")]
+    order_type: String,
+    #[serde(rename = "f")]
+    time_in_force: String,
+    #[serde(rename = "q")]
+    orig_qty: String,
+    #[serde(rename = "p")]
+    price: String,
+    #[serde(rename = "ap")]
+    avg_price: String,
+    #[serde(rename = "sp")]
+    stop_price: String,
+    #[serde(rename = "X")]
+    status: String,
+    #[serde(rename = "i")]
+    order_id: i64,
+    #[serde(rename = "z")]
+    executed_qty: String,
+    #[serde(rename = "T")]
+    update_time: i64,
+    #[serde(rename = "R")]
+    reduce_only: bool,
+    #[serde(rename = "wt")]
+    working_type: String,
+    #[serde(rename = "ot")]
+    orig_type: String,
+    #[serde(rename = "ps")]
+    position_side: String,
+    #[serde(rename = "cp")]
+    close_position: bool,
+    #[serde(rename = "AP")]
+    activate_price: Option<String>,
+    #[serde(rename = "cr")]
+    price_rate: Option<String>,
+    #[serde(rename = "pP")]
+    price_protect: bool,
+    #[serde(rename = "V")]
+    self_trade_prevention_mode: Option<String>,
+    #[serde(rename = "pm")]
+    price_match: Option<String>,
+    #[serde(rename = "gtd")]
+    good_till_date: Option<i64>,
+}
+
+// 快照和事件可能乱序, 只应用不早于当前数据的事件
+fn is_stale(update_time: Option<i64>, time: i64) -> bool {
+    update_time.is_some_and(|update_time| update_time > time)
+}
+
+fn is_zero(value: &str) -> bool {
+    value.parse::<Decimal>().is_ok_and(|value| value.is_zero())
+}
+
+// 可用余额和未实现盈亏随标记价格变化, 币安不推送, 缓存中不保留
+fn pushed_balance(
+    mut balance: FuturesAccountBalanceV2ResponseInner,
+) -> FuturesAccountBalanceV2ResponseInner {
+    balance.cross_un_pnl = None;
+    balance.available_balance = None;
+    balance.max_withdraw_amount = None;
+    balance
+}
+
+// 标记价格以及由它计算的盈亏, 保证金和强平价格不保留, 只保留持仓数量, 开仓价格等事件中的字段
+fn pushed_position(
+    mut position: PositionInformationV3ResponseInner,
+) -> PositionInformationV3ResponseInner {
+    position.mark_price = None;
+    position.un_realized_profit = None;
+    position.liquidation_price = None;
+    position.isolated_margin = None;
+    position.notional = None;
+    position.initial_margin = None;
+    position.maint_margin = None;
+    position.position_initial_margin = None;
+    position.open_order_initial_margin = None;
+    position.adl = None;
+    position.bid_notional = None;
+    position.ask_notional = None;
+    position
+}
+
+/// 单个 key 的挂单, 持仓和余额, 数据结构与对应 REST 接口的返回一致
+/// 持仓和余额只保留用户数据流推送的字段, 见 pushed_position 和 pushed_balance
+#[derive(Debug, Default)]
+struct AccountBook {
+    open_orders: BTreeMap<i64, AllOrdersResponseInner>,
+    // (symbol, position_side)
+    positions: BTreeMap<(String, String), PositionInformationV3ResponseInner>,
+    balances: BTreeMap<String, FuturesAccountBalanceV2ResponseInner>,
+}
+
+impl AccountBook {
+    fn from_snapshot(
+        open_orders: Vec<AllOrdersResponseInner>,
+        positions: Vec<PositionInformationV3ResponseInner>,
+        balances: Vec<FuturesAccountBalanceV2ResponseInner>,
+    ) -> Self {
+        AccountBook {
+            open_orders: open_orders
+                .into_iter()
+                .filter_map(|order| Some((order.order_id?, order)))
+                .collect(),
+            positions: positions
+                .into_iter()
+                .filter_map(|position| {
+                    let key = (position.symbol.clone()?, position.position_side.clone()?);
+                    Some((key, pushed_position(position)))
+                })
+                .collect(),
+            balances: balances
+                .into_iter()
+                .filter_map(|balance| Some((balance.asset.clone()?, pushed_balance(balance))))
+                .collect(),
+        }
+    }
+
+    // 无法解析的消息 (如 MARGIN_CALL) 不影响缓存
+    fn apply(&mut self, msg: &str) {
+        match serde_json::from_str::<UserDataEvent>(msg) {
+            Ok(UserDataEvent::AccountUpdate(event)) => self.apply_account_update(event),
+            Ok(UserDataEvent::OrderTradeUpdate(event)) => self.apply_order_update(event.order),
+            Err(_) => {}
+        }
+    }
+
+    fn apply_account_update(&mut self, event: AccountUpdateEvent) {
+        for update in event.account.balances {
+            let balance = self.balances.entry(update.asset.clone()).or_default();
+            if is_stale(balance.update_time, event.time) {
+                continue;
+            }
+            balance.asset = Some(update.asset);
+            balance.balance = Some(update.wallet_balance);
+            balance.cross_wallet_balance = Some(update.cross_wallet_balance);
+            balance.update_time = Some(event.time);
+        }
+
+        for update in event.account.positions {
+            let key = (update.symbol.clone(), update.position_side.clone());
+            if is_stale(
+                self.positions.get(&key).and_then(|p| p.update_time),
+                event.time,
+            ) {
+                continue;
+            }
+            // 与 REST 接口一致, 只保留有持仓的交易对
+            if is_zero(&update.position_amt) {
+                self.positions.remove(&key);
+                continue;
+            }
+
+            let position = self.positions.entry(key).or_default();
+            position.symbol = Some(update.symbol);
+            position.position_side = Some(update.position_side);
+            position.position_amt = Some(update.position_amt);
+            position.entry_price = Some(update.entry_price);
+            position.break_even_price =
+                update.break_even_price.or(position.break_even_price.take());
+            position.isolated_wallet = Some(update.isolated_wallet);
+            position.update_time = Some(event.time);
+        }
+    }
+
+    fn apply_order_update(&mut self, update: OrderUpdate) {
+        if is_stale(
+            self.open_orders
+                .get(&update.order_id)
+                .and_then(|o| o.update_time),
+            update.update_time,
+        ) {
+            return;
+        }
+        // 成交, 撤销和过期的订单从挂单中移除
+        if !matches!(update.status.as_str(), "NEW" | "PARTIALLY_FILLED") {
+            self.open_orders.remove(&update.order_id);
+            return;
+        }
+
+        let cum_quote = update
+            .avg_price
+            .parse::<Decimal>()
+            .ok()
+            .zip(update.executed_qty.parse::<Decimal>().ok())
+            .map(|(price, qty)| (price * qty).normalize().to_string());
+
+        let order = self.open_orders.entry(update.order_id).or_default();
+        order.order_id = Some(update.order_id);
+        order.symbol = Some(update.symbol);
+        order.client_order_id = Some(update.client_order_id);
+        order.side = Some(update.side);
+        order.position_side = Some(update.position_side);
+        order.r#type = Some(update.order_type);
+        order.orig_type = Some(update.orig_type);
+        order.time_in_force = Some(update.time_in_force);
+        order.orig_qty = Some(update.orig_qty);
+        order.price = Some(update.price);
+        order.avg_price = Some(update.avg_price);
+        order.stop_price = Some(update.stop_price);
+        order.executed_qty = Some(update.executed_qty);
+        order.cum_quote = cum_quote;
+        order.status = Some(update.status);
+        order.reduce_only = Some(update.reduce_only);
+        order.close_position = Some(update.close_position);
+        order.working_type = Some(update.working_type);
+        order.price_protect = Some(update.price_protect);
+        order.activate_price = update.activate_price;
+        order.price_rate = update.price_rate;
+        order.self_trade_prevention_mode = update.self_trade_prevention_mode;
+        order.price_match = update.price_match;
+        order.good_till_date = update.good_till_date;
+        order.time = order.time.or(Some(update.update_time));
+        order.update_time = Some(update.update_time);
+    }
+
+    fn open_orders(&self, symbol: Option<&str>) -> Vec<AllOrdersResponseInner> {
+        self.open_orders
+            .values()
+            .filter(|order| symbol.is_none_or(|symbol| order.symbol.as_deref() == Some(symbol)))
+            .cloned()
+            .collect()
+    }
+
+    fn positions(&self) -> Vec<PositionInformationV3ResponseInner> {
+        self.positions.values().cloned().collect()
+    }
+
+    fn balances(&self) -> Vec<FuturesAccountBalanceV2ResponseInner> {
+        self.balances.values().cloned().collect()
+    }
+}
+
+// 快照请求与该 key 的路由请求共用限流额度
+async fn snapshot(
+    rate_limiter: &RateLimiter,
+    key_name: &str,
+    client: &RestApi,
+) -> Result<AccountBook, ApiError> {
+    let key_name = Some(key_name);
+    let open_orders = async {
+        let params = CurrentAllOpenOrdersParams::default();
+        let response = rate_limiter
+            .call(
+                key_name,
+                Cost::weight(Product::UsdsFuture, 40),
+                async || client.current_all_open_orders(params).await,
+            )
+            .await?;
+        Ok::<_, ApiError>(response.data().await?)
+    };
+    let positions = async {
+        let params = PositionInformationV3Params::default();
+        let response = rate_limiter
+            .call(key_name, Cost::weight(Product::UsdsFuture, 5), async || {
+                client.position_information_v3(params).await
+            })
+            .await?;
+        Ok::<_, ApiError>(response.data().await?)
+    };
+    let balances = async {
+        let params = FuturesAccountBalanceV3Params::default();
+        let response = rate_limiter
+            .call(key_name, Cost::weight(Product::UsdsFuture, 5), async || {
+                client.futures_account_balance_v3(params).await
+            })
+            .await?;
+        Ok::<_, ApiError>(response.data().await?)
+    };
+
+    let (open_orders, positions, balances) =
+        futures::try_join!(open_orders, positions, balances)
+            .inspect_err(|e| error!("account cache snapshot: {}", e))?;
+
+    Ok(AccountBook::from_snapshot(open_orders, positions, balances))
+}
+
+// 单个 key 的缓存, book 在锁外初始化, 同一个 key 的并发读取等待同一次初始化
+#[derive(Default)]
+struct Entry {
+    book: OnceCell<Book>,
+    // 最近一次初始化失败的时间, FAILURE_BACKOFF 内不再重试
+    failed_at: std::sync::Mutex<Option<Instant>>,
+}
+
+/// U 本位合约账户缓存, 由用户数据流更新
+/// 某个 key 第一次读取时订阅用户数据流并用 REST 快照初始化, 之后一直保持该 key 的用户数据流
+pub struct AccountCache {
+    user_streams: Arc<UserDataStreams>,
+    rate_limiter: Arc<RateLimiter>,
+    entries: Arc<Mutex<HashMap<String, Arc<Entry>>>>,
+}
+
+impl AccountCache {
+    pub fn new(user_streams: Arc<UserDataStreams>, rate_limiter: Arc<RateLimiter>) -> Self {
+        AccountCache {
+            user_streams,
+            rate_limiter,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    async fn book(&self, key_name: &str, client: &RestApi) -> Result<Book, ApiError> {
+        // 只在查找时持有锁, 订阅和取快照期间不阻塞其他 key
+        let entry = self
+            .entries
+            .lock()
+            .await
+            .entry(key_name.to_string())
+            .or_default()
+            .clone();
+
+        entry
+            .book
+            .get_or_try_init(|| self.init(key_name, client, &entry))
+            .await
+            .cloned()
+    }
+
+    async fn init(
+        &self,
+        key_name: &str,
+        client: &RestApi,
+        entry: &Arc<Entry>,
+    ) -> Result<Book, ApiError> {
+        if let Some(failed_at) = *entry.failed_at.lock().unwrap()
+            && failed_at.elapsed() < FAILURE_BACKOFF
+        {
+            return Err(ApiError::gateway(
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("Account cache of {} failed recently", key_name),
+            ));
+        }
+
+        // 先订阅再取快照, 快照期间的事件缓存在通道中, 之后按时间顺序应用
+        let result = async {
+            let events = self
+                .user_streams
+                .subscribe(key_name, client.clone())
+                .await?;
+            let book = Arc::new(RwLock::new(
+                snapshot(&self.rate_limiter, key_name, client).await?,
+            ));
+            Ok::<_, ApiError>((events, book))
+        }
+        .await;
+        let (events, book) = result.inspect_err(|_| {
+            *entry.failed_at.lock().unwrap() = Some(Instant::now());
+        })?;
+        info!("account cache: {} initialized", key_name);
+
+        tokio::spawn(sync(
+            key_name.to_string(),
+            client.clone(),
+            self.rate_limiter.clone(),
+            events,
+            book.clone(),
+            entry.clone(),
+            self.entries.clone(),
+        ));
+
+        Ok(book)
+    }
+
+    // 缓存无法初始化时 (如 base_url key 没有用户数据流) 返回 None, 由调用方回退到 REST
+    async fn cached(&self, key_name: &str, client: &RestApi) -> Option<Book> {
+        self.book(key_name, client)
+            .await
+            .map_err(|e| debug!("account cache: {} unavailable: {}", key_name, e))
+            .ok()
+    }
+
+    pub async fn open_orders(
+        &self,
+        key_name: &str,
+        client: &RestApi,
+        symbol: Option<&str>,
+    ) -> Option<Vec<AllOrdersResponseInner>> {
+        let book = self.cached(key_name, client).await?;
+        Some(book.read().unwrap().open_orders(symbol))
+    }
+
+    pub async fn positions(
+        &self,
+        key_name: &str,
+        client: &RestApi,
+    ) -> Option<Vec<PositionInformationV3ResponseInner>> {
+        let book = self.cached(key_name, client).await?;
+        Some(book.read().unwrap().positions())
+    }
+
+    pub async fn balances(
+        &self,
+        key_name: &str,
+        client: &RestApi,
+    ) -> Option<Vec<FuturesAccountBalanceV2ResponseInner>> {
+        let book = self.cached(key_name, client).await?;
+        Some(book.read().unwrap().balances())
+    }
+}
+
+async fn resync(rate_limiter: &RateLimiter, key_name: &str, client: &RestApi, book: &Book) {
+    match snapshot(rate_limiter, key_name, client).await {
+        Ok(snapshot) => *book.write().unwrap() = snapshot,
+        Err(e) => warn!("account cache: {} resync: {}", key_name, e),
+    }
+}
+
+// 应用用户数据流事件, 用户数据流关闭后移除缓存, 下次读取时重新初始化
+async fn sync(
+    key_name: String,
+    client: RestApi,
+    rate_limiter: Arc<RateLimiter>,
+    mut events: broadcast::Receiver<String>,
+    book: Book,
+    entry: Arc<Entry>,
+    entries: Arc<Mutex<HashMap<String, Arc<Entry>>>>,
+) {
+    let mut check = tokio::time::interval_at(Instant::now() + RESYNC_INTERVAL, RESYNC_INTERVAL);
+
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                // 上游重连期间可能丢失了事件, 重新取快照
+                Ok(msg) if is_reconnected(&msg) => {
+                    info!("account cache: {} user data stream reconnected", key_name);
+                    resync(&rate_limiter, &key_name, &client, &book).await;
+                }
+                Ok(msg) => book.write().unwrap().apply(&msg),
+                // 丢失了事件, 重新取快照
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("account cache: {} lagged, {} events skipped", key_name, skipped);
+                    resync(&rate_limiter, &key_name, &client, &book).await;
+                }
+                Err(RecvError::Closed) => break,
+            },
+            _ = check.tick() => resync(&rate_limiter, &key_name, &client, &book).await,
+        }
+    }
+
+    let mut entries = entries.lock().await;
+    if entries
+        .get(&key_name)
+        .is_some_and(|e| Arc::ptr_eq(e, &entry))
+    {
+        entries.remove(&key_name);
+    }
+    info!("account cache: {} dropped", key_name);
+}
+
+#[cfg(test)]
+mod tests {
+    use binance_sdk::config::ConfigurationRestApi;
+    use binance_sdk::derivatives_trading_usds_futures::DerivativesTradingUsdsFuturesRestApi;
+    use serde_json::json;
+
+    use super::*;
+
+    fn snapshot_book() -> AccountBook {
+        let open_orders = serde_json::from_value(json!([
+            {"orderId": 1, "symbol": "BTCUSDT", "status": "NEW", "updateTime": 100},
+            {"orderId": 2, "symbol": "ETHUSDT", "status": "NEW", "updateTime": 100}
+        ]))
+        .unwrap();
+        let positions = serde_json::from_value(json!([
+            {"symbol": "BTCUSDT", "positionSide": "BOTH", "positionAmt": "0.01",
+             "markPrice": "60000", "updateTime": 100}
+        ]))
+        .unwrap();
+        let balances = serde_json::from_value(json!([
+            {"asset": "USDT", "balance": "100", "availableBalance": "90", "updateTime": 100}
+        ]))
+        .unwrap();
+        AccountBook::from_snapshot(open_orders, positions, balances)
+    }
+
+    fn order_update(order_id: i64, status: &str, time: i64) -> String {
+        json!({
+            "e": "ORDER_TRADE_UPDATE", "E": time, "T": time,
+            "o": {
+                "s": "BTCUSDT", "c": "c1", "S": "BUY", "o": "LIMIT", "f": "GTC",
+                "q": "0.02", "p": "50000", "ap": "50000", "sp": "0", "x": "TRADE",
+                "X": status, "i": order_id, "l": "0.01", "z": "0.01", "L": "50000",
+                "T": time, "t": 1, "R": false, "wt": "CONTRACT_PRICE", "ot": "LIMIT",
+                "ps": "BOTH", "cp": false, "pP": false, "V": "NONE", "pm": "NONE", "gtd": 0
+            }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_apply_order_update() {
+        let mut book = snapshot_book();
+
+        book.apply(&order_update(3, "PARTIALLY_FILLED", 200));
+        let orders = book.open_orders(Some("BTCUSDT"));
+        assert_eq!(orders.len(), 2);
+        assert_eq!(orders[1].cum_quote.as_deref(), Some("500"));
+        assert_eq!(orders[1].time, Some(200));
+
+        book.apply(&order_update(1, "FILLED", 200));
+        // 早于当前数据的事件不生效
+        book.apply(&order_update(3, "NEW", 150));
+        let orders = book.open_orders(None);
+        assert_eq!(
+            orders.iter().map(|o| o.order_id).collect::<Vec<_>>(),
+            vec![Some(2), Some(3)]
+        );
+        assert_eq!(orders[1].status.as_deref(), Some("PARTIALLY_FILLED"));
+    }
+
+    #[test]
+    fn test_apply_account_update() {
+        let mut book = snapshot_book();
+        // 快照中随标记价格变化的字段不保留
+        assert!(book.positions()[0].mark_price.is_none());
+        assert!(book.balances()[0].available_balance.is_none());
+
+        book.apply(
+            &json!({
+                "e": "ACCOUNT_UPDATE", "E": 200, "T": 200,
+                "a": {
+                    "m": "ORDER",
+                    "B": [{"a": "USDT", "wb": "110", "cw": "110", "bc": "0"}],
+                    "P": [
+                        {"s": "BTCUSDT", "pa": "0", "ep": "0", "bep": "0", "cr": "0",
+                         "up": "0", "mt": "cross", "iw": "0", "ps": "BOTH"},
+                        {"s": "ETHUSDT", "pa": "-1", "ep": "3000", "bep": "3001", "cr": "0",
+                         "up": "-5", "mt": "cross", "iw": "0", "ps": "BOTH"}
+                    ]
+                }
+            })
+            .to_string(),
+        );
+
+        let balances = book.balances();
+        assert_eq!(balances[0].balance.as_deref(), Some("110"));
+        // 事件中没有的字段不返回快照中过期的值
+        assert!(balances[0].available_balance.is_none());
+
+        let positions = book.positions();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].symbol.as_deref(), Some("ETHUSDT"));
+        assert_eq!(positions[0].position_amt.as_deref(), Some("-1"));
+        assert_eq!(positions[0].entry_price.as_deref(), Some("3000"));
+        assert!(positions[0].un_realized_profit.is_none());
+
+        // MARGIN_CALL 等事件不影响缓存
+        book.apply(r#"{"e":"MARGIN_CALL","E":300,"cw":"1","p":[]}"#);
+        assert_eq!(book.positions().len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_failure_backoff() {
+        let client = DerivativesTradingUsdsFuturesRestApi::production(
+            ConfigurationRestApi::builder().build().unwrap(),
+        );
+        let rate_limiter = Arc::new(RateLimiter::new(
+            &HashMap::new(),
+            &crate::config::RateLimitConfig::default(),
+        ));
+        let user_streams = UserDataStreams::new(&HashMap::new(), rate_limiter.clone()).unwrap();
+        let cache = AccountCache::new(Arc::new(user_streams), rate_limiter);
+
+        // 没有用户数据流的 key 初始化失败, 退避期间不再重试
+        let err = cache.book("mock1", &client).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        let err = cache.book("mock1", &client).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(cache.positions("mock1", &client).await.is_none());
+    }
+}
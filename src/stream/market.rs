@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::http::StatusCode;
+use binance_sdk::common::websocket::Subscription;
+use binance_sdk::config::ConfigurationWebsocketStreams;
+use binance_sdk::derivatives_trading_usds_futures::{
+    DerivativesTradingUsdsFuturesWsStreams, websocket_streams as usds_future_streams,
+};
+use binance_sdk::models::{WebsocketEvent, WebsocketStreamsConnectConfig};
+use binance_sdk::spot::{SpotWsStreams, websocket_streams as spot_streams};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::{Mutex, broadcast, mpsc};
+use tracing::{error, info, warn};
+
+use super::{Senders, idle_check, remove_if_idle};
+use crate::handler::common::ApiError;
+
+// 检查本地订阅者的间隔, 没有订阅者时关闭上游连接
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+const CHANNEL_CAPACITY: usize = 1024;
+
+const USDS_FUTURE_KLINE_INTERVALS: [&str; 15] = [
+    "1m", "3m", "5m", "15m", "30m", "1h", "2h", "4h", "6h", "8h", "12h", "1d", "3d", "1w", "1M",
+];
+const SPOT_KLINE_INTERVALS: [&str; 16] = [
+    "1s", "1m", "3m", "5m", "15m", "30m", "1h", "2h", "4h", "6h", "8h", "12h", "1d", "3d", "1w",
+    "1M",
+];
+// 深度推送频率, 不带后缀时为币安的默认频率
+const USDS_FUTURE_DEPTH_SPEEDS: [&str; 3] = ["100ms", "250ms", "500ms"];
+const SPOT_DEPTH_SPEEDS: [&str; 2] = ["100ms", "1000ms"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Product {
+    #[default]
+    UsdsFuture,
+    Spot,
+}
+
+impl Product {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UsdsFuture => "usds_future",
+            Self::Spot => "spot",
+        }
+    }
+
+    pub fn kline_intervals(&self) -> &'static [&'static str] {
+        match self {
+            Self::UsdsFuture => &USDS_FUTURE_KLINE_INTERVALS,
+            Self::Spot => &SPOT_KLINE_INTERVALS,
+        }
+    }
+
+    fn depth_speeds(&self) -> &'static [&'static str] {
+        match self {
+            Self::UsdsFuture => &USDS_FUTURE_DEPTH_SPEEDS,
+            Self::Spot => &SPOT_DEPTH_SPEEDS,
+        }
+    }
+}
+
+/// 校验并规范化行情流名称, 支持的流:
+/// - `<symbol>@aggTrade`
+/// - `<symbol>@bookTicker`
+/// - `<symbol>@kline_<interval>`
+/// - `<symbol>@depth[5|10|20][@<speed>]`
+/// - `<symbol>@markPrice[@1s]` (只有 U 本位合约)
+///
+/// symbol 统一转为小写, 不支持的流返回 None
+pub fn normalize_stream(product: Product, stream: &str) -> Option<String> {
+    let (symbol, name) = stream.split_once('@')?;
+    if symbol.is_empty() || !symbol.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+
+    let valid = match name {
+        "aggTrade" | "bookTicker" => true,
+        "markPrice" | "markPrice@1s" => product == Product::UsdsFuture,
+        _ => {
+            if let Some(interval) = name.strip_prefix("kline_") {
+                product.kline_intervals().contains(&interval)
+            } else if let Some(depth) = name.strip_prefix("depth") {
+                let (levels, speed) = match depth.split_once('@') {
+                    Some((levels, speed)) => (levels, Some(speed)),
+                    None => (depth, None),
+                };
+                ["", "5", "10", "20"].contains(&levels)
+                    && speed.is_none_or(|speed| product.depth_speeds().contains(&speed))
+            } else {
+                false
+            }
+        }
+    };
+
+    valid.then(|| format!("{}@{}", symbol.to_ascii_lowercase(), name))
+}
+
+// 只转发行情数据, 过滤订阅响应等消息
+fn is_stream_message(msg: &str) -> bool {
+    serde_json::from_str::<Value>(msg)
+        .is_ok_and(|value| value.get("stream").is_some() && value.get("data").is_some())
+}
+
+// SDK 中现货和合约的行情流是不同的类型
+enum Upstream {
+    UsdsFuture(usds_future_streams::WebsocketStreams),
+    Spot(spot_streams::WebsocketStreams),
+}
+
+impl Upstream {
+    fn on_message(&self, tx: mpsc::UnboundedSender<String>) -> Subscription {
+        let callback = move |event| {
+            if let WebsocketEvent::Message(msg) = event {
+                let _ = tx.send(msg);
+            }
+        };
+        match self {
+            Self::UsdsFuture(streams) => streams.subscribe_on_ws_events(callback),
+            Self::Spot(streams) => streams.subscribe_on_ws_events(callback),
+        }
+    }
+
+    async fn disconnect(&self) -> anyhow::Result<()> {
+        match self {
+            Self::UsdsFuture(streams) => streams.disconnect().await,
+            Self::Spot(streams) => streams.disconnect().await,
+        }
+    }
+}
+
+/// 公开行情流
+/// 每条行情流最多一条上游连接, 由所有本地订阅者共享, 最后一个订阅者断开后关闭
+pub struct MarketStreams {
+    usds_future: usds_future_streams::WebsocketStreamsHandle,
+    spot: spot_streams::WebsocketStreamsHandle,
+    senders: Senders,
+}
+
+impl MarketStreams {
+    pub fn new() -> Result<Self, std::io::Error> {
+        let ws_conf = || {
+            ConfigurationWebsocketStreams::builder()
+                .build()
+                .map_err(|_| {
+                    std::io::Error::other("Failed to build WebSocket streams configuration")
+                })
+        };
+
+        Ok(MarketStreams {
+            usds_future: DerivativesTradingUsdsFuturesWsStreams::production(ws_conf()?),
+            spot: SpotWsStreams::production(ws_conf()?),
+            senders: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    async fn connect(&self, product: Product, stream: &str) -> anyhow::Result<Upstream> {
+        let config = WebsocketStreamsConnectConfig {
+            streams: vec![stream.to_string()],
+            mode: None,
+        };
+        Ok(match product {
+            Product::UsdsFuture => {
+                Upstream::UsdsFuture(self.usds_future.connect_with_config(config).await?)
+            }
+            Product::Spot => Upstream::Spot(self.spot.connect_with_config(config).await?),
+        })
+    }
+
+    /// 订阅行情流, stream 需要先经过 `normalize_stream` 校验
+    pub async fn subscribe(
+        &self,
+        product: Product,
+        stream: &str,
+    ) -> Result<broadcast::Receiver<String>, ApiError> {
+        let name = format!("{}:{}", product.as_str(), stream);
+        super::subscribe(&self.senders, &name, || self.start(product, stream, &name)).await
+    }
+
+    // 建立上游连接并启动转发任务
+    async fn start(
+        &self,
+        product: Product,
+        stream: &str,
+        name: &str,
+    ) -> Result<broadcast::Sender<String>, ApiError> {
+        let upstream = self.connect(product, stream).await.map_err(|e| {
+            error!("market stream: {} connect: {}", name, e);
+            ApiError::gateway(
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to connect market stream {}: {}", stream, e),
+            )
+        })?;
+
+        // SDK 的回调是同步的, 通过 channel 交给后台任务处理
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let subscription = upstream.on_message(event_tx);
+
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        info!("market stream: {} connected", name);
+
+        let name = name.to_string();
+        let task_sender = sender.clone();
+        let senders = self.senders.clone();
+        tokio::spawn(async move {
+            run(&name, event_rx, &task_sender, &senders).await;
+            subscription.unsubscribe();
+            if let Err(e) = upstream.disconnect().await {
+                warn!("market stream: {} disconnect: {}", name, e);
+            }
+            info!("market stream: {} closed", name);
+        });
+
+        Ok(sender)
+    }
+}
+
+async fn run(
+    name: &str,
+    mut events: mpsc::UnboundedReceiver<String>,
+    sender: &broadcast::Sender<String>,
+    senders: &Senders,
+) {
+    let mut check = idle_check(IDLE_CHECK_INTERVAL);
+
+    loop {
+        tokio::select! {
+            Some(msg) = events.recv() => {
+                if is_stream_message(&msg) {
+                    let _ = sender.send(msg);
+                }
+            }
+            _ = check.tick() => {
+                if remove_if_idle(senders, name, sender).await {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_stream() {
+        let usds_future = Product::UsdsFuture;
+        let spot = Product::Spot;
+
+        assert_eq!(
+            normalize_stream(usds_future, "BTCUSDT@aggTrade").as_deref(),
+            Some("btcusdt@aggTrade")
+        );
+        assert!(normalize_stream(usds_future, "btcusdt@kline_1m").is_some());
+        assert!(normalize_stream(usds_future, "btcusdt@kline_1s").is_none());
+        assert!(normalize_stream(spot, "btcusdt@kline_1s").is_some());
+        assert!(normalize_stream(usds_future, "btcusdt@depth").is_some());
+        assert!(normalize_stream(usds_future, "btcusdt@depth20@100ms").is_some());
+        assert!(normalize_stream(usds_future, "btcusdt@depth15").is_none());
+        assert!(normalize_stream(spot, "btcusdt@depth@1000ms").is_some());
+        assert!(normalize_stream(spot, "btcusdt@depth@250ms").is_none());
+        assert!(normalize_stream(usds_future, "btcusdt@markPrice@1s").is_some());
+        assert!(normalize_stream(spot, "btcusdt@markPrice").is_none());
+        assert!(normalize_stream(usds_future, "btcusdt@trade").is_none());
+        assert!(normalize_stream(usds_future, "btc/usdt@aggTrade").is_none());
+        assert!(normalize_stream(usds_future, "btcusdt").is_none());
+    }
+
+    #[test]
+    fn test_is_stream_message() {
+        assert!(is_stream_message(
+            r#"{"stream":"btcusdt@aggTrade","data":{"e":"aggTrade"}}"#
+        ));
+        assert!(!is_stream_message(r#"{"result":null,"id":1}"#));
+    }
+}
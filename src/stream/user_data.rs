@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use actix_web::http::StatusCode;
+use binance_sdk::config::ConfigurationWebsocketStreams;
+use binance_sdk::derivatives_trading_usds_futures::DerivativesTradingUsdsFuturesWsStreams;
+use binance_sdk::derivatives_trading_usds_futures::rest_api::RestApi;
+use binance_sdk::derivatives_trading_usds_futures::websocket_streams::{
+    WebsocketStreams, WebsocketStreamsHandle,
+};
+use binance_sdk::models::{WebsocketEvent, WebsocketStreamsConnectConfig};
+use serde_json::{Value, json};
+use tokio::sync::{Mutex, broadcast, mpsc};
+use tracing::{error, info, warn};
+
+use super::market::Product;
+use super::{Senders, idle_check, remove_if_idle};
+use crate::app::{Environment, Key};
+use crate::common::paginate::now_ms;
+use crate::common::rate_limit::{Cost, RateLimiter};
+use crate::handler::common::ApiError;
+
+// listenKey 60 分钟未续期会失效, 每 30 分钟续期一次
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30 * 60);
+// 检查本地订阅者, 续期和重建 listenKey 的间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const CHANNEL_CAPACITY: usize = 1024;
+// 转发给本地客户端的事件类型
+const RELAY_EVENTS: [&str; 3] = ["ORDER_TRADE_UPDATE", "ACCOUNT_UPDATE", "MARGIN_CALL"];
+// 上游断线重连后发给本地订阅者的事件, 期间的事件可能丢失, 订阅者需要重新查询
+const RECONNECTED_EVENT: &str = "STREAM_RECONNECTED";
+// listenKey 的创建, 续期和关闭都占用该 key 的请求权重
+const LISTEN_KEY_COST: Cost = Cost {
+    product: Product::UsdsFuture,
+    weight: 1,
+    orders: 0,
+};
+
+/// 是否为网关发出的重连事件
+pub fn is_reconnected(msg: &str) -> bool {
+    serde_json::from_str::<Value>(msg)
+        .is_ok_and(|value| value.get("e").and_then(Value::as_str) == Some(RECONNECTED_EVENT))
+}
+
+// SDK 回调转交给后台任务的事件
+enum StreamEvent {
+    Message(String),
+    Disconnected,
+    Connected,
+}
+
+#[derive(Debug, PartialEq)]
+enum UserEvent {
+    Relay(String),
+    Expired,
+    Ignore,
+}
+
+// 组合流的消息形如 {"stream": "<listenKey>", "data": {...}}, 只转发 data, 不暴露 listenKey
+fn parse_event(msg: &str) -> UserEvent {
+    let Ok(mut value) = serde_json::from_str::<Value>(msg) else {
+        return UserEvent::Ignore;
+    };
+    let data = match value.get_mut("data") {
+        Some(data) => data.take(),
+        None => value,
+    };
+    match data.get("e").and_then(Value::as_str) {
+        Some("listenKeyExpired") => UserEvent::Expired,
+        Some(event) if RELAY_EVENTS.contains(&event) => UserEvent::Relay(data.to_string()),
+        _ => UserEvent::Ignore,
+    }
+}
+
+async fn start_listen_key(
+    rate_limiter: &RateLimiter,
+    key_name: &str,
+    client: &RestApi,
+) -> Result<String, ApiError> {
+    let response = rate_limiter
+        .call(Some(key_name), LISTEN_KEY_COST, async || {
+            client.start_user_data_stream().await
+        })
+        .await
+        .inspect_err(|e| error!("start_user_data_stream: {}", e))?;
+
+    let data = response.data().await.map_err(|e| {
+        error!("Failed to get data from response: {}", e);
+        ApiError::from(e)
+    })?;
+
+    data.listen_key
+        .ok_or_else(|| ApiError::gateway(StatusCode::BAD_GATEWAY, "Binance returned no listenKey"))
+}
+
+/// U 本位合约用户数据流
+/// 每个 key 最多一条上游连接, 由该 key 的所有本地订阅者共享
+///
+/// listenKey 按需创建: 第一个本地订阅者 (/ws 客户端或账户缓存) 订阅时创建并连接,
+/// 最后一个订阅者断开后, 下一次检查 (最多 CHECK_INTERVAL) 时关闭 listenKey 和连接.
+/// 不常驻的原因是 listenKey 需要每 30 分钟续期, 且每个 key 同时只有一个有效的 listenKey,
+/// 没有订阅者时保持连接只会占用权重; 账户缓存读取过的 key 会一直订阅, 连接随之常驻
+pub struct UserDataStreams {
+    handles: HashMap<String, WebsocketStreamsHandle>,
+    senders: Senders,
+    rate_limiter: Arc<RateLimiter>,
+}
+
+impl UserDataStreams {
+    // 配置了 base_url 的 key 没有对应的 WebSocket 地址, 不支持用户数据流
+    pub fn new(
+        keys: &HashMap<String, Key>,
+        rate_limiter: Arc<RateLimiter>,
+    ) -> Result<Self, std::io::Error> {
+        let mut handles = HashMap::new();
+        for (key_name, key) in keys.iter() {
+            if key.base_url.is_some() {
+                continue;
+            }
+
+            let ws_conf = ConfigurationWebsocketStreams::builder()
+                .build()
+                .map_err(|_| {
+                    std::io::Error::other("Failed to build WebSocket streams configuration")
+                })?;
+            let handle = match key.environment {
+                Environment::Production => {
+                    DerivativesTradingUsdsFuturesWsStreams::production(ws_conf)
+                }
+                Environment::Testnet => DerivativesTradingUsdsFuturesWsStreams::testnet(ws_conf),
+            };
+            handles.insert(key_name.clone(), handle);
+        }
+
+        Ok(UserDataStreams {
+            handles,
+            senders: Arc::new(Mutex::new(HashMap::new())),
+            rate_limiter,
+        })
+    }
+
+    /// 订阅 key 的用户数据流, 没有上游连接时创建 listenKey 并连接
+    pub async fn subscribe(
+        &self,
+        key_name: &str,
+        client: RestApi,
+    ) -> Result<broadcast::Receiver<String>, ApiError> {
+        let handle = self.handles.get(key_name).ok_or_else(|| {
+            ApiError::bad_request(format!(
+                "Key {} has no user data stream, keys with base_url are not supported",
+                key_name
+            ))
+        })?;
+
+        super::subscribe(&self.senders, key_name, || {
+            self.start(key_name, handle, client.clone())
+        })
+        .await
+    }
+
+    // 创建 listenKey, 建立上游连接并启动转发任务
+    async fn start(
+        &self,
+        key_name: &str,
+        handle: &WebsocketStreamsHandle,
+        client: RestApi,
+    ) -> Result<broadcast::Sender<String>, ApiError> {
+        let listen_key = start_listen_key(&self.rate_limiter, key_name, &client).await?;
+        let config = WebsocketStreamsConnectConfig {
+            streams: vec![listen_key.clone()],
+            mode: None,
+        };
+        let streams = handle.connect_with_config(config).await.map_err(|e| {
+            error!("user data stream: {} connect: {}", key_name, e);
+            ApiError::gateway(
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to connect user data stream: {}", e),
+            )
+        })?;
+
+        // SDK 的回调是同步的, 通过 channel 交给后台任务处理
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let subscription = streams.subscribe_on_ws_events(move |event| {
+            let event = match event {
+                WebsocketEvent::Message(msg) => StreamEvent::Message(msg),
+                WebsocketEvent::Close(..) | WebsocketEvent::Error(_) => StreamEvent::Disconnected,
+                WebsocketEvent::Open => StreamEvent::Connected,
+                _ => return,
+            };
+            let _ = event_tx.send(event);
+        });
+
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        info!("user data stream: {} connected", key_name);
+
+        let stream = UserDataStream {
+            key_name: key_name.to_string(),
+            client,
+            rate_limiter: self.rate_limiter.clone(),
+            streams,
+            listen_key,
+            last_keepalive: Instant::now(),
+            sender: sender.clone(),
+            senders: self.senders.clone(),
+        };
+        tokio::spawn(async move {
+            stream.run(event_rx).await;
+            subscription.unsubscribe();
+        });
+
+        Ok(sender)
+    }
+}
+
+// 单个 key 的上游连接, 负责续期, 重建 listenKey 和转发事件
+struct UserDataStream {
+    key_name: String,
+    client: RestApi,
+    rate_limiter: Arc<RateLimiter>,
+    streams: WebsocketStreams,
+    listen_key: String,
+    // listenKey 创建或最近一次续期的时间
+    last_keepalive: Instant,
+    sender: broadcast::Sender<String>,
+    senders: Senders,
+}
+
+impl UserDataStream {
+    async fn run(mut self, mut events: mpsc::UnboundedReceiver<StreamEvent>) {
+        let mut check = idle_check(CHECK_INTERVAL);
+        let mut renew_pending = false;
+        let mut disconnected = false;
+
+        loop {
+            tokio::select! {
+                Some(event) = events.recv() => match event {
+                    StreamEvent::Message(msg) => match parse_event(&msg) {
+                        UserEvent::Relay(data) => {
+                            let _ = self.sender.send(data);
+                        }
+                        UserEvent::Expired => {
+                            warn!("user data stream: {} listenKey expired", self.key_name);
+                            renew_pending = !self.renew().await;
+                        }
+                        UserEvent::Ignore => {}
+                    },
+                    StreamEvent::Disconnected => disconnected = true,
+                    // SDK 自动重连成功, 通知订阅者重新查询
+                    StreamEvent::Connected if disconnected => {
+                        disconnected = false;
+                        info!("user data stream: {} reconnected", self.key_name);
+                        let event = json!({ "e": RECONNECTED_EVENT, "E": now_ms() });
+                        let _ = self.sender.send(event.to_string());
+                    }
+                    StreamEvent::Connected => {}
+                },
+                _ = check.tick() => {
+                    if remove_if_idle(&self.senders, &self.key_name, &self.sender).await {
+                        break;
+                    }
+                    if renew_pending {
+                        renew_pending = !self.renew().await;
+                    } else if self.last_keepalive.elapsed() >= KEEPALIVE_INTERVAL {
+                        self.last_keepalive = Instant::now();
+                        let keepalive = self
+                            .rate_limiter
+                            .call(Some(&self.key_name), LISTEN_KEY_COST, async || {
+                                self.client.keepalive_user_data_stream().await
+                            })
+                            .await;
+                        if let Err(e) = keepalive {
+                            warn!("user data stream: {} keepalive: {}", self.key_name, e);
+                            renew_pending = !self.renew().await;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.close().await;
+    }
+
+    // 重新创建 listenKey, 与当前不同时切换订阅
+    async fn renew(&mut self) -> bool {
+        match start_listen_key(&self.rate_limiter, &self.key_name, &self.client).await {
+            Ok(listen_key) => {
+                // 创建 listenKey 同时会延长有效期, 重新计算续期时间
+                self.last_keepalive = Instant::now();
+                if listen_key != self.listen_key {
+                    info!("user data stream: {} listenKey recreated", self.key_name);
+                    self.streams
+                        .unsubscribe(vec![std::mem::take(&mut self.listen_key)], None);
+                    self.streams.subscribe(vec![listen_key.clone()], None);
+                    self.listen_key = listen_key;
+                }
+                true
+            }
+            Err(e) => {
+                error!("user data stream: {} renew: {}", self.key_name, e);
+                false
+            }
+        }
+    }
+
+    async fn close(self) {
+        let close = self
+            .rate_limiter
+            .call(Some(&self.key_name), LISTEN_KEY_COST, async || {
+                self.client.close_user_data_stream().await
+            })
+            .await;
+        if let Err(e) = close {
+            warn!("user data stream: {} close listenKey: {}", self.key_name, e);
+        }
+        if let Err(e) = self.streams.disconnect().await {
+            warn!("user data stream: {} disconnect: {}", self.key_name, e);
+        }
+        info!("user data stream: {} closed", self.key_name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_event() {
+        let msg = r#"{"stream":"abc","data":{"e":"ORDER_TRADE_UPDATE","T":1,"o":{"s":"BTCUSDT"}}}"#;
+        match parse_event(msg) {
+            UserEvent::Relay(data) => {
+                let data: Value = serde_json::from_str(&data).unwrap();
+                assert_eq!(data["o"]["s"], "BTCUSDT");
+                assert!(data.get("stream").is_none());
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+
+        let msg = r#"{"stream":"abc","data":{"e":"listenKeyExpired","listenKey":"abc"}}"#;
+        assert_eq!(parse_event(msg), UserEvent::Expired);
+
+        // 订阅响应和其他事件不转发
+        assert_eq!(parse_event(r#"{"result":null,"id":1}"#), UserEvent::Ignore);
+        let msg = r#"{"stream":"abc","data":{"e":"ACCOUNT_CONFIG_UPDATE"}}"#;
+        assert_eq!(parse_event(msg), UserEvent::Ignore);
+
+        assert!(is_reconnected(r#"{"e":"STREAM_RECONNECTED","E":1}"#));
+        assert!(!is_reconnected(r#"{"e":"ACCOUNT_UPDATE","E":1}"#));
+    }
+}